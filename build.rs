@@ -42,4 +42,20 @@ fn main() {
             "1.0", "core", &mut file
         ).unwrap();
     }
+
+    if target.contains("linux") {
+        let mut file = File::create(&out.join(Path::new("egl.rs"))).unwrap();
+        gl_generator::generate_bindings(
+            gl_generator::StaticGenerator,
+            gl_generator::registry::Ns::Egl,
+            gl_generator::Fallbacks::All,
+            khronos_api::EGL_XML,
+            vec![
+                "EGL_KHR_create_context".to_string(),
+                "EGL_KHR_platform_x11".to_string(),
+                "EGL_KHR_surfaceless_context".to_string()
+            ],
+            "1.5", "core", &mut file
+        ).unwrap();
+    }
 }
\ No newline at end of file
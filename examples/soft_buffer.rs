@@ -0,0 +1,34 @@
+extern crate tub;
+
+use tub::platform;
+use tub::config::WindowConfig;
+use tub::event::Event;
+
+fn main() {
+    let events_loop = platform::EventsLoop::new().unwrap();
+    let window = platform::Window::new(
+        &events_loop,
+        WindowConfig::new()
+            .name("Software rendering".to_owned())
+            .size(Some((640, 480))),
+        Default::default()).unwrap();
+    let mut buffer = platform::SoftBuffer::new(&window).unwrap();
+
+    window.show();
+    for (_, event) in events_loop.wait_events() {
+        match event {
+            Event::CloseRequested => break,
+            _                     => ()
+        }
+
+        // Draw a gradient that stretches to fill the window
+        let (width, height) = buffer.get_size();
+        for (i, pixel) in buffer.pixels_mut().iter_mut().enumerate() {
+            let x = (i as u32 % width) * 255 / width;
+            let y = (i as u32 / width) * 255 / height;
+
+            *pixel = 0xFF000000 | (x << 16) | (y << 8) | 0x80;
+        }
+        buffer.present().unwrap();
+    }
+}
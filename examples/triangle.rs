@@ -7,7 +7,7 @@ extern crate gfx_device_gl;
 use gfx_core::{format, handle, tex};
 use gfx_device_gl::Resources as R;
 
-use tub::platform::Window;
+use tub::platform::{Window, EventsLoop};
 
 gfx_vertex_struct!( Vertex {
     pos: [f32; 2] = "a_Pos",
@@ -22,7 +22,9 @@ gfx_pipeline!(pipe {
 fn main() {
     use gfx::traits::{Device, FactoryExt};
 
+    let events_loop = EventsLoop::new().unwrap();
     let window = init_window::<gfx::format::Srgba8, gfx::format::Depth>(
+        &events_loop,
         tub::config::WindowConfig::new()
             .name("Triangle".to_owned())
             .size(Some((500, 500)))
@@ -56,10 +58,10 @@ fn main() {
     window.show();
     'main: loop {
         // quit when Esc is pressed.
-        for event in window.poll_events() {
+        for (_, event) in events_loop.poll_events() {
             match event {
-                tub::event::Event::KeyInput(_, tub::event::VKeyCode::Escape) |
-                tub::event::Event::Closed => break 'main,
+                tub::event::Event::KeyInput(_, Some(tub::event::Key::Escape), _, _) |
+                tub::event::Event::CloseRequested => break 'main,
                 _ => {},
             }
         }
@@ -98,7 +100,7 @@ const TRIANGLE_VERT: &'static str = r#"
     }
 "#;
 
-fn init_window<'w, Cf, Df>(window_config: tub::config::WindowConfig, pixel_format: tub::config::PixelFormat) -> tub::platform::Window<'w>
+fn init_window<'w, Cf, Df>(events_loop: &EventsLoop, window_config: tub::config::WindowConfig, pixel_format: tub::config::PixelFormat) -> tub::platform::Window<'w>
 where
     Cf: format::RenderFormat,
     Df: format::DepthFormat,
@@ -120,7 +122,7 @@ where
             ..pixel_format
         }
     };
-    tub::platform::Window::new(window_config, pix_format).unwrap()
+    tub::platform::Window::new(events_loop, window_config, pix_format).unwrap()
 }
 
 fn init_context<'w, 'c, Cf, Df>(window: &'w Window) -> 
@@ -2,7 +2,7 @@ extern crate tub;
 
 use tub::platform;
 use tub::config::{WindowConfig};
-use tub::event::{Event, PressState, VKeyCode};
+use tub::event::{Event, PressState, Key};
 use std::path::Path;
 
 fn main() {
@@ -13,7 +13,9 @@ fn main() {
         .. Default::default()
     };
 
+    let events_loop = platform::EventsLoop::new().unwrap();
     let window = platform::Window::new(
+        &events_loop,
         WindowConfig::new()
             .name("It's a window!".to_owned())
             .icon(Some(Path::new("tub.ico").to_path_buf()))
@@ -35,10 +37,37 @@ fn main() {
     window.focus();
     child_window.show();
     loop {
-        for event in window.poll_events() {
+        for (id, event) in events_loop.poll_events() {
+            if id == child_window.id() {
+                println!("child: {:?}", event);
+                continue;
+            }
+
+            if let Some(ref owned) = owned_window {
+                if id == owned.id() {
+                    println!("owned: {:?}", event);
+                    match event {
+                        Event::KeyInput(PressState::Pressed, Some(Key::E), _, _)   => {
+                            owned.set_cursor(tub::CursorType::Crosshair);
+                        }
+
+                        Event::CloseRequested => owned.close(),
+
+                        Event::Destroyed => {
+                            owned.get_type().unwrap().enable();
+                            owned.get_type().unwrap().focus();
+                            reset_owned = true;
+                        }
+
+                        _ => ()
+                    }
+                    continue;
+                }
+            }
+
             println!("parent: {:?}", event);
             match event {
-                Event::KeyInput(PressState::Pressed, VKeyCode::D)   => {
+                Event::KeyInput(PressState::Pressed, Some(Key::D), _, _)   => {
                     match owned_window {
                         None    => { 
                             let owned = window.new_owned(owned_config.clone(), Default::default()).unwrap();
@@ -52,58 +81,34 @@ fn main() {
                     }
                 }
 
-                Event::KeyInput(PressState::Pressed, VKeyCode::E) => {
+                Event::KeyInput(PressState::Pressed, Some(Key::E), _, _) => {
                     window.set_cursor(tub::CursorType::Crosshair);
                 }
 
-                Event::KeyInput(PressState::Pressed, VKeyCode::I) => {
+                Event::KeyInput(PressState::Pressed, Some(Key::I), _, _) => {
                     window.set_cursor(tub::CursorType::Invisible);
                 }
 
-                Event::KeyInput(PressState::Pressed, VKeyCode::G) => {
+                Event::KeyInput(PressState::Pressed, Some(Key::G), _, _) => {
                     window.set_cursor(tub::CursorType::Hand);
                 }
 
-                Event::KeyInput(PressState::Released, VKeyCode::E)|
-                Event::KeyInput(PressState::Released, VKeyCode::I)|
-                Event::KeyInput(PressState::Released, VKeyCode::G) =>{
+                Event::KeyInput(PressState::Released, Some(Key::E), _, _)|
+                Event::KeyInput(PressState::Released, Some(Key::I), _, _)|
+                Event::KeyInput(PressState::Released, Some(Key::G), _, _) =>{
                     window.set_cursor(tub::CursorType::Arrow);
                 }
 
-                Event::KeyInput(PressState::Pressed, VKeyCode::C) => {
+                Event::KeyInput(PressState::Pressed, Some(Key::C), _, _) => {
                     window.set_cursor_pos(250, 250);
                 }
 
-                Event::Closed => return,
+                Event::CloseRequested => return,
 
                 _ => ()
             }
         }
 
-        if let Some(ref owned) = owned_window {
-            for event in owned.poll_events() {
-                println!("owned: {:?}", event);
-                match event {
-                    Event::KeyInput(PressState::Pressed, VKeyCode::E)   => {
-                        owned.set_cursor(tub::CursorType::Crosshair);
-                    }
-
-                    Event::Closed   => {
-                        owned.get_type().unwrap().enable();
-                        owned.get_type().unwrap().focus();
-                        reset_owned = true;
-                    }
-
-                    _ => ()
-                }
-                
-            }
-        }
-
-        for event in child_window.poll_events() {
-            println!("child: {:?}", event);
-        }
-
         if reset_owned {
             owned_window = None;
             reset_owned = false;
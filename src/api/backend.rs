@@ -0,0 +1,249 @@
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT, Ordering};
+use std::sync::mpsc::Sender;
+#[cfg(target_os = "linux")]
+use std::sync::atomic::AtomicBool;
+#[cfg(target_os = "linux")]
+use std::os::unix::io::RawFd;
+#[cfg(target_os = "linux")]
+use std::rc::Rc;
+#[cfg(target_os = "linux")]
+use std::time::Instant;
+
+#[cfg(target_os = "linux")]
+use libc;
+
+use {Cursor, GrabMode, Rect, WindowId};
+use error::TubResult;
+#[cfg(target_os = "linux")]
+use error::TubError;
+use config::{WindowConfig, PixelFormat};
+use event::Event;
+
+#[cfg(all(windows, feature = "win32"))]
+use api::win32;
+#[cfg(all(target_os = "linux", feature = "x11"))]
+use api::x11;
+#[cfg(all(target_os = "linux", feature = "wayland"))]
+use api::wayland;
+#[cfg(feature = "mock")]
+use api::mock;
+
+/// The ID that the next window gets. IDs are unique across all events loops, so mixing them up
+/// can't make an event look like it came from the wrong window.
+static NEXT_WINDOW_ID: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// Where a backend sends a window's events. Every window gets its own sink, which tags events with
+/// the window's ID and hands them to the `EventsLoop` that the window was created on. Sinks can be
+/// sent to other threads, so backends with an event thread can send events straight from it.
+#[derive(Clone)]
+pub struct EventSink {
+    id: WindowId,
+    sender: Sender<(WindowId, Event)>,
+    #[cfg(target_os = "linux")]
+    waker: Arc<Waker>,
+    /// The size from the last `Resized` event that was sent, which soft buffers use to keep their
+    /// size in sync with the window's.
+    size: Arc<Mutex<(u32, u32)>>
+}
+
+impl EventSink {
+    /// Create a sink for a new window, which gets a fresh ID
+    #[cfg(not(target_os = "linux"))]
+    pub fn new(sender: Sender<(WindowId, Event)>) -> EventSink {
+        EventSink {
+            id: WindowId(NEXT_WINDOW_ID.fetch_add(1, Ordering::Relaxed)),
+            sender: sender,
+            size: Arc::new(Mutex::new((0, 0)))
+        }
+    }
+
+    /// Create a sink for a new window, which gets a fresh ID
+    #[cfg(target_os = "linux")]
+    pub fn new(sender: Sender<(WindowId, Event)>, waker: Arc<Waker>) -> EventSink {
+        EventSink {
+            id: WindowId(NEXT_WINDOW_ID.fetch_add(1, Ordering::Relaxed)),
+            sender: sender,
+            waker: waker,
+            size: Arc::new(Mutex::new((0, 0)))
+        }
+    }
+
+    /// Create a sink for a new window that sends its events to the same loop as this one
+    #[cfg(not(target_os = "linux"))]
+    #[inline]
+    pub fn sibling(&self) -> EventSink {
+        EventSink::new(self.sender.clone())
+    }
+
+    /// Create a sink for a new window that sends its events to the same loop as this one
+    #[cfg(target_os = "linux")]
+    #[inline]
+    pub fn sibling(&self) -> EventSink {
+        EventSink::new(self.sender.clone(), self.waker.clone())
+    }
+
+    #[inline]
+    pub fn id(&self) -> WindowId {
+        self.id
+    }
+
+    pub fn send(&self, event: Event) {
+        if let Event::Resized(_, width, height) = event {
+            *self.size.lock().unwrap() = (width, height);
+        }
+
+        // If the loop is gone, there's nobody left to care about the event
+        self.sender.send((self.id, event)).ok();
+
+        #[cfg(target_os = "linux")]
+        self.waker.wake();
+    }
+
+    #[inline]
+    pub fn last_size(&self) -> (u32, u32) {
+        *self.size.lock().unwrap()
+    }
+
+    /// Set the size that `last_size` returns until the backend sends a `Resized` event, if it
+    /// hasn't sent one already
+    pub fn init_size(&self, size: (u32, u32)) {
+        let mut last_size = self.size.lock().unwrap();
+        if *last_size == (0, 0) {
+            *last_size = size;
+        }
+    }
+}
+
+/// A pipe that wakes the `EventsLoop` up while it's waiting on event pumps. The loop can only wait
+/// on either its channel or the pumps' file descriptors, so when it's waiting on the pumps, events
+/// sent from other threads also write to this pipe, which the loop waits on along with the pumps.
+#[cfg(target_os = "linux")]
+pub struct Waker {
+    read_fd: RawFd,
+    write_fd: RawFd,
+    /// Set while the loop is waiting on the pipe, so that events only touch the pipe when the loop
+    /// actually needs to be woken up
+    waiting: AtomicBool
+}
+
+#[cfg(target_os = "linux")]
+impl Waker {
+    pub fn new() -> TubResult<Waker> {
+        let mut fds = [0; 2];
+        if unsafe{ libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC | libc::O_NONBLOCK) } == -1 {
+            return Err(TubError::OsError("Could not create the events loop's wake pipe".to_owned()));
+        }
+
+        Ok(
+            Waker {
+                read_fd: fds[0],
+                write_fd: fds[1],
+                waiting: AtomicBool::new(false)
+            }
+        )
+    }
+
+    /// The end of the pipe that the loop waits on
+    #[inline]
+    pub fn fd(&self) -> RawFd {
+        self.read_fd
+    }
+
+    /// Called by the loop before it waits on the pipe. Anything sent after this wakes it up.
+    #[inline]
+    pub fn start_waiting(&self) {
+        self.waiting.store(true, Ordering::SeqCst);
+    }
+
+    /// Called by the loop once it's done waiting, which empties the pipe out
+    pub fn stop_waiting(&self) {
+        self.waiting.store(false, Ordering::SeqCst);
+
+        let mut buf = [0u8; 64];
+        while unsafe{ libc::read(self.read_fd, buf.as_mut_ptr() as *mut _, buf.len()) } > 0 {}
+    }
+
+    fn wake(&self) {
+        if self.waiting.swap(false, Ordering::SeqCst) {
+            unsafe{ libc::write(self.write_fd, b"\0".as_ptr() as *const _, 1) };
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for Waker {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.read_fd);
+            libc::close(self.write_fd);
+        }
+    }
+}
+
+/// Backends that can't read events on a separate thread (currently only Wayland) give the
+/// `EventsLoop` one of these, which it uses to read events while it's polling or waiting.
+#[cfg(target_os = "linux")]
+pub trait EventPump {
+    /// Read whatever events have arrived and send them to their windows, without blocking
+    fn dispatch_pending(&self);
+    /// A file descriptor that becomes readable when there are events to dispatch
+    fn fd(&self) -> RawFd;
+    /// When the pump has to be run again even if no events arrive, like to repeat a held key
+    fn next_timeout(&self) -> Option<Instant>;
+}
+
+/// The interface that every windowing backend implements. `platform::Window` holds one of these
+/// and dispatches all of its methods through it, which is what lets the backend get picked at
+/// runtime. Top-level windows are created by `platform::Backend`, since there's no window to call
+/// a method on yet. Windows send their events through the `EventSink` that they were created with.
+pub trait WindowBackend {
+    fn new_owned<'a>(&'a self, config: WindowConfig, pixel_format: PixelFormat, sink: EventSink) -> TubResult<Box<WindowBackend + 'a>>;
+    fn new_child<'a>(&'a self, config: WindowConfig, pixel_format: PixelFormat, sink: EventSink) -> TubResult<Box<WindowBackend + 'a>>;
+
+    fn set_title(&self, title: &str);
+    fn show(&self);
+    fn hide(&self);
+    fn close(&self);
+    fn enable(&self);
+    fn disable(&self);
+    fn focus(&self);
+
+    fn get_inner_pos(&self) -> Option<(i32, i32)>;
+    fn get_outer_pos(&self) -> Option<(i32, i32)>;
+    fn get_inner_size(&self) -> Option<(u32, u32)>;
+    fn get_outer_size(&self) -> Option<(u32, u32)>;
+    fn set_pos(&self, x: i32, y: i32) -> Option<()>;
+    fn set_inner_size(&self, x: u32, y: u32) -> Option<()>;
+    fn is_active(&self) -> bool;
+
+    fn set_cursor(&self, cursor: &Cursor);
+    fn set_cursor_pos(&self, x: i32, y: i32);
+    fn set_cursor_grab(&self, mode: GrabMode) -> Option<()>;
+    fn confine_cursor(&self, rect: Option<Rect>) -> Option<()>;
+    fn set_ime_position(&self, x: i32, y: i32);
+
+    /// Get the pump that has to be run for the window to receive events, if the backend needs one
+    #[cfg(target_os = "linux")]
+    fn event_pump(&self) -> Option<Rc<EventPump>> {
+        None
+    }
+
+    fn get_config(&self) -> &WindowConfig;
+    fn get_pixel_format(&self) -> &PixelFormat;
+
+    /// Get the backend-specific window, for the things (like OpenGL contexts) that only work with
+    /// particular backends.
+    fn native<'a>(&'a self) -> NativeWindow<'a>;
+}
+
+pub enum NativeWindow<'a> {
+    #[cfg(all(windows, feature = "win32"))]
+    Win32(&'a win32::Window<'a>),
+    #[cfg(all(target_os = "linux", feature = "x11"))]
+    X11(&'a x11::Window),
+    #[cfg(all(target_os = "linux", feature = "wayland"))]
+    Wayland(&'a wayland::Window),
+    #[cfg(feature = "mock")]
+    Mock(&'a mock::Window<'a>)
+}
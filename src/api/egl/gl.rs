@@ -0,0 +1,21 @@
+#![allow(non_camel_case_types)]
+
+pub mod egl {
+    use std::os::raw::{c_long, c_void};
+
+    // The EGL bindings expect the platform types from eglplatform.h and khrplatform.h to be in scope
+    pub type khronos_utime_nanoseconds_t = u64;
+    pub type khronos_uint64_t = u64;
+    pub type khronos_ssize_t = c_long;
+    pub type EGLint = i32;
+    pub type EGLNativeDisplayType = *const c_void;
+    pub type EGLNativePixmapType = *const c_void;
+    pub type EGLNativeWindowType = *const c_void;
+    pub type NativeDisplayType = EGLNativeDisplayType;
+    pub type NativePixmapType = EGLNativePixmapType;
+    pub type NativeWindowType = EGLNativeWindowType;
+
+    include!(concat!(env!("OUT_DIR"), "/egl.rs"));
+}
+
+#[link(name="EGL")] extern {}
@@ -0,0 +1,361 @@
+pub mod gl;
+
+use x11::xlib;
+
+use std::ptr;
+use std::mem;
+use std::ffi::{CStr, CString};
+use std::marker::PhantomData;
+use std::os::raw::c_void;
+
+use self::gl::egl;
+use self::gl::egl::types::{EGLDisplay, EGLConfig, EGLContext, EGLSurface, EGLint};
+use api::x11::Window;
+use error::{TubResult, TubError, GlCreationError, GlCreationResult};
+use config::{PixelFormat, ContextConfig, GlApi};
+
+// Constants from EGL extensions that may be missing from older registries
+const PLATFORM_SURFACELESS_MESA: u32 = 0x31DD;
+const COLOR_COMPONENT_TYPE_EXT: EGLint = 0x3339;
+const COLOR_COMPONENT_TYPE_FLOAT_EXT: EGLint = 0x333B;
+const OPENGL_ES3_BIT: EGLint = 0x0040;
+
+/// What an EGL context draws into
+enum SurfaceType {
+    Window(xlib::VisualID),
+    Pbuffer,
+    Surfaceless
+}
+
+pub struct GlContext<'w, 'c> {
+    display: EGLDisplay,
+    /// The surface that the context draws to. For surfaceless contexts, this is `EGL_NO_SURFACE`.
+    surface: EGLSurface,
+    /// A handle to the OpenGL context
+    context: EGLContext,
+    /// Guarantees that this won't live longer than the window that created it, which would
+    /// be very very bad.
+    window_lifetime: PhantomData<&'w ()>,
+    /// Guarantees that this won't live longer than any context that this is sharing resources
+    /// with.
+    shared_lifetime: PhantomData<&'c ()>
+}
+
+impl<'w, 'c> GlContext<'w, 'c> {
+    /// Create a context that draws to an X window
+    pub fn new(window: &'w Window, shared_context: Option<&'c GlContext>, context_config: &ContextConfig) -> GlCreationResult<GlContext<'w, 'c>> {
+        unsafe {
+            let conn = &window.wrapper.1;
+
+            let display = try!(get_display(egl::PLATFORM_X11_KHR, conn.display as *mut c_void));
+
+            // The EGL configuration has to use the same visual that the window was created with
+            let visual_id = {
+                let mut attributes = mem::uninitialized();
+                if xlib::XGetWindowAttributes(conn.display, window.wrapper.0, &mut attributes) == 0 {
+                    return Err(GlCreationError::OsError("XGetWindowAttributes failed".to_owned(), "Could not get window visual".to_owned()));
+                }
+
+                xlib::XVisualIDFromVisual(attributes.visual)
+            };
+
+            let pixel_format = window.get_pixel_format();
+            let config = try!(choose_config(display, pixel_format, context_config, SurfaceType::Window(visual_id)));
+
+            let mut surface_attrs = Vec::new();
+            if let Some(srgb) = pixel_format.srgb {
+                surface_attrs.push(egl::GL_COLORSPACE as EGLint);
+                match srgb {
+                    true  => surface_attrs.push(egl::GL_COLORSPACE_SRGB as EGLint),
+                    false => surface_attrs.push(egl::GL_COLORSPACE_LINEAR as EGLint)
+                }
+            }
+            surface_attrs.push(egl::NONE as EGLint);
+
+            let surface = egl::CreateWindowSurface(display, config, window.wrapper.0 as egl::EGLNativeWindowType, surface_attrs.as_ptr());
+            if surface == ptr::null() {
+                return Err(egl_error("Could not create window surface"));
+            }
+
+            GlContext::with_surface(display, config, surface, shared_context, context_config)
+        }
+    }
+
+    /// Create a context that doesn't need a window or a display server. If `size` is `Some`, the context
+    /// renders to a pbuffer of that size; otherwise, the context has no default framebuffer at all and
+    /// everything has to be rendered into framebuffer objects.
+    pub fn new_headless(size: Option<(u32, u32)>, pixel_format: &PixelFormat, shared_context: Option<&'c GlContext>, context_config: &ContextConfig) -> GlCreationResult<GlContext<'w, 'c>> {
+        unsafe {
+            // Mesa's surfaceless platform doesn't need any kind of display server. If it isn't available,
+            // fall back to the default display, which works on most drivers as long as there's something
+            // for them to connect to.
+            let client_extns = query_string(ptr::null(), egl::EXTENSIONS);
+            let display = match client_extns.contains("EGL_MESA_platform_surfaceless") {
+                true  => try!(get_display(PLATFORM_SURFACELESS_MESA, ptr::null_mut())),
+                false => {
+                    let display = egl::GetDisplay(ptr::null());
+                    if display == ptr::null() || egl::Initialize(display, ptr::null_mut(), ptr::null_mut()) == 0 {
+                        return Err(egl_error("Could not initialize EGL display"));
+                    }
+                    display
+                }
+            };
+
+            let surface_type = match size {
+                Some(_) => SurfaceType::Pbuffer,
+                None => {
+                    if !query_string(display, egl::EXTENSIONS).contains("EGL_KHR_surfaceless_context") {
+                        return Err(GlCreationError::OsError("EGL_KHR_surfaceless_context missing".to_owned(), "Driver does not support surfaceless contexts".to_owned()));
+                    }
+                    SurfaceType::Surfaceless
+                }
+            };
+
+            let config = try!(choose_config(display, pixel_format, context_config, surface_type));
+
+            let surface = match size {
+                Some((width, height)) => {
+                    let surface_attrs = [
+                        egl::WIDTH as EGLint, width as EGLint,
+                        egl::HEIGHT as EGLint, height as EGLint,
+                        egl::NONE as EGLint
+                    ];
+
+                    let surface = egl::CreatePbufferSurface(display, config, surface_attrs.as_ptr());
+                    if surface == ptr::null() {
+                        return Err(egl_error("Could not create pbuffer surface"));
+                    }
+                    surface
+                }
+
+                None => ptr::null()
+            };
+
+            GlContext::with_surface(display, config, surface, shared_context, context_config)
+        }
+    }
+
+    unsafe fn with_surface(display: EGLDisplay, config: EGLConfig, surface: EGLSurface, shared_context: Option<&'c GlContext>, context_config: &ContextConfig) -> GlCreationResult<GlContext<'w, 'c>> {
+        let api = match context_config.api {
+            GlApi::Gl   => egl::OPENGL_API,
+            GlApi::Gles => egl::OPENGL_ES_API
+        };
+        if egl::BindAPI(api) == 0 {
+            if surface != ptr::null() {
+                egl::DestroySurface(display, surface);
+            }
+            return Err(GlCreationError::UnsupportedApiError);
+        }
+
+        let mut context_attrs = Vec::new();
+        match (context_config.api, context_config.version) {
+            (GlApi::Gl, Some((major, minor))) => {
+                context_attrs.push(egl::CONTEXT_MAJOR_VERSION as EGLint);
+                context_attrs.push(major as EGLint);
+                context_attrs.push(egl::CONTEXT_MINOR_VERSION as EGLint);
+                context_attrs.push(minor as EGLint);
+
+                if (major, minor) >= (3, 2) {
+                    context_attrs.push(egl::CONTEXT_OPENGL_PROFILE_MASK as EGLint);
+                    context_attrs.push(egl::CONTEXT_OPENGL_CORE_PROFILE_BIT as EGLint);
+                }
+            }
+
+            (GlApi::Gl, None) => (),
+
+            (GlApi::Gles, version) => {
+                let (major, minor) = version.unwrap_or((2, 0));
+
+                context_attrs.push(egl::CONTEXT_MAJOR_VERSION as EGLint);
+                context_attrs.push(major as EGLint);
+                context_attrs.push(egl::CONTEXT_MINOR_VERSION as EGLint);
+                context_attrs.push(minor as EGLint);
+            }
+        }
+        context_attrs.push(egl::NONE as EGLint);
+
+        let shared_context_ptr =
+            match shared_context {
+                Some(c) => c.context,
+                None    => ptr::null()
+            };
+
+        let context = egl::CreateContext(display, config, shared_context_ptr, context_attrs.as_ptr());
+        if context == ptr::null() {
+            if surface != ptr::null() {
+                egl::DestroySurface(display, surface);
+            }
+            return Err(GlCreationError::ExtendedCreationError);
+        }
+
+        Ok(
+            GlContext {
+                display: display,
+                surface: surface,
+                context: context,
+                window_lifetime: PhantomData,
+                shared_lifetime: PhantomData
+            }
+        )
+    }
+
+    pub unsafe fn make_current(&self) -> TubResult<()> {
+        if egl::MakeCurrent(self.display, self.surface, self.surface, self.context) == 0 {
+            return Err(TubError::OsError(format!("Context Switch Error: EGL error {:#x}", egl::GetError())));
+        }
+        Ok(())
+    }
+
+    pub fn get_proc_address(&self, proc_name: &str) -> *const () {
+        unsafe {
+            let proc_name = CString::new(proc_name.as_bytes()).unwrap();
+            egl::GetProcAddress(proc_name.as_ptr()) as *const ()
+        }
+    }
+
+    pub fn swap_buffers(&self) {
+        unsafe {
+            if self.surface != ptr::null() {
+                egl::SwapBuffers(self.display, self.surface);
+            }
+        }
+    }
+}
+
+impl<'w, 'c> Drop for GlContext<'w, 'c> {
+    fn drop(&mut self) {
+        unsafe {
+            if egl::GetCurrentContext() == self.context {
+                egl::MakeCurrent(self.display, ptr::null(), ptr::null(), ptr::null());
+            }
+
+            egl::DestroyContext(self.display, self.context);
+
+            if self.surface != ptr::null() {
+                egl::DestroySurface(self.display, self.surface);
+            }
+        }
+    }
+}
+
+unsafe fn get_display(platform: u32, native_display: *mut c_void) -> GlCreationResult<EGLDisplay> {
+    let display = egl::GetPlatformDisplay(platform, native_display, ptr::null());
+    if display == ptr::null() {
+        return Err(egl_error("Could not get EGL display"));
+    }
+
+    if egl::Initialize(display, ptr::null_mut(), ptr::null_mut()) == 0 {
+        return Err(egl_error("Could not initialize EGL display"));
+    }
+
+    Ok(display)
+}
+
+unsafe fn choose_config(display: EGLDisplay, pixel_format: &PixelFormat, context_config: &ContextConfig, surface_type: SurfaceType) -> GlCreationResult<EGLConfig> {
+    let extns = query_string(display, egl::EXTENSIONS);
+
+    // The color bits are the total of the red, green and blue bits, so each channel gets a third.
+    let channel_bits = pixel_format.color_bits as EGLint / 3;
+
+    let mut attrs =
+        vec![
+            egl::COLOR_BUFFER_TYPE as EGLint, egl::RGB_BUFFER as EGLint,
+            egl::RED_SIZE as EGLint, channel_bits,
+            egl::GREEN_SIZE as EGLint, channel_bits,
+            egl::BLUE_SIZE as EGLint, channel_bits,
+            egl::ALPHA_SIZE as EGLint, pixel_format.alpha_bits as EGLint,
+            egl::DEPTH_SIZE as EGLint, pixel_format.depth_bits as EGLint,
+            egl::STENCIL_SIZE as EGLint, pixel_format.stencil_bits as EGLint
+        ];
+
+    attrs.push(egl::RENDERABLE_TYPE as EGLint);
+    match (context_config.api, context_config.version) {
+        (GlApi::Gl, _)                     => attrs.push(egl::OPENGL_BIT as EGLint),
+        (GlApi::Gles, Some((major, _))) if major >= 3
+                                           => attrs.push(OPENGL_ES3_BIT),
+        (GlApi::Gles, _)                   => attrs.push(egl::OPENGL_ES2_BIT as EGLint)
+    }
+
+    attrs.push(egl::SURFACE_TYPE as EGLint);
+    match surface_type {
+        SurfaceType::Window(visual_id) => {
+            attrs.push(egl::WINDOW_BIT as EGLint);
+            attrs.push(egl::NATIVE_VISUAL_ID as EGLint);
+            attrs.push(visual_id as EGLint);
+        }
+        SurfaceType::Pbuffer        => attrs.push(egl::PBUFFER_BIT as EGLint),
+        // Surfaceless contexts don't care which surfaces the configuration supports
+        SurfaceType::Surfaceless    => attrs.push(0)
+    }
+
+    if pixel_format.color_buffer_float {
+        if extns.contains("EGL_EXT_pixel_format_float") {
+            attrs.push(COLOR_COMPONENT_TYPE_EXT);
+            attrs.push(COLOR_COMPONENT_TYPE_FLOAT_EXT);
+        }
+        else {
+            return Err(GlCreationError::FloatingBufferError);
+        }
+    }
+
+    // EGL handles sRGB when creating the surface, so it doesn't need to be checked here. Not all drivers
+    // support the sRGB colorspace for window surfaces, though.
+    if pixel_format.srgb == Some(true) && !extns.contains("EGL_KHR_gl_colorspace") {
+        return Err(GlCreationError::SRGBBufferError);
+    }
+
+    if pixel_format.multisampling > 0 {
+        attrs.push(egl::SAMPLE_BUFFERS as EGLint);
+        attrs.push(1);
+        attrs.push(egl::SAMPLES as EGLint);
+        attrs.push(pixel_format.multisampling as EGLint);
+    }
+
+    // EGL has no concept of stereoscopic framebuffers
+    if pixel_format.stereoscopic {
+        return Err(GlCreationError::IndescribableFormatError("EGL does not support stereoscopic framebuffers".to_owned()));
+    }
+
+    if let Some(accel) = pixel_format.hardware_accel {
+        attrs.push(egl::CONFIG_CAVEAT as EGLint);
+        match accel {
+            true  => attrs.push(egl::NONE as EGLint),
+            false => attrs.push(egl::SLOW_CONFIG as EGLint)
+        }
+    }
+
+    // The attributes list must end with EGL_NONE, so this makes it end with EGL_NONE
+    attrs.push(egl::NONE as EGLint);
+
+
+    let mut config = ptr::null();
+    let mut config_count = 0;
+    if egl::ChooseConfig(display, attrs.as_ptr(), &mut config, 1, &mut config_count) == 0 {
+        return Err(egl_error("eglChooseConfig failed"));
+    }
+
+    if config_count == 0 {
+        if pixel_format.multisampling > 0 {
+            return Err(GlCreationError::MSAABufferError);
+        }
+        return Err(GlCreationError::IndescribableFormatError("eglChooseConfig found no matching configurations".to_owned()));
+    }
+
+    Ok(config)
+}
+
+unsafe fn query_string(display: EGLDisplay, name: u32) -> String {
+    let string_ptr = egl::QueryString(display, name as EGLint);
+
+    match string_ptr == ptr::null() {
+        // Querying client extensions on EGL_NO_DISPLAY fails on implementations without EGL_EXT_client_extensions,
+        // which just means that there aren't any.
+        true  => String::new(),
+        false => CStr::from_ptr(string_ptr).to_string_lossy().into_owned()
+    }
+}
+
+fn egl_error(reason: &str) -> GlCreationError {
+    let error = unsafe{ egl::GetError() };
+    GlCreationError::OsError(format!("EGL error {:#x}", error), reason.to_owned())
+}
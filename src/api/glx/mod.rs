@@ -0,0 +1,318 @@
+use x11::xlib;
+use x11::glx;
+
+use std::ptr;
+use std::mem;
+use std::slice;
+use std::ffi::{CStr, CString};
+use std::marker::PhantomData;
+use std::os::raw::{c_int, c_void};
+
+use api::x11::Window;
+use api::x11::wrapper::XConnection;
+use error::{TubResult, TubError, GlCreationError, GlCreationResult};
+use config::{PixelFormat, ContextConfig, GlApi};
+
+// Constants from GLX extensions, which aren't included in the x11 crate
+const GLX_RGBA_FLOAT_BIT_ARB: c_int = 0x0004;
+const GLX_FRAMEBUFFER_SRGB_CAPABLE_ARB: c_int = 0x20B2;
+const GLX_CONTEXT_MAJOR_VERSION_ARB: c_int = 0x2091;
+const GLX_CONTEXT_MINOR_VERSION_ARB: c_int = 0x2092;
+const GLX_CONTEXT_PROFILE_MASK_ARB: c_int = 0x9126;
+const GLX_CONTEXT_CORE_PROFILE_BIT_ARB: c_int = 0x0001;
+
+type GlXCreateContextAttribsARB = unsafe extern "C" fn(*mut xlib::Display, glx::GLXFBConfig, glx::GLXContext,
+                                                       xlib::Bool, *const c_int) -> glx::GLXContext;
+
+/// An FBConfig and the visual that a window needs to be created with to use it. Unlike with WGL,
+/// where the pixel format can be set after the window is created, GLX requires that the window be
+/// created with a visual that matches the framebuffer configuration, so this gets chosen by the
+/// window on creation.
+#[derive(Clone, Copy)]
+pub struct FbConfig {
+    pub config: glx::GLXFBConfig,
+    pub visual_info: xlib::XVisualInfo
+}
+
+unsafe impl Send for FbConfig {}
+unsafe impl Sync for FbConfig {}
+
+/// Find the framebuffer configuration that best matches the pixel format. If `transparent` is true,
+/// configurations with a 32-bit visual are preferred so that the window can have an alpha channel.
+pub fn choose_fb_config(conn: &XConnection, pixel_format: &PixelFormat, transparent: bool) -> GlCreationResult<FbConfig> {
+    unsafe {
+        let extns = query_extensions(conn);
+
+        // The color bits are the total of the red, green and blue bits, so each channel gets a third.
+        let channel_bits = pixel_format.color_bits as c_int / 3;
+
+        let mut attrs =
+            vec![
+                glx::GLX_X_RENDERABLE, 1,
+                glx::GLX_DRAWABLE_TYPE, glx::GLX_WINDOW_BIT,
+                glx::GLX_X_VISUAL_TYPE, glx::GLX_TRUE_COLOR,
+                glx::GLX_DOUBLEBUFFER, 1,
+                glx::GLX_RED_SIZE, channel_bits,
+                glx::GLX_GREEN_SIZE, channel_bits,
+                glx::GLX_BLUE_SIZE, channel_bits,
+                glx::GLX_ALPHA_SIZE, pixel_format.alpha_bits as c_int,
+                glx::GLX_DEPTH_SIZE, pixel_format.depth_bits as c_int,
+                glx::GLX_STENCIL_SIZE, pixel_format.stencil_bits as c_int
+            ];
+
+        attrs.push(glx::GLX_RENDER_TYPE);
+        if pixel_format.color_buffer_float {
+            if has_extension(extns, "GLX_ARB_fbconfig_float") {
+                attrs.push(GLX_RGBA_FLOAT_BIT_ARB);
+            }
+            else {
+                return Err(GlCreationError::FloatingBufferError);
+            }
+        }
+        else {
+            attrs.push(glx::GLX_RGBA_BIT);
+        }
+
+        if let Some(srgb) = pixel_format.srgb {
+            if has_extension(extns, "GLX_ARB_framebuffer_sRGB") || has_extension(extns, "GLX_EXT_framebuffer_sRGB") {
+                attrs.push(GLX_FRAMEBUFFER_SRGB_CAPABLE_ARB);
+            }
+            else {
+                return Err(GlCreationError::SRGBBufferError);
+            }
+
+            match srgb {
+                true  => attrs.push(1),
+                false => attrs.push(0)
+            }
+        }
+
+        if pixel_format.multisampling > 0 {
+            if has_extension(extns, "GLX_ARB_multisample") {
+                attrs.push(glx::GLX_SAMPLE_BUFFERS);
+                attrs.push(1);
+                attrs.push(glx::GLX_SAMPLES);
+                attrs.push(pixel_format.multisampling as c_int);
+            }
+            else {
+                return Err(GlCreationError::MSAABufferError);
+            }
+        }
+
+        if pixel_format.stereoscopic {
+            attrs.push(glx::GLX_STEREO);
+            attrs.push(1);
+        }
+
+        // GLX doesn't directly say whether or not a configuration is accelerated, but software
+        // configurations are marked as slow.
+        if let Some(accel) = pixel_format.hardware_accel {
+            attrs.push(glx::GLX_CONFIG_CAVEAT);
+            match accel {
+                true  => attrs.push(glx::GLX_NONE),
+                false => attrs.push(glx::GLX_SLOW_CONFIG)
+            }
+        }
+
+        // The attributes list must end with a zero, so this makes it end with a zero
+        attrs.push(0);
+
+
+        let mut config_count = 0;
+        let configs = glx::glXChooseFBConfig(conn.display, conn.screen, attrs.as_ptr(), &mut config_count);
+
+        if configs == ptr::null_mut() || config_count == 0 {
+            return Err(GlCreationError::IndescribableFormatError("glXChooseFBConfig found no matching configurations".to_owned()));
+        }
+
+        // The configurations are sorted from best to worst match, so the first one with a visual is used
+        // unless we're looking for one with an alpha channel.
+        let mut chosen = None;
+        for &config in slice::from_raw_parts(configs, config_count as usize) {
+            let visual_info = glx::glXGetVisualFromFBConfig(conn.display, config);
+            if visual_info == ptr::null_mut() {
+                continue;
+            }
+
+            let fb_config = FbConfig {
+                config: config,
+                visual_info: *visual_info
+            };
+            xlib::XFree(visual_info as *mut _);
+
+            if !transparent || fb_config.visual_info.depth == 32 {
+                chosen = Some(fb_config);
+                break;
+            }
+            else if chosen.is_none() {
+                chosen = Some(fb_config);
+            }
+        }
+        xlib::XFree(configs as *mut _);
+
+        chosen.ok_or(GlCreationError::IndescribableFormatError("No matching configuration has an X visual".to_owned()))
+    }
+}
+
+pub struct GlContext<'w, 'c> {
+    display: *mut xlib::Display,
+    window: xlib::Window,
+    /// A handle to the OpenGL context
+    context: glx::GLXContext,
+    /// Guarantees that this won't live longer than the window that created it, which would
+    /// be very very bad.
+    window_lifetime: PhantomData<&'w ()>,
+    /// Guarantees that this won't live longer than any context that this is sharing resources
+    /// with.
+    shared_lifetime: PhantomData<&'c ()>
+}
+
+impl<'w, 'c> GlContext<'w, 'c> {
+    pub fn new(window: &'w Window, shared_context: Option<&'c GlContext>, context_config: &ContextConfig) -> GlCreationResult<GlContext<'w, 'c>> {
+        unsafe {
+            // OpenGL ES contexts go through EGL
+            if context_config.api != GlApi::Gl {
+                return Err(GlCreationError::UnsupportedApiError);
+            }
+
+            let display = window.wrapper.1.display;
+
+            // If the window couldn't get a framebuffer configuration on creation, this returns the error
+            // it got then.
+            let fb_config = try!(window.fb_config.clone());
+
+            let shared_context_ptr =
+                match shared_context {
+                    Some(c) => c.context,
+                    None    => ptr::null_mut()
+                };
+
+            // glXGetProcAddress hands out an address for any name, even for functions that the driver
+            // doesn't have, so the extension has to be checked for first.
+            let extns = query_extensions(&window.wrapper.1);
+            let create_context_attribs: Option<GlXCreateContextAttribsARB> =
+                match has_extension(extns, "GLX_ARB_create_context") {
+                    true  => {
+                        let addr = get_proc_address("glXCreateContextAttribsARB");
+                        match addr == ptr::null() {
+                            true  => None,
+                            false => Some(mem::transmute(addr))
+                        }
+                    }
+                    false => None
+                };
+            let has_profiles = has_extension(extns, "GLX_ARB_create_context_profile");
+
+            let context = match (create_context_attribs, context_config.version) {
+                (Some(create_context_attribs), Some((major, minor))) => {
+                    let mut attrs = vec![
+                        GLX_CONTEXT_MAJOR_VERSION_ARB, major as c_int,
+                        GLX_CONTEXT_MINOR_VERSION_ARB, minor as c_int
+                    ];
+
+                    // Without GLX_ARB_create_context_profile, the driver picks the profile itself
+                    if (major, minor) >= (3, 2) && has_profiles {
+                        attrs.push(GLX_CONTEXT_PROFILE_MASK_ARB);
+                        attrs.push(GLX_CONTEXT_CORE_PROFILE_BIT_ARB);
+                    }
+                    attrs.push(0);
+
+                    create_context_attribs(display, fb_config.config, shared_context_ptr, xlib::True, attrs.as_ptr())
+                }
+
+                (Some(create_context_attribs), None) => {
+                    // Try to get a 3.2 core context first, since that's what modern renderers (gfx included)
+                    // expect and Mesa only exposes newer versions through core contexts. If the driver can't
+                    // do that, fall back to whatever it gives by default.
+                    let core_attrs = [
+                        GLX_CONTEXT_MAJOR_VERSION_ARB, 3,
+                        GLX_CONTEXT_MINOR_VERSION_ARB, 2,
+                        GLX_CONTEXT_PROFILE_MASK_ARB, GLX_CONTEXT_CORE_PROFILE_BIT_ARB,
+                        0
+                    ];
+                    let default_attrs = [0];
+
+                    let context = match has_profiles {
+                        true  => create_context_attribs(display, fb_config.config, shared_context_ptr, xlib::True, core_attrs.as_ptr()),
+                        false => ptr::null_mut()
+                    };
+                    match context == ptr::null_mut() {
+                        true  => create_context_attribs(display, fb_config.config, shared_context_ptr, xlib::True, default_attrs.as_ptr()),
+                        false => context
+                    }
+                }
+
+                // Without GLX_ARB_create_context there's no way to ask for a specific version
+                (None, Some(_)) => return Err(GlCreationError::FunctionLoadError),
+                (None, None)    => glx::glXCreateNewContext(display, fb_config.config, glx::GLX_RGBA_TYPE, shared_context_ptr, xlib::True)
+            };
+
+            if context == ptr::null_mut() {
+                return Err(GlCreationError::ExtendedCreationError);
+            }
+
+            Ok(
+                GlContext {
+                    display: display,
+                    window: window.wrapper.0,
+                    context: context,
+                    window_lifetime: PhantomData,
+                    shared_lifetime: PhantomData
+                }
+            )
+        }
+    }
+
+    pub unsafe fn make_current(&self) -> TubResult<()> {
+        if glx::glXMakeCurrent(self.display, self.window, self.context) == 0 {
+            return Err(TubError::OsError("Context Switch Error: glXMakeCurrent failed".to_owned()));
+        }
+        Ok(())
+    }
+
+    pub fn get_proc_address(&self, proc_name: &str) -> *const () {
+        get_proc_address(proc_name)
+    }
+
+    pub fn swap_buffers(&self) {
+        unsafe{ glx::glXSwapBuffers(self.display, self.window) };
+    }
+}
+
+impl<'w, 'c> Drop for GlContext<'w, 'c> {
+    fn drop(&mut self) {
+        unsafe {
+            if glx::glXGetCurrentContext() == self.context {
+                glx::glXMakeCurrent(self.display, 0, ptr::null_mut());
+            }
+
+            glx::glXDestroyContext(self.display, self.context);
+        }
+    }
+}
+
+fn get_proc_address(proc_name: &str) -> *const () {
+    unsafe {
+        let proc_name = CString::new(proc_name.as_bytes()).unwrap();
+
+        match glx::glXGetProcAddress(proc_name.as_ptr() as *const _) {
+            Some(f) => f as *const c_void as *const (),
+            None    => ptr::null()
+        }
+    }
+}
+
+/// Get the GLX extensions that the connection's screen supports, separated by spaces
+unsafe fn query_extensions(conn: &XConnection) -> &str {
+    let extns_ptr = glx::glXQueryExtensionsString(conn.display, conn.screen);
+    match extns_ptr == ptr::null() {
+        true  => "",
+        false => ::std::str::from_utf8(CStr::from_ptr(extns_ptr).to_bytes()).unwrap_or("")
+    }
+}
+
+/// Whether an extension is in a list of extensions from `query_extensions`. The names have to be
+/// compared whole, since some extensions' names start with the names of others.
+fn has_extension(extns: &str, name: &str) -> bool {
+    extns.split(' ').any(|e| e == name)
+}
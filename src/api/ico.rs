@@ -0,0 +1,164 @@
+//! A minimal reader for Windows `.ico` and `.cur` files. Windows can load these natively, but other
+//! platforms and custom cursors need the raw pixels, so this pulls them out of the file by hand. Only uncompressed 32-bit
+//! BMP entries are supported, which is what most modern icon editors produce.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// The largest width or height that an icon file can store
+const MAX_SIZE: u32 = 256;
+
+/// A single image pulled out of an icon file
+pub struct IcoImage {
+    pub width: u32,
+    pub height: u32,
+    /// For `.cur` files, the cursor hotspot. For `.ico` files, this is always `(0, 0)`.
+    pub hotspot: (u32, u32),
+    /// The image pixels, in ARGB order, going from the top row to the bottom row
+    pub pixels: Vec<u32>
+}
+
+/// Load every supported image from the icon file at `path`. Returns `None` if the file could not
+/// be read or isn't a valid icon file.
+pub fn load_ico(path: &Path) -> Option<Vec<IcoImage>> {
+    let mut data = Vec::new();
+    match File::open(path) {
+        Ok(mut f) => if f.read_to_end(&mut data).is_err() { return None },
+        Err(_)    => return None
+    }
+
+    parse_ico(&data)
+}
+
+pub fn parse_ico(data: &[u8]) -> Option<Vec<IcoImage>> {
+    if data.len() < 6 {
+        return None;
+    }
+
+    // Type 1 is an icon file and type 2 is a cursor file. The only difference between the two is
+    // that cursor files store the hotspot where icon files store the plane count and bit depth.
+    let file_type = read_u16(data, 2);
+    if read_u16(data, 0) != 0 || (file_type != 1 && file_type != 2) {
+        return None;
+    }
+
+    let count = read_u16(data, 4) as usize;
+    let mut images = Vec::with_capacity(count);
+
+    for i in 0..count {
+        let entry = 6 + i * 16;
+        if data.len() < entry + 16 {
+            return None;
+        }
+
+        let hotspot = match file_type {
+            2 => (read_u16(data, entry + 4) as u32, read_u16(data, entry + 6) as u32),
+            _ => (0, 0)
+        };
+        let size = read_u32(data, entry + 8) as usize;
+        let offset = read_u32(data, entry + 12) as usize;
+
+        let end = match offset.checked_add(size) {
+            Some(end) if end <= data.len() => end,
+            _ => return None
+        };
+
+        if let Some(mut image) = parse_bmp(&data[offset..end]) {
+            image.hotspot = hotspot;
+            images.push(image);
+        }
+    }
+
+    Some(images)
+}
+
+/// Parse the headerless BMP stored inside of an icon entry. Entries stored as PNGs are skipped.
+fn parse_bmp(data: &[u8]) -> Option<IcoImage> {
+    // The BITMAPINFOHEADER is 40 bytes long
+    if data.len() < 40 || read_u32(data, 0) != 40 {
+        return None;
+    }
+
+    let width = read_u32(data, 4);
+    // The height stored in the header includes the AND mask, so it's twice the actual height.
+    let height = read_u32(data, 8) / 2;
+    let bit_count = read_u16(data, 14);
+    let compression = read_u32(data, 16);
+
+    if bit_count != 32 || compression != 0 {
+        return None;
+    }
+
+    if width == 0 || height == 0 || width > MAX_SIZE || height > MAX_SIZE {
+        return None;
+    }
+
+    let pixel_len = match (width as usize).checked_mul(height as usize) {
+        Some(len) => len,
+        None      => return None
+    };
+    match pixel_len.checked_mul(4).and_then(|l| l.checked_add(40)) {
+        Some(end) if end <= data.len() => (),
+        _ => return None
+    }
+
+    // BMP rows are stored bottom-to-top, so flip them while copying the pixels out.
+    let mut pixels = Vec::with_capacity(pixel_len);
+    for row in (0..height as usize).rev() {
+        for col in 0..width as usize {
+            pixels.push(read_u32(data, 40 + (row * width as usize + col) * 4));
+        }
+    }
+
+    Some(IcoImage {
+        width: width,
+        height: height,
+        hotspot: (0, 0),
+        pixels: pixels
+    })
+}
+
+#[inline]
+fn read_u16(data: &[u8], at: usize) -> u16 {
+    data[at] as u16 | (data[at + 1] as u16) << 8
+}
+
+#[inline]
+fn read_u32(data: &[u8], at: usize) -> u32 {
+    read_u16(data, at) as u32 | (read_u16(data, at + 2) as u32) << 16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A cursor file holding a single 32-bit BMP entry with the given header dimensions, and no
+    /// pixel data at all
+    fn cursor_with_size(width: u32, height: u32) -> Vec<u8> {
+        let mut data = vec![0, 0, 2, 0, 1, 0];
+        let entry: [u32; 4] = [0, 0, 40, 22];
+        let header: [u32; 10] = [40, width, height, 32 << 16 | 1, 0, 0, 0, 0, 0, 0];
+        for word in entry.iter().chain(header.iter()) {
+            data.extend((0..4).map(|i| (word >> (i * 8)) as u8));
+        }
+        data
+    }
+
+    #[test]
+    fn huge_dimensions() {
+        assert!(parse_bmp(&cursor_with_size(0xffffffff, 0xfffffffe)[22..]).is_none());
+        assert!(parse_bmp(&cursor_with_size(0x10000, 0x20000)[22..]).is_none());
+        assert_eq!(parse_ico(&cursor_with_size(0xffffffff, 0xfffffffe)).map(|i| i.len()), Some(0));
+    }
+
+    #[test]
+    fn huge_entry() {
+        let mut data = cursor_with_size(1, 2);
+        // Move the entry's offset right up against the end of the address space
+        for i in 0..4 {
+            data[18 + i] = 0xff;
+        }
+        assert!(parse_ico(&data).is_none());
+    }
+}
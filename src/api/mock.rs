@@ -0,0 +1,520 @@
+use std::mem;
+use std::cell::{Cell, RefCell};
+use std::marker::PhantomData;
+
+use {Cursor, CursorType, GrabMode, Rect};
+use api::backend::{WindowBackend, NativeWindow, EventSink};
+use error::TubResult;
+use config::{WindowConfig, PixelFormat};
+use event::{Event, ResizeType};
+
+/// The size that windows are created with when `WindowConfig::size` is `None`
+const DEFAULT_SIZE: (u32, u32) = (800, 600);
+
+thread_local!{
+    /// The ID and event sink of the window that currently has input focus. This is thread-local so
+    /// that tests running in parallel don't steal focus from each other.
+    static FOCUSED_WINDOW: RefCell<Option<(usize, EventSink)>> = RefCell::new(None);
+    /// The ID that the next created window gets
+    static NEXT_ID: Cell<usize> = Cell::new(1);
+}
+
+#[derive(Clone)]
+pub enum WindowType<'p> {
+    Owned(&'p Window<'p>),
+    Child(&'p Window<'p>),
+    Top
+}
+
+/// A window that only exists in memory. It has the same interface as `platform::Window`, but
+/// instead of talking to the OS it just records whatever is done to it, and events are only
+/// received when they're pushed into the window with `push_event`.
+///
+/// Like a real window, setting the position or size sends a `Moved` or `Resized` event, and both
+/// of those are sent when the window is first created.
+///
+/// ```
+/// # use tub::config::WindowConfig;
+/// # use tub::event::{Event, PressState, Key, ScanCode, PhysicalKey, ModifiersState};
+/// use tub::platform::{Window, Backend, EventsLoop};
+///
+/// let events_loop = EventsLoop::new().unwrap();
+/// let window = Window::new_with_backend(&events_loop, Backend::Mock, WindowConfig::new().size(Some((640, 480))), Default::default()).unwrap();
+/// // Skip the creation events
+/// events_loop.poll_events().count();
+///
+/// let s = ScanCode{ raw: 31, key: Some(PhysicalKey::S) };
+/// let ctrl = ModifiersState{ ctrl: true, ..Default::default() };
+/// window.as_mock().unwrap().push_event(Event::KeyInput(PressState::Pressed, Some(Key::S), s, ctrl));
+/// assert_eq!(events_loop.poll_events().next(), Some((window.id(), Event::KeyInput(PressState::Pressed, Some(Key::S), s, ctrl))));
+/// assert_eq!(window.get_inner_size(), Some((640, 480)));
+/// ```
+pub struct Window<'p> {
+    id: usize,
+    title: RefCell<String>,
+    pos: Cell<(i32, i32)>,
+    size: Cell<(u32, u32)>,
+    cursor: RefCell<Cursor>,
+    cursor_pos: Cell<Option<(i32, i32)>>,
+    ime_pos: Cell<Option<(i32, i32)>>,
+    grab: Cell<GrabMode>,
+    confine_rect: Cell<Option<Rect>>,
+    visible: Cell<bool>,
+    enabled: Cell<bool>,
+    closed: Cell<bool>,
+    event_sink: EventSink,
+    win_type: WindowType<'p>,
+    config: WindowConfig,
+    pixel_format: PixelFormat
+}
+
+impl<'p> Window<'p> {
+    pub(crate) fn new(config: WindowConfig, pixel_format: PixelFormat, sink: EventSink) -> TubResult<Window<'p>> {
+        Ok(Window::with_type(config, pixel_format, sink, WindowType::Top))
+    }
+
+    pub(crate) fn new_owned(&'p self, config: WindowConfig, pixel_format: PixelFormat, sink: EventSink) -> TubResult<Window<'p>> {
+        Ok(Window::with_type(config, pixel_format, sink, WindowType::Owned(self)))
+    }
+
+    pub(crate) fn new_child(&'p self, config: WindowConfig, pixel_format: PixelFormat, sink: EventSink) -> TubResult<Window<'p>> {
+        Ok(Window::with_type(config, pixel_format, sink, WindowType::Child(self)))
+    }
+
+    fn with_type(config: WindowConfig, pixel_format: PixelFormat, sink: EventSink, win_type: WindowType<'p>) -> Window<'p> {
+        let id = NEXT_ID.with(|n| {
+            let id = n.get();
+            n.set(id + 1);
+            id
+        });
+
+        let size = match config.size {
+            Some(s) => (s.0 as u32, s.1 as u32),
+            None    => DEFAULT_SIZE
+        };
+
+        sink.send(Event::Resized(ResizeType::Changed, size.0, size.1));
+        sink.send(Event::Moved(0, 0));
+
+        Window {
+            id: id,
+            title: RefCell::new(config.name.clone()),
+            pos: Cell::new((0, 0)),
+            size: Cell::new(size),
+            cursor: RefCell::new(Cursor::System(CursorType::Arrow)),
+            cursor_pos: Cell::new(None),
+            ime_pos: Cell::new(None),
+            grab: Cell::new(GrabMode::None),
+            confine_rect: Cell::new(None),
+            visible: Cell::new(false),
+            enabled: Cell::new(true),
+            closed: Cell::new(false),
+            event_sink: sink,
+            win_type: win_type,
+            config: config,
+            pixel_format: pixel_format
+        }
+    }
+
+    /// Send an event from the window to its events loop. Like with real windows, input events are
+    /// dropped if the window is disabled.
+    ///
+    /// Any event can be sent this way, including ones that are hard to make happen for real, like
+    /// two fingers touching at once:
+    ///
+    /// ```
+    /// # use tub::event::{Event, TouchPhase};
+    /// # use tub::platform::{Window, Backend, EventsLoop};
+    /// let events_loop = EventsLoop::new().unwrap();
+    /// let window = Window::new_with_backend(&events_loop, Backend::Mock, Default::default(), Default::default()).unwrap();
+    /// events_loop.poll_events().count();
+    ///
+    /// let mock = window.as_mock().unwrap();
+    /// mock.push_event(Event::Touch{ id: 0, phase: TouchPhase::Started, x: 10, y: 10, pressure: None });
+    /// mock.push_event(Event::Touch{ id: 1, phase: TouchPhase::Started, x: 50, y: 10, pressure: None });
+    /// mock.push_event(Event::Touch{ id: 0, phase: TouchPhase::Ended, x: 10, y: 20, pressure: None });
+    ///
+    /// let ids: Vec<u64> = events_loop.poll_events().filter_map(|(_, e)| match e {
+    ///     Event::Touch{id, ..} => Some(id),
+    ///     _                    => None
+    /// }).collect();
+    /// assert_eq!(ids, [0, 1, 0]);
+    /// ```
+    pub fn push_event(&self, event: Event) {
+        use event::Event::*;
+
+        let is_input = match event {
+            KeyInput(..)    |
+            ReceivedCharacter(..) |
+            ImeCompositionStart   |
+            ImeCompositionUpdate(..) |
+            ImeCompositionCommit(..) |
+            MButtonInput(..)|
+            MouseMoved(..)  |
+            MouseMotion(..) |
+            MouseWheel(..)  |
+            MouseHover(..)  |
+            MouseLeave      |
+            MouseEnter      |
+            Touch{..}       |
+            Pen{..}         => true,
+            _               => false
+        };
+
+        if self.enabled.get() || !is_input {
+            self.event_sink.send(event);
+        }
+    }
+
+    #[inline]
+    pub fn set_title(&self, title: &str) {
+        *self.title.borrow_mut() = title.to_owned();
+    }
+
+    #[inline]
+    pub fn get_title(&self) -> String {
+        self.title.borrow().clone()
+    }
+
+    #[inline]
+    pub fn show(&self) {
+        self.visible.set(true);
+    }
+
+    #[inline]
+    pub fn hide(&self) {
+        self.visible.set(false);
+    }
+
+    #[inline]
+    pub fn is_visible(&self) -> bool {
+        self.visible.get()
+    }
+
+    /// Close the window, which hides it and sends the `Destroyed` event. Unlike a real window, the
+    /// mock window still works afterwards.
+    pub fn close(&self) {
+        if !self.closed.replace(true) {
+            self.visible.set(false);
+            self.event_sink.send(Event::Destroyed);
+        }
+    }
+
+    /// Check whether `close` has been called
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.closed.get()
+    }
+
+    #[inline]
+    pub fn enable(&self) {
+        self.enabled.set(true);
+    }
+
+    #[inline]
+    pub fn disable(&self) {
+        self.enabled.set(false);
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.get()
+    }
+
+    /// Give the window input focus, which sends `Focused(false)` to the window that had focus and
+    /// `Focused(true)` to this one.
+    pub fn focus(&self) {
+        if self.is_active() {
+            return;
+        }
+
+        let last = FOCUSED_WINDOW.with(|f| mem::replace(&mut *f.borrow_mut(), Some((self.id, self.event_sink.clone()))));
+        if let Some((_, sink)) = last {
+            sink.send(Event::Focused(false));
+        }
+        self.event_sink.send(Event::Focused(true));
+    }
+
+    #[inline]
+    pub fn get_inner_pos(&self) -> Option<(i32, i32)> {
+        Some(self.pos.get())
+    }
+
+    /// Mock windows have no decorations, so this is the same as the inner position
+    #[inline]
+    pub fn get_outer_pos(&self) -> Option<(i32, i32)> {
+        Some(self.pos.get())
+    }
+
+    #[inline]
+    pub fn get_inner_size(&self) -> Option<(u32, u32)> {
+        Some(self.size.get())
+    }
+
+    /// Mock windows have no decorations, so this is the same as the inner size
+    #[inline]
+    pub fn get_outer_size(&self) -> Option<(u32, u32)> {
+        Some(self.size.get())
+    }
+
+    #[inline]
+    pub fn set_pos(&self, x: i32, y: i32) -> Option<()> {
+        self.pos.set((x, y));
+        self.event_sink.send(Event::Moved(x, y));
+        Some(())
+    }
+
+    #[inline]
+    pub fn set_inner_size(&self, x: u32, y: u32) -> Option<()> {
+        self.size.set((x, y));
+        self.event_sink.send(Event::Resized(ResizeType::Changed, x, y));
+        Some(())
+    }
+
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        FOCUSED_WINDOW.with(|f| f.borrow().as_ref().map(|&(id, _)| id) == Some(self.id))
+    }
+
+    #[inline]
+    pub fn set_cursor(&self, cursor: &Cursor) {
+        *self.cursor.borrow_mut() = cursor.clone();
+    }
+
+    #[inline]
+    pub fn get_cursor(&self) -> Cursor {
+        self.cursor.borrow().clone()
+    }
+
+    /// Sets the cursor position relative to window space, which sends a `MouseMoved` event. Like on
+    /// a real window, this does nothing if the window isn't active.
+    #[inline]
+    pub fn set_cursor_pos(&self, x: i32, y: i32) {
+        if self.is_active() {
+            self.cursor_pos.set(Some((x, y)));
+            self.push_event(Event::MouseMoved(x, y));
+        }
+    }
+
+    /// Get the last position set with `set_cursor_pos`
+    #[inline]
+    pub fn get_cursor_pos(&self) -> Option<(i32, i32)> {
+        self.cursor_pos.get()
+    }
+
+    /// Mock windows have no cursor to grab, so this just records the mode
+    #[inline]
+    pub fn set_cursor_grab(&self, mode: GrabMode) -> Option<()> {
+        self.grab.set(mode);
+        self.confine_rect.set(None);
+        Some(())
+    }
+
+    /// Get the last mode set with `set_cursor_grab`, or `Confined` if the cursor was confined with
+    /// `confine_cursor`
+    #[inline]
+    pub fn get_cursor_grab(&self) -> GrabMode {
+        self.grab.get()
+    }
+
+    /// Mock windows have no cursor to confine, so this just records the rectangle
+    #[inline]
+    pub fn confine_cursor(&self, rect: Option<Rect>) -> Option<()> {
+        self.grab.set(match rect {
+            Some(_) => GrabMode::Confined,
+            None    => GrabMode::None
+        });
+        self.confine_rect.set(rect);
+        Some(())
+    }
+
+    /// Get the rectangle that was last set with `confine_cursor`, if the cursor is still confined
+    /// to it
+    #[inline]
+    pub fn get_cursor_confinement(&self) -> Option<Rect> {
+        self.confine_rect.get()
+    }
+
+    #[inline]
+    pub fn set_ime_position(&self, x: i32, y: i32) {
+        self.ime_pos.set(Some((x, y)));
+    }
+
+    /// Get the last position set with `set_ime_position`
+    #[inline]
+    pub fn get_ime_position(&self) -> Option<(i32, i32)> {
+        self.ime_pos.get()
+    }
+
+    #[inline]
+    pub fn get_type(&self) -> WindowType {
+        self.win_type.clone()
+    }
+
+    #[inline]
+    pub fn get_config(&self) -> &WindowConfig {
+        &self.config
+    }
+
+    #[inline]
+    pub fn get_pixel_format(&self) -> &PixelFormat {
+        &self.pixel_format
+    }
+
+}
+
+impl<'p> WindowBackend for Window<'p> {
+    fn new_owned<'a>(&'a self, config: WindowConfig, pixel_format: PixelFormat, sink: EventSink) -> TubResult<Box<WindowBackend + 'a>> {
+        Window::new_owned(self, config, pixel_format, sink).map(|w| Box::new(w) as Box<WindowBackend + 'a>)
+    }
+
+    fn new_child<'a>(&'a self, config: WindowConfig, pixel_format: PixelFormat, sink: EventSink) -> TubResult<Box<WindowBackend + 'a>> {
+        Window::new_child(self, config, pixel_format, sink).map(|w| Box::new(w) as Box<WindowBackend + 'a>)
+    }
+
+    #[inline]
+    fn set_title(&self, title: &str) {
+        Window::set_title(self, title);
+    }
+
+    #[inline]
+    fn show(&self) {
+        Window::show(self);
+    }
+
+    #[inline]
+    fn hide(&self) {
+        Window::hide(self);
+    }
+
+    #[inline]
+    fn close(&self) {
+        Window::close(self);
+    }
+
+    #[inline]
+    fn enable(&self) {
+        Window::enable(self);
+    }
+
+    #[inline]
+    fn disable(&self) {
+        Window::disable(self);
+    }
+
+    #[inline]
+    fn focus(&self) {
+        Window::focus(self);
+    }
+
+    #[inline]
+    fn get_inner_pos(&self) -> Option<(i32, i32)> {
+        Window::get_inner_pos(self)
+    }
+
+    #[inline]
+    fn get_outer_pos(&self) -> Option<(i32, i32)> {
+        Window::get_outer_pos(self)
+    }
+
+    #[inline]
+    fn get_inner_size(&self) -> Option<(u32, u32)> {
+        Window::get_inner_size(self)
+    }
+
+    #[inline]
+    fn get_outer_size(&self) -> Option<(u32, u32)> {
+        Window::get_outer_size(self)
+    }
+
+    #[inline]
+    fn set_pos(&self, x: i32, y: i32) -> Option<()> {
+        Window::set_pos(self, x, y)
+    }
+
+    #[inline]
+    fn set_inner_size(&self, x: u32, y: u32) -> Option<()> {
+        Window::set_inner_size(self, x, y)
+    }
+
+    #[inline]
+    fn is_active(&self) -> bool {
+        Window::is_active(self)
+    }
+
+    #[inline]
+    fn set_cursor(&self, cursor: &Cursor) {
+        Window::set_cursor(self, cursor);
+    }
+
+    #[inline]
+    fn set_cursor_pos(&self, x: i32, y: i32) {
+        Window::set_cursor_pos(self, x, y);
+    }
+
+    #[inline]
+    fn set_cursor_grab(&self, mode: GrabMode) -> Option<()> {
+        Window::set_cursor_grab(self, mode)
+    }
+
+    #[inline]
+    fn confine_cursor(&self, rect: Option<Rect>) -> Option<()> {
+        Window::confine_cursor(self, rect)
+    }
+
+    #[inline]
+    fn set_ime_position(&self, x: i32, y: i32) {
+        Window::set_ime_position(self, x, y);
+    }
+
+    #[inline]
+    fn get_config(&self) -> &WindowConfig {
+        &self.config
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> &PixelFormat {
+        &self.pixel_format
+    }
+
+    #[inline]
+    fn native<'a>(&'a self) -> NativeWindow<'a> {
+        NativeWindow::Mock(self)
+    }
+}
+
+impl<'p> Drop for Window<'p> {
+    fn drop(&mut self) {
+        if self.is_active() {
+            FOCUSED_WINDOW.with(|f| *f.borrow_mut() = None);
+        }
+    }
+}
+
+/// A soft buffer for a mock window. There's nowhere for the pixels to be shown, so they just stay
+/// in memory.
+pub struct SoftBuffer<'w> {
+    pixels: Vec<u32>,
+    /// Guarantees that this won't live longer than the window that created it
+    window_lifetime: PhantomData<&'w ()>
+}
+
+impl<'w> SoftBuffer<'w> {
+    pub fn new(_: &'w Window) -> SoftBuffer<'w> {
+        SoftBuffer {
+            pixels: Vec::new(),
+            window_lifetime: PhantomData
+        }
+    }
+
+    /// Replace the pixels with new ones of the given size, cleared to black
+    #[inline]
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.pixels = vec![0; width as usize * height as usize];
+    }
+
+    #[inline]
+    pub fn pixels_mut(&mut self) -> &mut [u32] {
+        &mut self.pixels
+    }
+}
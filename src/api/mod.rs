@@ -1,9 +1,31 @@
+pub mod backend;
+
+#[cfg(all(windows, feature = "win32"))]
 pub mod win32;
+#[cfg(all(windows, feature = "win32"))]
 pub mod wgl;
+#[cfg(all(target_os = "linux", feature = "x11"))]
+pub mod x11;
+#[cfg(all(target_os = "linux", feature = "x11"))]
+pub mod glx;
+#[cfg(all(target_os = "linux", feature = "x11"))]
+pub mod egl;
+#[cfg(all(target_os = "linux", feature = "wayland"))]
+pub mod wayland;
+#[cfg(feature = "mock")]
+pub mod mock;
+#[cfg(feature = "osmesa")]
+pub mod osmesa;
+
+pub mod ico;
+pub mod xcursor;
+#[cfg(all(target_os = "linux", any(feature = "x11", feature = "wayland")))]
+pub mod evdev;
 
+#[cfg(all(windows, feature = "win32"))]
 fn osstr<'a>(s: &'a str) -> Vec<u16> {
     use std::ffi::OsStr;
     use std::os::windows::ffi::OsStrExt;
 
     OsStr::new(s).encode_wide().chain(Some(0).into_iter()).collect::<Vec<_>>()
-}
\ No newline at end of file
+}
@@ -0,0 +1,183 @@
+use std::ptr;
+use std::mem;
+use std::slice;
+use std::ffi::CString;
+use std::marker::PhantomData;
+use std::os::raw::{c_char, c_int, c_uint, c_uchar, c_void};
+
+use error::{TubResult, TubError, GlCreationError, GlCreationResult};
+use config::{PixelFormat, ContextConfig, GlApi};
+
+#[allow(non_camel_case_types)]
+type OSMesaContext = *mut c_void;
+
+const OSMESA_RGBA: c_uint = 0x1908;
+const OSMESA_Y_UP: c_int = 0x11;
+const OSMESA_FORMAT: c_int = 0x22;
+const OSMESA_DEPTH_BITS: c_int = 0x30;
+const OSMESA_STENCIL_BITS: c_int = 0x31;
+const OSMESA_PROFILE: c_int = 0x33;
+const OSMESA_CORE_PROFILE: c_int = 0x34;
+const OSMESA_CONTEXT_MAJOR_VERSION: c_int = 0x36;
+const OSMESA_CONTEXT_MINOR_VERSION: c_int = 0x37;
+const GL_UNSIGNED_BYTE: c_uint = 0x1401;
+
+#[link(name = "OSMesa")]
+extern "C" {
+    fn OSMesaCreateContextAttribs(attrib_list: *const c_int, sharelist: OSMesaContext) -> OSMesaContext;
+    fn OSMesaDestroyContext(ctx: OSMesaContext);
+    fn OSMesaMakeCurrent(ctx: OSMesaContext, buffer: *mut c_void, buffer_type: c_uint, width: c_int, height: c_int) -> c_uchar;
+    fn OSMesaGetCurrentContext() -> OSMesaContext;
+    fn OSMesaPixelStore(pname: c_int, value: c_int);
+    fn OSMesaGetProcAddress(func_name: *const c_char) -> *const c_void;
+}
+
+/// An OpenGL context that renders into memory on the CPU through Mesa's OSMesa library. This needs
+/// neither a window nor a display server, which makes it useful for rendering tests that compare
+/// against reference images.
+///
+/// The color buffer is always 8-bit RGBA, stored in rows from the top-left corner of the image.
+/// It can either be allocated by tub, with `new`, or borrowed from the caller with `with_buffer`.
+/// Since OSMesa only renders in software, `PixelFormat::hardware_accel` is ignored.
+pub struct OsMesaContext<'c, 'b> {
+    context: OSMesaContext,
+    size: (u32, u32),
+    /// The memory that the context renders into. It's only ever accessed through this pointer,
+    /// since OSMesa writes to it whenever the context is drawn with.
+    buffer: *mut u8,
+    /// Keeps the buffer alive when tub allocated it
+    _owned_buffer: Option<Vec<u8>>,
+    /// Guarantees that this won't live longer than a borrowed buffer
+    buffer_lifetime: PhantomData<&'b mut [u8]>,
+    /// Guarantees that this won't live longer than any context that this is sharing resources
+    /// with.
+    shared_lifetime: PhantomData<&'c ()>
+}
+
+impl<'c> OsMesaContext<'c, 'static> {
+    /// Create a context that renders into a buffer allocated by tub
+    pub fn new(size: (u32, u32), pixel_format: PixelFormat, context_config: ContextConfig, shared_context: Option<&'c OsMesaContext>) -> GlCreationResult<OsMesaContext<'c, 'static>> {
+        let mut buffer = vec![0; size.0 as usize * size.1 as usize * 4];
+        let buffer_ptr = buffer.as_mut_ptr();
+
+        OsMesaContext::with_ptr(buffer_ptr, Some(buffer), size, &pixel_format, &context_config, shared_context)
+    }
+}
+
+impl<'c, 'b> OsMesaContext<'c, 'b> {
+    /// Create a context that renders into `buffer`, which has to be at least `size.0 * size.1 * 4`
+    /// bytes long.
+    pub fn with_buffer(buffer: &'b mut [u8], size: (u32, u32), pixel_format: PixelFormat, context_config: ContextConfig, shared_context: Option<&'c OsMesaContext>) -> GlCreationResult<OsMesaContext<'c, 'b>> {
+        if buffer.len() < size.0 as usize * size.1 as usize * 4 {
+            return Err(GlCreationError::OsError("Buffer too small".to_owned(), "The buffer can't hold an image of the requested size".to_owned()));
+        }
+
+        OsMesaContext::with_ptr(buffer.as_mut_ptr(), None, size, &pixel_format, &context_config, shared_context)
+    }
+
+    fn with_ptr(buffer: *mut u8, owned_buffer: Option<Vec<u8>>, size: (u32, u32), pixel_format: &PixelFormat, context_config: &ContextConfig, shared_context: Option<&'c OsMesaContext>) -> GlCreationResult<OsMesaContext<'c, 'b>> {
+        if context_config.api != GlApi::Gl {
+            return Err(GlCreationError::UnsupportedApiError);
+        }
+        if pixel_format.color_bits > 24 || pixel_format.alpha_bits > 8 {
+            return Err(GlCreationError::IndescribableFormatError("OSMesa only supports 8-bit color channels".to_owned()));
+        }
+        if pixel_format.color_buffer_float {
+            return Err(GlCreationError::FloatingBufferError);
+        }
+        if pixel_format.srgb == Some(true) {
+            return Err(GlCreationError::SRGBBufferError);
+        }
+        if pixel_format.multisampling > 0 {
+            return Err(GlCreationError::MSAABufferError);
+        }
+        if pixel_format.stereoscopic {
+            return Err(GlCreationError::IndescribableFormatError("OSMesa does not support stereoscopic framebuffers".to_owned()));
+        }
+
+        let mut attrs = vec![
+            OSMESA_FORMAT, OSMESA_RGBA as c_int,
+            OSMESA_DEPTH_BITS, pixel_format.depth_bits as c_int,
+            OSMESA_STENCIL_BITS, pixel_format.stencil_bits as c_int
+        ];
+
+        if let Some((major, minor)) = context_config.version {
+            attrs.push(OSMESA_CONTEXT_MAJOR_VERSION);
+            attrs.push(major as c_int);
+            attrs.push(OSMESA_CONTEXT_MINOR_VERSION);
+            attrs.push(minor as c_int);
+
+            if (major, minor) >= (3, 2) {
+                attrs.push(OSMESA_PROFILE);
+                attrs.push(OSMESA_CORE_PROFILE);
+            }
+        }
+        attrs.push(0);
+
+        let shared_context_ptr =
+            match shared_context {
+                Some(c) => c.context,
+                None    => ptr::null_mut()
+            };
+
+        let context = unsafe{ OSMesaCreateContextAttribs(attrs.as_ptr(), shared_context_ptr) };
+        if context.is_null() {
+            return Err(GlCreationError::ExtendedCreationError);
+        }
+
+        Ok(
+            OsMesaContext {
+                context: context,
+                size: size,
+                buffer: buffer,
+                _owned_buffer: owned_buffer,
+                buffer_lifetime: PhantomData,
+                shared_lifetime: PhantomData
+            }
+        )
+    }
+
+    pub unsafe fn make_current(&self) -> TubResult<()> {
+        if OSMesaMakeCurrent(self.context, self.buffer as *mut c_void, GL_UNSIGNED_BYTE, self.size.0 as c_int, self.size.1 as c_int) == 0 {
+            return Err(TubError::OsError("Context Switch Error: OSMesaMakeCurrent failed".to_owned()));
+        }
+
+        // OSMesa puts the bottom row first by default, like glReadPixels does
+        OSMesaPixelStore(OSMESA_Y_UP, 0);
+        Ok(())
+    }
+
+    pub fn get_proc_address(&self, proc_name: &str) -> *const () {
+        unsafe {
+            let proc_name = CString::new(proc_name.as_bytes()).unwrap();
+            OSMesaGetProcAddress(proc_name.as_ptr()) as *const ()
+        }
+    }
+
+    #[inline]
+    pub fn get_size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    /// Copy the color buffer into a vector of 8-bit RGBA pixels, with the top row first. If the
+    /// context is current, this waits for any rendering that's still in progress to finish first.
+    pub fn read_pixels(&self) -> Vec<u8> {
+        unsafe {
+            if OSMesaGetCurrentContext() == self.context {
+                let finish = self.get_proc_address("glFinish");
+                if !finish.is_null() {
+                    let finish: extern "system" fn() = mem::transmute(finish);
+                    finish();
+                }
+            }
+
+            slice::from_raw_parts(self.buffer, self.size.0 as usize * self.size.1 as usize * 4).to_vec()
+        }
+    }
+}
+
+impl<'c, 'b> Drop for OsMesaContext<'c, 'b> {
+    fn drop(&mut self) {
+        unsafe{ OSMesaDestroyContext(self.context) };
+    }
+}
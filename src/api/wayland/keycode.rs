@@ -0,0 +1,167 @@
+use event::Key;
+
+/// Get the key for a Linux evdev keycode, which is what `wl_keyboard` sends. This goes by where the
+/// key is on a US keyboard instead of by the active layout, so it's only used when the compositor
+/// doesn't send an xkbcommon keymap.
+pub fn key_from_evdev(keycode: u32) -> Option<Key> {
+    use event::Key::*;
+
+    match keycode {
+        1   => Some(Escape  ),
+        2   => Some(Key1    ),
+        3   => Some(Key2    ),
+        4   => Some(Key3    ),
+        5   => Some(Key4    ),
+        6   => Some(Key5    ),
+        7   => Some(Key6    ),
+        8   => Some(Key7    ),
+        9   => Some(Key8    ),
+        10  => Some(Key9    ),
+        11  => Some(Key0    ),
+        12  => Some(Minus   ),
+        13  => Some(Plus    ),
+        14  => Some(Back    ),
+        15  => Some(Tab     ),
+
+        16  => Some(Q),
+        17  => Some(W),
+        18  => Some(E),
+        19  => Some(R),
+        20  => Some(T),
+        21  => Some(Y),
+        22  => Some(U),
+        23  => Some(I),
+        24  => Some(O),
+        25  => Some(P),
+        26  => Some(LBrac   ),
+        27  => Some(RBrac   ),
+        28  => Some(Enter   ),
+        29  => Some(LCtrl   ),
+
+        30  => Some(A),
+        31  => Some(S),
+        32  => Some(D),
+        33  => Some(F),
+        34  => Some(G),
+        35  => Some(H),
+        36  => Some(J),
+        37  => Some(K),
+        38  => Some(L),
+        39  => Some(Semi    ),
+        40  => Some(Quote   ),
+        41  => Some(Tilde   ),
+        42  => Some(LShift  ),
+        43  => Some(Pipe    ),
+
+        44  => Some(Z),
+        45  => Some(X),
+        46  => Some(C),
+        47  => Some(V),
+        48  => Some(B),
+        49  => Some(N),
+        50  => Some(M),
+        51  => Some(Comma   ),
+        52  => Some(Dot     ),
+        53  => Some(Slash   ),
+        54  => Some(RShift  ),
+        55  => Some(NumStar ),
+        56  => Some(LAlt    ),
+        57  => Some(Space   ),
+        58  => Some(Caps    ),
+
+        59  => Some(F1      ),
+        60  => Some(F2      ),
+        61  => Some(F3      ),
+        62  => Some(F4      ),
+        63  => Some(F5      ),
+        64  => Some(F6      ),
+        65  => Some(F7      ),
+        66  => Some(F8      ),
+        67  => Some(F9      ),
+        68  => Some(F10     ),
+        69  => Some(Num     ),
+        70  => Some(Scroll  ),
+
+        71  => Some(Num7    ),
+        72  => Some(Num8    ),
+        73  => Some(Num9    ),
+        74  => Some(NumSub  ),
+        75  => Some(Num4    ),
+        76  => Some(Num5    ),
+        77  => Some(Num6    ),
+        78  => Some(NumPlus ),
+        79  => Some(Num1    ),
+        80  => Some(Num2    ),
+        81  => Some(Num3    ),
+        82  => Some(Num0    ),
+        83  => Some(NumDot  ),
+        86  => Some(Oem102  ),
+
+        87  => Some(F11     ),
+        88  => Some(F12     ),
+        92  => Some(Convert ),
+        94  => Some(Nonconvert),
+        96  => Some(Enter   ),
+        97  => Some(RCtrl   ),
+        98  => Some(NumSlash),
+        99  => Some(PrntScr ),
+        100 => Some(RAlt    ),
+
+        102 => Some(Home    ),
+        103 => Some(UArrow  ),
+        104 => Some(PgUp    ),
+        105 => Some(LArrow  ),
+        106 => Some(RArrow  ),
+        107 => Some(End     ),
+        108 => Some(DArrow  ),
+        109 => Some(PgDn    ),
+        110 => Some(Insert  ),
+        111 => Some(Delete  ),
+        113 => Some(VolMute ),
+        114 => Some(VolDown ),
+        115 => Some(VolUp   ),
+        119 => Some(Pause   ),
+        121 => Some(NumSeparator),
+
+        // Hangul and Hanja share the Kana and Kanji keys
+        122 => Some(Kana    ),
+        123 => Some(Kanji   ),
+        125 => Some(LWin    ),
+        126 => Some(RWin    ),
+        127 => Some(Apps    ),
+
+        128 => Some(BStop   ),
+        138 => Some(Help    ),
+        140 => Some(App2    ),
+        142 => Some(Sleep   ),
+        155 => Some(Mail    ),
+        156 => Some(BFav    ),
+        157 => Some(App1    ),
+        158 => Some(BBack   ),
+        159 => Some(BFwd    ),
+        163 => Some(MNTrack ),
+        164 => Some(MPause  ),
+        165 => Some(MPTrack ),
+        166 => Some(MStop   ),
+        172 => Some(BHome   ),
+        173 => Some(BRef    ),
+
+        183 => Some(F13     ),
+        184 => Some(F14     ),
+        185 => Some(F15     ),
+        186 => Some(F16     ),
+        187 => Some(F17     ),
+        188 => Some(F18     ),
+        189 => Some(F19     ),
+        190 => Some(F20     ),
+        191 => Some(F21     ),
+        192 => Some(F22     ),
+        193 => Some(F23     ),
+        194 => Some(F24     ),
+
+        217 => Some(BSearch ),
+        226 => Some(MSelect ),
+
+        _ => None
+    }
+}
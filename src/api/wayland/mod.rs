@@ -0,0 +1,441 @@
+pub mod wrapper;
+pub mod soft_buffer;
+mod keycode;
+mod xkb;
+use self::wrapper::Connection;
+
+use wayland_client::Main;
+use wayland_client::protocol::wl_surface::WlSurface;
+use wayland_client::protocol::wl_subsurface::WlSubsurface;
+use wayland_protocols::xdg_shell::client::xdg_surface::{self, XdgSurface};
+use wayland_protocols::xdg_shell::client::xdg_toplevel::{self, XdgToplevel};
+
+use std::rc::Rc;
+use std::cell::Cell;
+
+use {Cursor, GrabMode, Rect};
+use api::backend::{WindowBackend, NativeWindow, EventSink, EventPump};
+use error::TubResult;
+use config::{WindowConfig, PixelFormat, InitialState};
+use event::{Event, ResizeType};
+
+/// The size that windows are created with when `WindowConfig::size` is `None`. Like on X11, the
+/// compositor doesn't pick a size for us.
+const DEFAULT_SIZE: (u32, u32) = (800, 600);
+
+/// Whether the libraries that the backend loads at runtime are installed
+#[inline]
+pub fn is_available() -> bool {
+    xkb::is_available()
+}
+
+/// The role that a window's surface has, which decides how the compositor treats it
+enum SurfaceRole {
+    Toplevel(Main<XdgSurface>, Main<XdgToplevel>),
+    Subsurface(Main<WlSubsurface>)
+}
+
+/// A window on a Wayland compositor. Top-level and owned windows are xdg-shell toplevels, and
+/// child windows are subsurfaces of their parent.
+///
+/// Wayland is a lot more restrictive than win32 or X11 about what clients are allowed to do, so
+/// some of the window functions don't do anything here:
+///
+/// * Windows can't find out or change where they are on the screen, so `get_inner_pos` and
+///   `get_outer_pos` always return `None` and `set_pos` only works on child windows.
+/// * Windows can't take focus or move the cursor, so `focus` and `set_cursor_pos` do nothing.
+/// * The cursor can't be grabbed, so `set_cursor_grab` and `confine_cursor` return `None` and
+///   there are no `MouseMotion` events.
+/// * Input methods aren't supported, so there are no composition events and `set_ime_position`
+///   does nothing. Text still comes through `ReceivedCharacter`.
+/// * Tablets need the tablet protocol, which tub doesn't use, so there are no pen events. Pens
+///   still move the mouse.
+/// * The compositor doesn't draw window decorations, so all windows are borderless.
+/// * Surfaces only appear on screen once something has been drawn to them.
+///
+/// This backend can be tested without a desktop session by running against weston's headless
+/// backend:
+///
+/// ```text
+/// weston --backend=headless-backend.so --socket=tub-test &
+/// WAYLAND_DISPLAY=tub-test TUB_BACKEND=wayland cargo test
+/// ```
+pub struct Window {
+    pub surface: Main<WlSurface>,
+    /// The ID of the surface, which the event callbacks use to identify the window. This is kept
+    /// separately because dead surfaces report an ID of 0.
+    id: u32,
+    role: SurfaceRole,
+    pub conn: Rc<Connection>,
+    /// The position of a child window relative to its parent. Top-level windows don't know where
+    /// they are.
+    child_pos: Cell<(i32, i32)>,
+    config: WindowConfig,
+    pixel_format: PixelFormat
+}
+
+impl Window {
+    /// Create a new window with the specified title and config
+    pub fn new(config: WindowConfig, pixel_format: PixelFormat, sink: EventSink) -> TubResult<Window> {
+        let conn = Rc::new(try!(Connection::new()));
+        Window::new_toplevel(conn, None, config, pixel_format, sink)
+    }
+
+    /// Creates a window that is owned by the calling window. Owned windows share a connection with
+    /// their owner, and the compositor is told to keep them above it.
+    pub fn new_owned(&self, config: WindowConfig, pixel_format: PixelFormat, sink: EventSink) -> TubResult<Window> {
+        let owner = match self.role {
+            SurfaceRole::Toplevel(_, ref toplevel) => Some(&***toplevel),
+            SurfaceRole::Subsurface(_)             => None
+        };
+
+        Window::new_toplevel(self.conn.clone(), owner, config, pixel_format, sink)
+    }
+
+    /// Creates a window that's drawn inside of the calling window, as a subsurface of it
+    pub fn new_child(&self, config: WindowConfig, pixel_format: PixelFormat, sink: EventSink) -> TubResult<Window> {
+        let conn = self.conn.clone();
+        let size = config.size.map(|s| (s.0 as u32, s.1 as u32)).unwrap_or(DEFAULT_SIZE);
+
+        let surface = conn.compositor.create_surface();
+        let surface_id = surface.as_ref().id();
+        let subsurface = conn.subcompositor.get_subsurface(&surface, &self.surface);
+        // Let the child update without waiting on its parent to commit
+        subsurface.set_desync();
+
+        sink.send(Event::Resized(ResizeType::Changed, size.0, size.1));
+        conn.callback_data.borrow_mut().add_window(surface_id, sink, size);
+
+        surface.commit();
+        conn.flush();
+
+        Ok(
+            Window {
+                surface: surface,
+                id: surface_id,
+                role: SurfaceRole::Subsurface(subsurface),
+                conn: conn,
+                child_pos: Cell::new((0, 0)),
+                config: config,
+                pixel_format: pixel_format
+            }
+        )
+    }
+
+    fn new_toplevel(conn: Rc<Connection>, owner: Option<&XdgToplevel>, config: WindowConfig, pixel_format: PixelFormat, sink: EventSink) -> TubResult<Window> {
+        let size = config.size.map(|s| (s.0 as u32, s.1 as u32)).unwrap_or(DEFAULT_SIZE);
+
+        let surface = conn.compositor.create_surface();
+        let surface_id = surface.as_ref().id();
+        let xdg_surface = conn.wm_base.get_xdg_surface(&surface);
+        let toplevel = xdg_surface.get_toplevel();
+
+        {
+            let callback_data = conn.callback_data.clone();
+            xdg_surface.quick_assign(move |xdg_surface, event, _| {
+                if let xdg_surface::Event::Configure{serial} = event {
+                    xdg_surface.ack_configure(serial);
+                    callback_data.borrow_mut().configure_surface(surface_id);
+                }
+            });
+        }
+
+        {
+            let callback_data = conn.callback_data.clone();
+
+            toplevel.quick_assign(move |_, event, _| {
+                match event {
+                    xdg_toplevel::Event::Configure{width, height, states} =>
+                        callback_data.borrow_mut().configure_toplevel(surface_id, (width as u32, height as u32), &states),
+
+                    xdg_toplevel::Event::Close =>
+                        callback_data.borrow_mut().send_close_requested(surface_id),
+
+                    _ => ()
+                }
+            });
+        }
+
+        toplevel.set_title(config.name.clone());
+        toplevel.set_parent(owner);
+
+        if !config.resizable {
+            toplevel.set_min_size(size.0 as i32, size.1 as i32);
+            toplevel.set_max_size(size.0 as i32, size.1 as i32);
+        }
+
+        match config.initial_state {
+            InitialState::Windowed  => (),
+            InitialState::Maximized => toplevel.set_maximized(),
+            InitialState::Minimized => toplevel.set_minimized()
+        }
+
+        sink.send(Event::Resized(ResizeType::Changed, size.0, size.1));
+        conn.callback_data.borrow_mut().add_window(surface_id, sink, size);
+
+        // The surface has to be committed without a buffer before the compositor sends the initial
+        // configure event, which has to be acknowledged before anything can be drawn.
+        surface.commit();
+        try!(conn.roundtrip());
+
+        Ok(
+            Window {
+                surface: surface,
+                id: surface_id,
+                role: SurfaceRole::Toplevel(xdg_surface, toplevel),
+                conn: conn,
+                child_pos: Cell::new((0, 0)),
+                config: config,
+                pixel_format: pixel_format
+            }
+        )
+    }
+
+    #[inline]
+    pub fn set_title(&self, title: &str) {
+        if let SurfaceRole::Toplevel(_, ref toplevel) = self.role {
+            toplevel.set_title(title.to_owned());
+            self.conn.flush();
+        }
+    }
+
+    /// Surfaces are shown whenever they have something drawn to them, so this just makes sure
+    /// that the compositor has the latest state of the surface.
+    #[inline]
+    pub fn show(&self) {
+        self.surface.commit();
+        self.conn.flush();
+    }
+
+    /// Hide the window by taking away its contents. The window shows up again the next time
+    /// something is drawn to it.
+    #[inline]
+    pub fn hide(&self) {
+        self.surface.attach(None, 0, 0);
+        self.surface.commit();
+        self.conn.flush();
+    }
+
+    /// Destroy the window's surface. Destroying a surface that's already been destroyed does
+    /// nothing, so this is safe to call more than once.
+    pub fn close(&self) {
+        match self.role {
+            SurfaceRole::Toplevel(ref xdg_surface, ref toplevel) => {
+                toplevel.destroy();
+                xdg_surface.destroy();
+            }
+            SurfaceRole::Subsurface(ref subsurface) => subsurface.destroy()
+        }
+        self.surface.destroy();
+
+        self.conn.callback_data.borrow_mut().close_window(self.id);
+        self.conn.flush();
+    }
+
+    /// Allow the window to take user input.
+    #[inline]
+    pub fn enable(&self) {
+        self.conn.callback_data.borrow_mut().set_enabled(self.id, true);
+    }
+
+    /// Disallow the window from taking user input.
+    #[inline]
+    pub fn disable(&self) {
+        self.conn.callback_data.borrow_mut().set_enabled(self.id, false);
+    }
+
+    #[inline]
+    pub fn get_inner_pos(&self) -> Option<(i32, i32)> {
+        match self.role {
+            SurfaceRole::Toplevel(..)  => None,
+            SurfaceRole::Subsurface(_) => Some(self.child_pos.get())
+        }
+    }
+
+    #[inline]
+    pub fn get_inner_size(&self) -> Option<(u32, u32)> {
+        self.conn.callback_data.borrow().get_size(self.id)
+    }
+
+    /// Only child windows can be moved, and their position is relative to their parent. The new
+    /// position takes effect the next time that the parent window is drawn.
+    #[inline]
+    pub fn set_pos(&self, x: i32, y: i32) -> Option<()> {
+        match self.role {
+            SurfaceRole::Toplevel(..)               => None,
+            SurfaceRole::Subsurface(ref subsurface) => {
+                subsurface.set_position(x, y);
+                self.child_pos.set((x, y));
+                self.conn.callback_data.borrow_mut().send_moved(self.id, (x, y));
+                self.conn.flush();
+                Some(())
+            }
+        }
+    }
+
+    #[inline]
+    pub fn set_inner_size(&self, x: u32, y: u32) -> Option<()> {
+        if let SurfaceRole::Toplevel(_, ref toplevel) = self.role {
+            if !self.config.resizable {
+                toplevel.set_min_size(x as i32, y as i32);
+                toplevel.set_max_size(x as i32, y as i32);
+                self.conn.flush();
+            }
+        }
+
+        self.conn.callback_data.borrow_mut().set_size(self.id, (x, y));
+        Some(())
+    }
+
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        self.conn.callback_data.borrow().is_focused(self.id)
+    }
+
+    #[inline]
+    pub fn set_cursor(&self, cursor: &Cursor) {
+        self.conn.callback_data.borrow_mut().set_cursor(self.id, cursor, &self.conn.shm);
+        self.conn.flush();
+    }
+
+    pub fn get_config(&self) -> &WindowConfig {
+        &self.config
+    }
+
+    pub fn get_pixel_format(&self) -> &PixelFormat {
+        &self.pixel_format
+    }
+}
+
+impl WindowBackend for Window {
+    fn new_owned<'a>(&'a self, config: WindowConfig, pixel_format: PixelFormat, sink: EventSink) -> TubResult<Box<WindowBackend + 'a>> {
+        Window::new_owned(self, config, pixel_format, sink).map(|w| Box::new(w) as Box<WindowBackend>)
+    }
+
+    fn new_child<'a>(&'a self, config: WindowConfig, pixel_format: PixelFormat, sink: EventSink) -> TubResult<Box<WindowBackend + 'a>> {
+        Window::new_child(self, config, pixel_format, sink).map(|w| Box::new(w) as Box<WindowBackend>)
+    }
+
+    #[inline]
+    fn set_title(&self, title: &str) {
+        Window::set_title(self, title);
+    }
+
+    #[inline]
+    fn show(&self) {
+        Window::show(self);
+    }
+
+    #[inline]
+    fn hide(&self) {
+        Window::hide(self);
+    }
+
+    #[inline]
+    fn close(&self) {
+        Window::close(self);
+    }
+
+    #[inline]
+    fn enable(&self) {
+        Window::enable(self);
+    }
+
+    #[inline]
+    fn disable(&self) {
+        Window::disable(self);
+    }
+
+    /// Wayland clients can't take focus for themselves
+    #[inline]
+    fn focus(&self) {}
+
+    #[inline]
+    fn get_inner_pos(&self) -> Option<(i32, i32)> {
+        Window::get_inner_pos(self)
+    }
+
+    /// Windows have no decorations, so this is the same as the inner position
+    #[inline]
+    fn get_outer_pos(&self) -> Option<(i32, i32)> {
+        Window::get_inner_pos(self)
+    }
+
+    #[inline]
+    fn get_inner_size(&self) -> Option<(u32, u32)> {
+        Window::get_inner_size(self)
+    }
+
+    /// Windows have no decorations, so this is the same as the inner size
+    #[inline]
+    fn get_outer_size(&self) -> Option<(u32, u32)> {
+        Window::get_inner_size(self)
+    }
+
+    #[inline]
+    fn set_pos(&self, x: i32, y: i32) -> Option<()> {
+        Window::set_pos(self, x, y)
+    }
+
+    #[inline]
+    fn set_inner_size(&self, x: u32, y: u32) -> Option<()> {
+        Window::set_inner_size(self, x, y)
+    }
+
+    #[inline]
+    fn is_active(&self) -> bool {
+        Window::is_active(self)
+    }
+
+    #[inline]
+    fn set_cursor(&self, cursor: &Cursor) {
+        Window::set_cursor(self, cursor);
+    }
+
+    /// Wayland clients can't move the cursor
+    #[inline]
+    fn set_cursor_pos(&self, _: i32, _: i32) {}
+
+    /// Grabbing the cursor needs the pointer constraints protocol, which tub doesn't use
+    #[inline]
+    fn set_cursor_grab(&self, _: GrabMode) -> Option<()> {
+        None
+    }
+
+    #[inline]
+    fn confine_cursor(&self, _: Option<Rect>) -> Option<()> {
+        None
+    }
+
+    /// Input methods on Wayland need the text-input protocol, which tub doesn't use
+    #[inline]
+    fn set_ime_position(&self, _: i32, _: i32) {}
+
+    /// Wayland windows don't have an event thread, so the events loop has to read their events
+    /// through the connection. Owned and child windows share their owner's connection.
+    #[inline]
+    fn event_pump(&self) -> Option<Rc<EventPump>> {
+        Some(self.conn.clone())
+    }
+
+    #[inline]
+    fn get_config(&self) -> &WindowConfig {
+        Window::get_config(self)
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> &PixelFormat {
+        Window::get_pixel_format(self)
+    }
+
+    #[inline]
+    fn native<'a>(&'a self) -> NativeWindow<'a> {
+        NativeWindow::Wayland(self)
+    }
+}
+
+impl Drop for Window {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
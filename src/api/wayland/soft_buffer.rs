@@ -0,0 +1,185 @@
+use wayland_client::Main;
+use wayland_client::protocol::wl_buffer::{self, WlBuffer};
+use wayland_client::protocol::wl_shm::{Format, WlShm};
+use wayland_client::protocol::wl_surface::WlSurface;
+use libc;
+
+use std::ptr;
+use std::slice;
+use std::fs::File;
+use std::rc::Rc;
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+
+use error::{TubResult, TubError};
+use super::Window;
+use super::wrapper::Connection;
+
+/// How many buffers can be handed to the compositor at once. With two, one can be drawn into while
+/// the compositor is still reading the other.
+const MAX_BUFFERS: usize = 2;
+
+/// A buffer of pixels that gets copied into `wl_shm` buffers and attached to a Wayland surface. The
+/// compositor reads `wl_shm` buffers whenever it likes until it releases them, so the pixels are
+/// drawn into separate memory and only copied over when they're presented.
+pub struct SoftBuffer<'w> {
+    conn: Rc<Connection>,
+    surface: Main<WlSurface>,
+    format: Format,
+    pixels: Vec<u32>,
+    size: (u32, u32),
+    buffers: Vec<ShmBuffer>,
+    /// Guarantees that this won't live longer than the window that created it
+    window_lifetime: PhantomData<&'w ()>
+}
+
+/// A `wl_buffer` along with the shared memory that backs it
+pub struct ShmBuffer {
+    pub buffer: Main<WlBuffer>,
+    /// Set while the compositor may be reading the buffer
+    busy: Rc<Cell<bool>>,
+    size: (u32, u32),
+    memory: *mut u32,
+    /// Kept so that the memory is closed along with the buffer
+    _file: File
+}
+
+impl<'w> SoftBuffer<'w> {
+    pub fn new(window: &'w Window) -> TubResult<SoftBuffer<'w>> {
+        let format = match window.get_config().transparent {
+            true  => Format::Argb8888,
+            false => Format::Xrgb8888
+        };
+
+        Ok(
+            SoftBuffer {
+                conn: window.conn.clone(),
+                surface: window.surface.clone(),
+                format: format,
+                pixels: Vec::new(),
+                size: (0, 0),
+                buffers: Vec::with_capacity(MAX_BUFFERS),
+                window_lifetime: PhantomData
+            }
+        )
+    }
+
+    /// Replace the pixels with new ones of the given size, cleared to black
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.pixels = vec![0; width as usize * height as usize];
+        self.size = (width, height);
+        // Buffers of the old size are useless now, but the compositor could still be reading them.
+        // Destroying them is fine, though, since it keeps its own reference to the memory.
+        self.buffers.clear();
+    }
+
+    #[inline]
+    pub fn pixels_mut(&mut self) -> &mut [u32] {
+        &mut self.pixels
+    }
+
+    /// Copy the pixels into a free `wl_shm` buffer and attach it to the window's surface. If the
+    /// compositor is still holding on to every buffer, this blocks until it releases one.
+    pub fn present(&mut self) -> TubResult<()> {
+        if self.size.0 == 0 || self.size.1 == 0 {
+            return Ok(());
+        }
+
+        let index = try!(self.free_buffer());
+        let buffer = &self.buffers[index];
+
+        unsafe{ ptr::copy_nonoverlapping(self.pixels.as_ptr(), buffer.memory, self.pixels.len()) };
+        buffer.busy.set(true);
+
+        self.surface.attach(Some(&buffer.buffer), 0, 0);
+        self.surface.damage(0, 0, self.size.0 as i32, self.size.1 as i32);
+        self.surface.commit();
+        self.conn.flush();
+        Ok(())
+    }
+
+    /// Get the index of a buffer that the compositor isn't using, creating one if there's room
+    fn free_buffer(&mut self) -> TubResult<usize> {
+        loop {
+            if let Some(index) = self.buffers.iter().position(|b| !b.busy.get()) {
+                return Ok(index);
+            }
+
+            if self.buffers.len() < MAX_BUFFERS {
+                let buffer = try!(ShmBuffer::new(&self.conn.shm, self.size, self.format));
+                self.buffers.push(buffer);
+                return Ok(self.buffers.len() - 1);
+            }
+
+            if !self.conn.dispatch() {
+                return Err(TubError::OsError("Lost the connection to the Wayland compositor".to_owned()));
+            }
+        }
+    }
+}
+
+impl ShmBuffer {
+    pub fn new(shm: &WlShm, size: (u32, u32), format: Format) -> TubResult<ShmBuffer> {
+        let stride = size.0 as i32 * 4;
+        let len = stride as usize * size.1 as usize;
+
+        unsafe {
+            let fd = libc::memfd_create(b"tub-soft-buffer\0".as_ptr() as *const _, libc::MFD_CLOEXEC);
+            if fd == -1 {
+                return Err(TubError::OsError("Could not create shared memory for the buffer".to_owned()));
+            }
+            let file = File::from_raw_fd(fd);
+
+            if libc::ftruncate(fd, len as libc::off_t) == -1 {
+                return Err(TubError::OsError("Could not create shared memory for the buffer".to_owned()));
+            }
+
+            let memory = libc::mmap(ptr::null_mut(), len, libc::PROT_READ | libc::PROT_WRITE, libc::MAP_SHARED, fd, 0);
+            if memory == libc::MAP_FAILED {
+                return Err(TubError::OsError("Could not map shared memory for the buffer".to_owned()));
+            }
+
+            // The buffer keeps its own reference to the pool's memory, so the pool can be destroyed
+            // straight away.
+            let pool = shm.create_pool(file.as_raw_fd(), len as i32);
+            let buffer = pool.create_buffer(0, size.0 as i32, size.1 as i32, stride, format);
+            pool.destroy();
+
+            let busy = Rc::new(Cell::new(false));
+            {
+                let busy = busy.clone();
+                buffer.quick_assign(move |_, event, _| {
+                    if let wl_buffer::Event::Release = event {
+                        busy.set(false);
+                    }
+                });
+            }
+
+            Ok(
+                ShmBuffer {
+                    buffer: buffer,
+                    busy: busy,
+                    size: size,
+                    memory: memory as *mut u32,
+                    _file: file
+                }
+            )
+        }
+    }
+}
+
+impl ShmBuffer {
+    /// Get the buffer's pixels. These must not be changed while the compositor may be reading them.
+    #[inline]
+    pub fn pixels_mut(&mut self) -> &mut [u32] {
+        unsafe{ slice::from_raw_parts_mut(self.memory, self.size.0 as usize * self.size.1 as usize) }
+    }
+}
+
+impl Drop for ShmBuffer {
+    fn drop(&mut self) {
+        self.buffer.destroy();
+        unsafe{ libc::munmap(self.memory as *mut _, self.size.0 as usize * self.size.1 as usize * 4) };
+    }
+}
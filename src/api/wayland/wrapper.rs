@@ -0,0 +1,834 @@
+use wayland_client::{Display, EventQueue, GlobalManager, Main};
+use wayland_client::protocol::wl_compositor::WlCompositor;
+use wayland_client::protocol::wl_subcompositor::WlSubcompositor;
+use wayland_client::protocol::wl_buffer::WlBuffer;
+use wayland_client::protocol::wl_shm::{Format, WlShm};
+use wayland_client::protocol::wl_surface::WlSurface;
+use wayland_client::protocol::wl_seat::{self, WlSeat};
+use wayland_client::protocol::wl_pointer::{self, WlPointer};
+use wayland_client::protocol::wl_keyboard::{self, WlKeyboard};
+use wayland_client::protocol::wl_touch::{self, WlTouch};
+use wayland_protocols::xdg_shell::client::xdg_wm_base::{self, XdgWmBase};
+use wayland_protocols::xdg_shell::client::xdg_toplevel;
+use wayland_cursor::CursorTheme;
+
+use std::io;
+use std::cmp;
+use std::mem;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::File;
+use std::os::unix::io::{FromRawFd, RawFd};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use {Cursor, CursorType};
+use api::backend::{EventSink, EventPump};
+use api::evdev;
+use api::xcursor::premultiply;
+use error::{TubResult, TubError};
+use event::{Event, ResizeType, ModifiersState, PressState, ScrollDelta, TouchPhase};
+use super::keycode;
+use super::soft_buffer::ShmBuffer;
+use super::xkb::Keymap;
+
+/// The maximum number of milliseconds between two clicks for them to count as a double click.
+const DOUBLE_CLICK_TIME: u32 = 500;
+
+/// The key repeat rate and delay that are used until the compositor sends its own, in repeats per
+/// second and milliseconds
+const DEFAULT_REPEAT_INFO: (i32, i32) = (25, 600);
+
+/// The size of the cursors loaded from the cursor theme
+const CURSOR_SIZE: u32 = 24;
+
+// The masks of the standard modifiers in the state that `wl_keyboard` sends. These are the same as
+// X11's masks, since xkbcommon puts the real modifiers first and in the same order as X does.
+const XKB_SHIFT: u32 = 1 << 0;
+const XKB_LOCK: u32 = 1 << 1;
+const XKB_CONTROL: u32 = 1 << 2;
+const XKB_MOD1: u32 = 1 << 3;
+const XKB_MOD2: u32 = 1 << 4;
+const XKB_MOD4: u32 = 1 << 6;
+
+// Linux evdev button codes, which is what `wl_pointer` sends
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+const BTN_MIDDLE: u32 = 0x112;
+const BTN_SIDE: u32 = 0x113;
+const BTN_EXTRA: u32 = 0x114;
+
+/// A connection to the Wayland compositor, along with the globals that tub uses. Like on X11, every
+/// top-level window opens its own connection, which is then shared with any owned or child windows.
+///
+/// Wayland event queues can't be sent between threads, so instead of getting an event thread like
+/// on win32 and X11, the connection is an `EventPump` that the events loop reads events through
+/// whenever it's polled.
+pub struct Connection {
+    pub display: Display,
+    queue: RefCell<EventQueue>,
+    pub compositor: Main<WlCompositor>,
+    pub subcompositor: Main<WlSubcompositor>,
+    pub wm_base: Main<XdgWmBase>,
+    pub shm: Main<WlShm>,
+    /// Kept so that the seat's input devices stay alive for as long as the connection does
+    _seat: Option<Main<WlSeat>>,
+    pub callback_data: Rc<RefCell<CallbackData>>
+}
+
+impl Connection {
+    pub fn new() -> TubResult<Connection> {
+        let display = try!(Display::connect_to_env().map_err(|e|
+            TubError::OsError(format!("Could not connect to the Wayland compositor: {}", e))));
+
+        let mut queue = display.create_event_queue();
+        let globals = GlobalManager::new(&display.attach(queue.token()));
+        try!(queue.sync_roundtrip(&mut (), |_, _, _| ()).map_err(io_error));
+
+        let compositor = try!(globals.instantiate_range::<WlCompositor>(1, 4).map_err(|_| missing_global("wl_compositor")));
+        let subcompositor = try!(globals.instantiate_exact::<WlSubcompositor>(1).map_err(|_| missing_global("wl_subcompositor")));
+        let wm_base = try!(globals.instantiate_exact::<XdgWmBase>(1).map_err(|_| missing_global("xdg_wm_base")));
+        let shm = try!(globals.instantiate_exact::<WlShm>(1).map_err(|_| missing_global("wl_shm")));
+
+        // The compositor pings clients to check that they're still responding
+        wm_base.quick_assign(|wm_base, event, _| {
+            if let xdg_wm_base::Event::Ping{serial} = event {
+                wm_base.pong(serial);
+            }
+        });
+
+        let callback_data = Rc::new(RefCell::new(CallbackData::new(CursorTheme::load(CURSOR_SIZE, &shm), compositor.create_surface())));
+
+        // Compositors without any input devices (like weston's headless backend) don't need to
+        // have a seat, so a missing one isn't an error.
+        let seat = globals.instantiate_range::<WlSeat>(1, 5).ok();
+        if let Some(ref seat) = seat {
+            let callback_data = callback_data.clone();
+            seat.quick_assign(move |seat, event, _| seat_callback(&seat, event, &callback_data));
+        }
+
+        // Get the seat's capabilities, so that input devices exist before any windows are created
+        try!(queue.sync_roundtrip(&mut (), |_, _, _| ()).map_err(io_error));
+
+        Ok(
+            Connection {
+                display: display,
+                queue: RefCell::new(queue),
+                compositor: compositor,
+                subcompositor: subcompositor,
+                wm_base: wm_base,
+                shm: shm,
+                _seat: seat,
+                callback_data: callback_data
+            }
+        )
+    }
+
+    /// Send any pending requests to the compositor
+    #[inline]
+    pub fn flush(&self) {
+        self.display.flush().ok();
+    }
+
+    /// Read whatever events the compositor has sent and hand them to the windows they're for,
+    /// without blocking.
+    fn dispatch_events(&self) {
+        let mut queue = self.queue.borrow_mut();
+
+        self.flush();
+        if let Some(guard) = queue.prepare_read() {
+            // This returns `WouldBlock` when there's nothing to read, which isn't a problem
+            guard.read_events().ok();
+        }
+        queue.dispatch_pending(&mut (), |_, _, _| ()).ok();
+
+        self.callback_data.borrow_mut().repeat_key();
+    }
+
+    /// Block until the compositor sends at least one event and hand it to the window it's for.
+    /// Returns false if the connection to the compositor has been lost.
+    pub fn dispatch(&self) -> bool {
+        self.flush();
+        self.queue.borrow_mut().dispatch(&mut (), |_, _, _| ()).is_ok()
+    }
+
+    /// Block until the compositor has processed all of the requests sent so far
+    pub fn roundtrip(&self) -> TubResult<()> {
+        self.queue.borrow_mut().sync_roundtrip(&mut (), |_, _, _| ()).map(|_| ()).map_err(io_error)
+    }
+}
+
+impl EventPump for Connection {
+    #[inline]
+    fn dispatch_pending(&self) {
+        self.dispatch_events();
+    }
+
+    #[inline]
+    fn fd(&self) -> RawFd {
+        self.display.get_connection_fd()
+    }
+
+    #[inline]
+    fn next_timeout(&self) -> Option<Instant> {
+        self.callback_data.borrow().repeat.map(|r| r.1)
+    }
+}
+
+/// State shared between the event callbacks and the windows using the connection. Wayland
+/// callbacks are only ever run from inside of `Connection::dispatch`, on the thread that owns the
+/// windows, so this doesn't need a mutex like on X11.
+pub struct CallbackData {
+    win_vec: Vec<WindowDataIntern>,
+    pointer: Option<Main<WlPointer>>,
+    keyboard: Option<Main<WlKeyboard>>,
+    touch: Option<Main<WlTouch>>,
+    /// The surface that the pointer is currently over and the serial of the event that said so,
+    /// which is needed to change the cursor.
+    pointer_focus: Option<(u32, u32)>,
+    keyboard_focus: Option<u32>,
+    /// The fingers that are touching the screen. `wl_touch` only says which surface a finger is
+    /// over when it first touches, and doesn't say where it was lifted.
+    touches: Vec<TouchPoint>,
+    /// The modifier state from the last `wl_keyboard` modifiers event
+    modifiers: ModifiersState,
+    /// The keyboard layout, if the compositor sent one that xkbcommon could load
+    keymap: Option<Keymap>,
+    /// The rate and delay from the last `wl_keyboard` repeat info event
+    repeat_info: (i32, i32),
+    /// The key that's being held down and when it next repeats. Wayland leaves key repeat to the
+    /// client, so the connection repeats the key whenever it's dispatched after that time.
+    repeat: Option<(u32, Instant)>,
+    scroll: ScrollFrame,
+    cursor_theme: CursorTheme,
+    cursor_surface: Main<WlSurface>
+}
+
+impl CallbackData {
+    fn new(cursor_theme: CursorTheme, cursor_surface: Main<WlSurface>) -> CallbackData {
+        CallbackData {
+            win_vec: Vec::with_capacity(4),
+            pointer: None,
+            keyboard: None,
+            touch: None,
+            pointer_focus: None,
+            keyboard_focus: None,
+            touches: Vec::new(),
+            modifiers: ModifiersState::default(),
+            keymap: None,
+            repeat_info: DEFAULT_REPEAT_INFO,
+            repeat: None,
+            scroll: ScrollFrame::default(),
+            cursor_theme: cursor_theme,
+            cursor_surface: cursor_surface
+        }
+    }
+
+    #[inline]
+    pub fn add_window(&mut self, surface: u32, sink: EventSink, size: (u32, u32)) {
+        self.win_vec.push(WindowDataIntern::new(surface, sink, size));
+    }
+
+    /// Set whether or not the window takes user input. Like X, Wayland has no concept of a disabled
+    /// window, so input events for disabled windows are dropped here instead.
+    #[inline]
+    pub fn set_enabled(&mut self, surface: u32, enabled: bool) {
+        if let Some(w) = self.get_window(surface) {
+            w.enabled = enabled;
+        }
+    }
+
+    #[inline]
+    pub fn is_focused(&self, surface: u32) -> bool {
+        self.keyboard_focus == Some(surface)
+    }
+
+    #[inline]
+    pub fn get_size(&self, surface: u32) -> Option<(u32, u32)> {
+        self.win_vec.iter().find(|w| w.surface == surface).map(|w| w.size)
+    }
+
+    /// Set the size of the window. Wayland clients pick their own size, so this just records the
+    /// new size and tells the window about it.
+    pub fn set_size(&mut self, surface: u32, size: (u32, u32)) {
+        if let Some(w) = self.get_window(surface) {
+            if w.size != size {
+                w.size = size;
+                send_event(w, Event::Resized(w.state, size.0, size.1), false);
+            }
+        }
+    }
+
+    /// Tell a child window that it's been moved
+    pub fn send_moved(&mut self, surface: u32, pos: (i32, i32)) {
+        if let Some(w) = self.get_window(surface) {
+            send_event(w, Event::Moved(pos.0, pos.1), false);
+        }
+    }
+
+    /// Set the window's cursor. Custom cursors are copied into a buffer the first time that the
+    /// window uses them, which is kept until the window is closed.
+    pub fn set_cursor(&mut self, surface: u32, cursor: &Cursor, shm: &WlShm) {
+        if let Some(w) = self.get_window(surface) {
+            if let Cursor::Custom(ref custom) = *cursor {
+                if !w.cursor_buffers.contains_key(&custom.id()) {
+                    let mut buffer = match ShmBuffer::new(shm, (custom.width(), custom.height()), Format::Argb8888) {
+                        Ok(b)  => b,
+                        Err(_) => return
+                    };
+                    for (pixel, &argb) in buffer.pixels_mut().iter_mut().zip(custom.pixels()) {
+                        *pixel = premultiply(argb);
+                    }
+                    w.cursor_buffers.insert(custom.id(), buffer);
+                }
+            }
+
+            w.cursor = cursor.clone();
+        }
+
+        if self.pointer_focus.map(|f| f.0) == Some(surface) {
+            self.update_cursor();
+        }
+    }
+
+    /// Record the size and state that the compositor wants the window to have. These don't take
+    /// effect until the compositor finishes configuring the surface.
+    pub fn configure_toplevel(&mut self, surface: u32, size: (u32, u32), states: &[u8]) {
+        if let Some(w) = self.get_window(surface) {
+            // The states are an array of native-endian u32s
+            let has_state = |state: xdg_toplevel::State| {
+                states.chunks(4).any(|s| s.len() == 4 && [s[0], s[1], s[2], s[3]] == (state as u32).to_ne_bytes())
+            };
+
+            w.pending_state = match has_state(xdg_toplevel::State::Maximized) {
+                true  => ResizeType::Maximized,
+                false => ResizeType::Changed
+            };
+
+            // A size of zero means that the window gets to pick its own size
+            if size.0 != 0 && size.1 != 0 {
+                w.pending_size = Some(size);
+            }
+        }
+    }
+
+    pub fn configure_surface(&mut self, surface: u32) {
+        if let Some(w) = self.get_window(surface) {
+            let size = w.pending_size.take().unwrap_or(w.size);
+
+            if size != w.size || w.pending_state != w.state {
+                w.size = size;
+                w.state = w.pending_state;
+                send_event(w, Event::Resized(w.state, size.0, size.1), false);
+
+                // The compositor keeps showing the old buffer until one with the new size is attached
+                send_event(w, Event::RedrawRequested, false);
+            }
+        }
+    }
+
+    /// Tell a window that the user wants it closed
+    pub fn send_close_requested(&mut self, surface: u32) {
+        if let Some(w) = self.get_window(surface) {
+            send_event(w, Event::CloseRequested, false);
+        }
+    }
+
+    /// Forget about a window and send it the `Destroyed` event
+    pub fn close_window(&mut self, surface: u32) {
+        if let Some(index) = self.win_vec.iter().position(|w| w.surface == surface) {
+            self.win_vec.remove(index).sink.send(Event::Destroyed);
+        }
+    }
+
+    /// Send a repeat of the held key if one is due
+    fn repeat_key(&mut self) {
+        let (key, next) = match self.repeat {
+            Some(r) if r.1 <= Instant::now() => r,
+            _ => return
+        };
+        if self.repeat_info.0 <= 0 {
+            self.repeat = None;
+            return;
+        }
+
+        // If the loop wasn't run for a while, repeat from now instead of sending a burst of repeats
+        let interval = Duration::from_secs(1) / self.repeat_info.0 as u32;
+        self.repeat = Some((key, cmp::max(next, Instant::now()) + interval));
+
+        send_key(self, key, PressState::Held);
+    }
+
+    /// Set the pointer's image to the cursor of the window that it's over
+    fn update_cursor(&mut self) {
+        let (surface, serial) = match self.pointer_focus {
+            Some(f) => f,
+            None    => return
+        };
+        let window = match self.win_vec.iter().find(|w| w.surface == surface) {
+            Some(w) => w,
+            None    => return
+        };
+        let pointer = match self.pointer {
+            Some(ref p) => p,
+            None        => return
+        };
+
+        let (buffer, (width, height), (hotspot_x, hotspot_y)): (&WlBuffer, _, _) = match window.cursor {
+            Cursor::System(CursorType::Invisible) => {
+                pointer.set_cursor(serial, None, 0, 0);
+                return;
+            }
+
+            Cursor::System(cursor_type) => {
+                // Not every theme has every cursor, so fall back to the standard arrow if the theme
+                // is missing the one we want.
+                let cursor = match self.cursor_theme.get_cursor(cursor_name(cursor_type)) {
+                    Some(c) => c,
+                    None    => match self.cursor_theme.get_cursor(cursor_name(CursorType::Arrow)) {
+                        Some(c) => c,
+                        None    => return
+                    }
+                };
+
+                let image = &cursor[0];
+                (image, image.dimensions(), image.hotspot())
+            }
+
+            Cursor::Custom(ref custom) => match window.cursor_buffers.get(&custom.id()) {
+                Some(b) => (&b.buffer, (custom.width(), custom.height()), custom.hotspot()),
+                None    => return
+            }
+        };
+
+        self.cursor_surface.attach(Some(buffer), 0, 0);
+        self.cursor_surface.damage(0, 0, width as i32, height as i32);
+        self.cursor_surface.commit();
+        pointer.set_cursor(serial, Some(&self.cursor_surface), hotspot_x as i32, hotspot_y as i32);
+    }
+
+    fn get_window(&mut self, surface: u32) -> Option<&mut WindowDataIntern> {
+        self.win_vec.iter_mut().find(|w| w.surface == surface)
+    }
+}
+
+/// The scrolling that `wl_pointer` has reported since the last frame event. Compositors split a
+/// single scroll into several events, which get combined into one `MouseWheel` event when the frame
+/// ends.
+#[derive(Default)]
+struct ScrollFrame {
+    /// The distance scrolled, in surface coordinates
+    pixels: (f64, f64),
+    /// The distance scrolled in wheel notches, which is only sent for mouse wheels
+    discrete: (i32, i32),
+    source: Option<wl_pointer::AxisSource>,
+    /// Whether the user lifted their fingers off of the touchpad in this frame
+    stopped: bool,
+    /// Whether a touchpad scroll is in progress. This is the only field that's kept between frames.
+    scrolling: bool
+}
+
+/// A finger that's touching the screen
+#[derive(Clone, Copy)]
+struct TouchPoint {
+    id: i32,
+    surface: u32,
+    pos: (i32, i32)
+}
+
+/// Struct that contains information about the window wrapper to the event callbacks.
+struct WindowDataIntern {
+    /// The ID of the window's surface, which input events use to say which window they're for
+    surface: u32,
+    sink: EventSink,
+    enabled: bool,
+    size: (u32, u32),
+    state: ResizeType,
+    /// The size and state from the last toplevel configure event, which are applied once the
+    /// surface configure event arrives.
+    pending_size: Option<(u32, u32)>,
+    pending_state: ResizeType,
+    cursor: Cursor,
+    /// The buffers holding the custom cursors that the window has used, by cursor ID
+    cursor_buffers: HashMap<usize, ShmBuffer>,
+    /// The button and time of the last mouse press, used to detect double clicks
+    last_click: Option<(u32, u32)>
+}
+
+impl WindowDataIntern {
+    #[inline]
+    fn new(surface: u32, sink: EventSink, size: (u32, u32)) -> WindowDataIntern {
+        WindowDataIntern {
+            surface: surface,
+            sink: sink,
+            enabled: true,
+            size: size,
+            state: ResizeType::Changed,
+            pending_size: None,
+            pending_state: ResizeType::Changed,
+            cursor: Cursor::System(CursorType::Arrow),
+            cursor_buffers: HashMap::new(),
+            last_click: None
+        }
+    }
+}
+
+fn seat_callback(seat: &Main<WlSeat>, event: wl_seat::Event, data: &Rc<RefCell<CallbackData>>) {
+    if let wl_seat::Event::Capabilities{capabilities} = event {
+        let mut data_ref = data.borrow_mut();
+
+        if capabilities.contains(wl_seat::Capability::Pointer) && data_ref.pointer.is_none() {
+            let pointer = seat.get_pointer();
+            let data = data.clone();
+            pointer.quick_assign(move |_, event, _| pointer_callback(event, &mut data.borrow_mut()));
+            data_ref.pointer = Some(pointer);
+        }
+
+        if capabilities.contains(wl_seat::Capability::Keyboard) && data_ref.keyboard.is_none() {
+            let keyboard = seat.get_keyboard();
+            let data = data.clone();
+            keyboard.quick_assign(move |_, event, _| keyboard_callback(event, &mut data.borrow_mut()));
+            data_ref.keyboard = Some(keyboard);
+        }
+
+        if capabilities.contains(wl_seat::Capability::Touch) && data_ref.touch.is_none() {
+            let touch = seat.get_touch();
+            let data = data.clone();
+            touch.quick_assign(move |_, event, _| touch_callback(event, &mut data.borrow_mut()));
+            data_ref.touch = Some(touch);
+        }
+    }
+}
+
+fn pointer_callback(event: wl_pointer::Event, data: &mut CallbackData) {
+    use wayland_client::protocol::wl_pointer::Event as PointerEvent;
+    use event::ClickType;
+
+    match event {
+        PointerEvent::Enter{serial, surface, surface_x, surface_y} => {
+            let surface = surface.as_ref().id();
+            data.pointer_focus = Some((surface, serial));
+            data.update_cursor();
+
+            if let Some(w) = data.get_window(surface) {
+                send_event(w, Event::MouseEnter, true);
+                send_event(w, Event::MouseMoved(surface_x as i32, surface_y as i32), true);
+            }
+        }
+
+        PointerEvent::Leave{surface, ..} => {
+            let surface = surface.as_ref().id();
+            data.pointer_focus = None;
+
+            if let Some(w) = data.get_window(surface) {
+                send_event(w, Event::MouseLeave, true);
+            }
+        }
+
+        PointerEvent::Motion{surface_x, surface_y, ..} => {
+            if let Some((surface, _)) = data.pointer_focus {
+                if let Some(w) = data.get_window(surface) {
+                    send_event(w, Event::MouseMoved(surface_x as i32, surface_y as i32), true);
+                }
+            }
+        }
+
+        PointerEvent::Button{time, button, state, ..} => {
+            let surface = match data.pointer_focus {
+                Some((s, _)) => s,
+                None         => return
+            };
+            let mbutton = match mbutton_from_evdev(button) {
+                Some(b) => b,
+                None    => return
+            };
+
+            let modifiers = data.modifiers;
+            if let Some(w) = data.get_window(surface) {
+                let click_type = match state {
+                    wl_pointer::ButtonState::Released => ClickType::Released,
+
+                    // Wayland doesn't report double clicks, so they have to be detected by hand
+                    _ => match w.last_click {
+                        Some((b, t)) if b == button && time.wrapping_sub(t) < DOUBLE_CLICK_TIME => {
+                            w.last_click = None;
+                            ClickType::Double
+                        }
+
+                        _ => {
+                            w.last_click = Some((button, time));
+                            ClickType::Single
+                        }
+                    }
+                };
+
+                send_event(w, Event::MButtonInput(click_type, mbutton, modifiers), true);
+            }
+        }
+
+        PointerEvent::Axis{axis, value, ..} => {
+            match axis {
+                wl_pointer::Axis::HorizontalScroll => data.scroll.pixels.0 += value,
+                _                                  => data.scroll.pixels.1 += value
+            }
+
+            // Frame events only exist since version 5, and before that every axis event stands on
+            // its own
+            if data.pointer.as_ref().map_or(true, |p| p.as_ref().version() < 5) {
+                send_scroll(data);
+            }
+        }
+
+        PointerEvent::AxisDiscrete{axis, discrete} => match axis {
+            wl_pointer::Axis::HorizontalScroll => data.scroll.discrete.0 += discrete,
+            _                                  => data.scroll.discrete.1 += discrete
+        },
+
+        PointerEvent::AxisSource{axis_source} => data.scroll.source = Some(axis_source),
+        PointerEvent::AxisStop{..} => data.scroll.stopped = true,
+        PointerEvent::Frame => send_scroll(data),
+
+        _ => ()
+    }
+}
+
+/// Send the scrolling from the current pointer frame to the window under the pointer
+fn send_scroll(data: &mut CallbackData) {
+    let scrolling = data.scroll.scrolling;
+    let frame = mem::replace(&mut data.scroll, ScrollFrame::default());
+
+    // Touchpads tell us when the user starts and stops scrolling, but wheels don't
+    let phase = match frame.source {
+        Some(wl_pointer::AxisSource::Finger) |
+        Some(wl_pointer::AxisSource::Continuous) => match (scrolling, frame.stopped) {
+            (false, false) => TouchPhase::Started,
+            (true, false)  => TouchPhase::Moved,
+            (true, true)   => TouchPhase::Ended,
+            (false, true)  => return
+        },
+        _ => TouchPhase::Moved
+    };
+    data.scroll.scrolling = phase == TouchPhase::Started || phase == TouchPhase::Moved;
+
+    if phase == TouchPhase::Moved && frame.pixels == (0.0, 0.0) && frame.discrete == (0, 0) {
+        return;
+    }
+
+    // Wayland's axes point right and down, and tub's point right and up
+    let delta = match frame.discrete {
+        (0, 0) => ScrollDelta::Pixels(frame.pixels.0 as f32, -frame.pixels.1 as f32),
+        (x, y) => ScrollDelta::Lines(x as f32, -y as f32)
+    };
+
+    if let Some((surface, _)) = data.pointer_focus {
+        if let Some(w) = data.get_window(surface) {
+            send_event(w, Event::MouseWheel(delta, phase), true);
+        }
+    }
+}
+
+fn keyboard_callback(event: wl_keyboard::Event, data: &mut CallbackData) {
+    use wayland_client::protocol::wl_keyboard::Event as KeyboardEvent;
+
+    match event {
+        KeyboardEvent::Keymap{format, fd, size} => {
+            data.keymap = match format {
+                wl_keyboard::KeymapFormat::XkbV1 => Keymap::from_fd(fd, size),
+                _                                => None
+            };
+            unsafe{ drop(File::from_raw_fd(fd)) };
+        }
+
+        KeyboardEvent::Enter{surface, ..} => {
+            let surface = surface.as_ref().id();
+            data.keyboard_focus = Some(surface);
+
+            if let Some(w) = data.get_window(surface) {
+                send_event(w, Event::Focused(true), false);
+            }
+        }
+
+        KeyboardEvent::Leave{surface, ..} => {
+            data.keyboard_focus = None;
+            data.repeat = None;
+
+            if let Some(w) = data.get_window(surface.as_ref().id()) {
+                send_event(w, Event::Focused(false), false);
+            }
+        }
+
+        KeyboardEvent::Key{key, state, ..} => {
+            let press_state = match state {
+                wl_keyboard::KeyState::Released => PressState::Released,
+                _                                => PressState::Pressed
+            };
+
+            // Pressing a key that doesn't repeat, like a modifier, keeps repeating the held key
+            match press_state {
+                PressState::Released => if data.repeat.map(|r| r.0) == Some(key) {
+                    data.repeat = None;
+                },
+                _ => if data.repeat_info.0 > 0 && data.keymap.as_ref().map_or(false, |k| k.key_repeats(key)) {
+                    data.repeat = Some((key, Instant::now() + Duration::from_millis(data.repeat_info.1.max(0) as u64)));
+                }
+            }
+
+            send_key(data, key, press_state);
+        }
+
+        KeyboardEvent::RepeatInfo{rate, delay} => {
+            data.repeat_info = (rate, delay);
+            if rate <= 0 {
+                data.repeat = None;
+            }
+        }
+
+        KeyboardEvent::Modifiers{mods_depressed, mods_latched, mods_locked, group, ..} => {
+            if let Some(ref mut keymap) = data.keymap {
+                keymap.update_mask(mods_depressed, mods_latched, mods_locked, group);
+            }
+
+            let active = mods_depressed | mods_latched;
+
+            data.modifiers = ModifiersState {
+                shift: active & XKB_SHIFT != 0,
+                ctrl: active & XKB_CONTROL != 0,
+                alt: active & XKB_MOD1 != 0,
+                logo: active & XKB_MOD4 != 0,
+                caps_lock: mods_locked & XKB_LOCK != 0,
+                num_lock: mods_locked & XKB_MOD2 != 0
+            };
+        }
+
+        _ => ()
+    }
+}
+
+/// Send the events for a key to the window with keyboard focus
+fn send_key(data: &mut CallbackData, key: u32, press_state: PressState) {
+    let modifiers = data.modifiers;
+    // Without a keymap, keys can only be translated by where they are on the keyboard, and there's
+    // no way to know what text they type.
+    let (k, text) = match data.keymap {
+        Some(ref mut keymap) => (
+            keymap.key(key, modifiers.num_lock),
+            match press_state {
+                PressState::Released => String::new(),
+                _                    => keymap.text(key)
+            }
+        ),
+        None => (keycode::key_from_evdev(key), String::new())
+    };
+
+    if let Some(surface) = data.keyboard_focus {
+        if let Some(w) = data.get_window(surface) {
+            send_event(w, Event::KeyInput(press_state, k, evdev::scancode_from_evdev(key), modifiers), true);
+            for c in text.chars() {
+                send_event(w, Event::ReceivedCharacter(c), true);
+            }
+        }
+    }
+}
+
+fn touch_callback(event: wl_touch::Event, data: &mut CallbackData) {
+    use wayland_client::protocol::wl_touch::Event as TouchEvent;
+
+    let (touch, phase) = match event {
+        TouchEvent::Down{surface, id, x, y, ..} => {
+            data.touches.push(TouchPoint{ id: id, surface: surface.as_ref().id(), pos: (x as i32, y as i32) });
+            (data.touches.len() - 1, TouchPhase::Started)
+        }
+
+        TouchEvent::Motion{id, x, y, ..} => match data.touches.iter().position(|t| t.id == id) {
+            Some(i) => {
+                data.touches[i].pos = (x as i32, y as i32);
+                (i, TouchPhase::Moved)
+            }
+            None    => return
+        },
+
+        TouchEvent::Up{id, ..} => match data.touches.iter().position(|t| t.id == id) {
+            Some(i) => (i, TouchPhase::Ended),
+            None    => return
+        },
+
+        // The compositor has taken over every touch, like for a gesture of its own
+        TouchEvent::Cancel => {
+            for touch in mem::replace(&mut data.touches, Vec::new()) {
+                send_touch(data, &touch, TouchPhase::Cancelled);
+            }
+            return;
+        }
+
+        _ => return
+    };
+
+    let touch = match phase {
+        TouchPhase::Ended => data.touches.remove(touch),
+        _                 => data.touches[touch]
+    };
+    send_touch(data, &touch, phase);
+}
+
+/// Send a touch event to the window that the touch started on. Wayland touches don't report
+/// pressure.
+fn send_touch(data: &mut CallbackData, touch: &TouchPoint, phase: TouchPhase) {
+    if let Some(w) = data.get_window(touch.surface) {
+        let event = Event::Touch{ id: touch.id as u32 as u64, phase: phase, x: touch.pos.0, y: touch.pos.1, pressure: None };
+        send_event(w, event, true);
+    }
+}
+
+/// Send an event to a window. Input events are dropped if the window is disabled.
+#[inline]
+fn send_event(win_data: &WindowDataIntern, event: Event, is_input: bool) {
+    if win_data.enabled || !is_input {
+        win_data.sink.send(event);
+    }
+}
+
+fn mbutton_from_evdev(button: u32) -> Option<::event::MButton> {
+    use event::MButton::*;
+
+    match button {
+        BTN_LEFT   => Some(Left),
+        BTN_RIGHT  => Some(Right),
+        BTN_MIDDLE => Some(Middle),
+        BTN_SIDE   => Some(Button4),
+        BTN_EXTRA  => Some(Button5),
+        _          => None
+    }
+}
+
+/// Get the name of the cursor theme image for a cursor type. These are the names of the X cursor font
+/// shapes, which every cursor theme provides.
+fn cursor_name(cursor_type: CursorType) -> &'static str {
+    use CursorType::*;
+
+    match cursor_type {
+        AppStarting     => "left_ptr_watch",
+        Arrow           => "left_ptr",
+        Crosshair       => "crosshair",
+        Hand            => "hand2",
+        Help            => "question_arrow",
+        Text            => "xterm",
+        Prohibited      => "crossed_circle",
+        ResizeAll       => "fleur",
+        ResizeNESW      => "size_bdiag",
+        ResizeNWSE      => "size_fdiag",
+        ResizeVertical  => "sb_v_double_arrow",
+        ResizeHoriz     => "sb_h_double_arrow",
+        UpArrow         => "sb_up_arrow",
+        Wait            => "watch",
+        // Invisible cursors are handled by not giving the pointer a surface
+        Invisible       => "left_ptr"
+    }
+}
+
+fn io_error(e: io::Error) -> TubError {
+    TubError::OsError(format!("Lost connection to the Wayland compositor: {}", e))
+}
+
+fn missing_global(name: &str) -> TubError {
+    TubError::OsError(format!("The Wayland compositor doesn't support {}", name))
+}
@@ -0,0 +1,317 @@
+use libc;
+
+use std::ptr;
+use std::mem;
+use std::env;
+use std::ffi::CString;
+use std::sync::{Once, ONCE_INIT};
+use std::os::raw::{c_char, c_int, c_void};
+use std::os::unix::io::RawFd;
+
+use event::Key;
+use keymap;
+
+#[allow(non_camel_case_types)]
+type xkb_context = c_void;
+#[allow(non_camel_case_types)]
+type xkb_keymap = c_void;
+#[allow(non_camel_case_types)]
+type xkb_state = c_void;
+#[allow(non_camel_case_types)]
+type xkb_compose_table = c_void;
+#[allow(non_camel_case_types)]
+type xkb_compose_state = c_void;
+
+const XKB_KEYMAP_FORMAT_TEXT_V1: c_int = 1;
+
+const XKB_COMPOSE_COMPOSING: c_int = 1;
+const XKB_COMPOSE_COMPOSED: c_int = 2;
+const XKB_COMPOSE_CANCELLED: c_int = 3;
+
+// The range of numpad keysyms that num lock affects, from XK_KP_Space to XK_KP_9
+const XKB_KEY_KP_SPACE: u32 = 0xFF80;
+const XKB_KEY_KP_9: u32 = 0xFFB9;
+
+/// The xkbcommon functions that keymaps use. libxkbcommon is loaded at runtime instead of being
+/// linked, so that programs built with the Wayland backend still start on systems without it.
+struct XkbFns {
+    xkb_context_new: unsafe extern "C" fn(c_int) -> *mut xkb_context,
+    xkb_context_unref: unsafe extern "C" fn(*mut xkb_context),
+    xkb_keymap_new_from_string: unsafe extern "C" fn(*mut xkb_context, *const c_char, c_int, c_int) -> *mut xkb_keymap,
+    xkb_keymap_unref: unsafe extern "C" fn(*mut xkb_keymap),
+    xkb_keymap_key_repeats: unsafe extern "C" fn(*mut xkb_keymap, u32) -> c_int,
+    xkb_keymap_key_get_syms_by_level: unsafe extern "C" fn(*mut xkb_keymap, u32, u32, u32, *mut *const u32) -> c_int,
+    xkb_state_new: unsafe extern "C" fn(*mut xkb_keymap) -> *mut xkb_state,
+    xkb_state_unref: unsafe extern "C" fn(*mut xkb_state),
+    xkb_state_update_mask: unsafe extern "C" fn(*mut xkb_state, u32, u32, u32, u32, u32, u32) -> c_int,
+    xkb_state_key_get_layout: unsafe extern "C" fn(*mut xkb_state, u32) -> u32,
+    xkb_state_key_get_one_sym: unsafe extern "C" fn(*mut xkb_state, u32) -> u32,
+    xkb_state_key_get_utf8: unsafe extern "C" fn(*mut xkb_state, u32, *mut c_char, usize) -> c_int,
+
+    xkb_compose_table_new_from_locale: unsafe extern "C" fn(*mut xkb_context, *const c_char, c_int) -> *mut xkb_compose_table,
+    xkb_compose_table_unref: unsafe extern "C" fn(*mut xkb_compose_table),
+    xkb_compose_state_new: unsafe extern "C" fn(*mut xkb_compose_table, c_int) -> *mut xkb_compose_state,
+    xkb_compose_state_unref: unsafe extern "C" fn(*mut xkb_compose_state),
+    xkb_compose_state_feed: unsafe extern "C" fn(*mut xkb_compose_state, u32) -> c_int,
+    xkb_compose_state_reset: unsafe extern "C" fn(*mut xkb_compose_state),
+    xkb_compose_state_get_status: unsafe extern "C" fn(*mut xkb_compose_state) -> c_int,
+    xkb_compose_state_get_utf8: unsafe extern "C" fn(*mut xkb_compose_state, *mut c_char, usize) -> c_int
+}
+
+static LOAD_FNS: Once = ONCE_INIT;
+/// The loaded functions, which are never freed once they're loaded
+static mut FNS: *const XkbFns = ptr::null();
+
+fn xkb_fns() -> Option<&'static XkbFns> {
+    unsafe {
+        LOAD_FNS.call_once(|| {
+            let lib = libc::dlopen(b"libxkbcommon.so.0\0".as_ptr() as *const c_char, libc::RTLD_LAZY);
+            if lib.is_null() {
+                return;
+            }
+
+            // Every function has to be there, since they can't be transmuted from null pointers
+            let mut missing = false;
+            let mut sym = |name: &[u8]| {
+                let f = libc::dlsym(lib, name.as_ptr() as *const c_char);
+                missing |= f.is_null();
+                f
+            };
+
+            let xkb_context_new = sym(b"xkb_context_new\0");
+            let xkb_context_unref = sym(b"xkb_context_unref\0");
+            let xkb_keymap_new_from_string = sym(b"xkb_keymap_new_from_string\0");
+            let xkb_keymap_unref = sym(b"xkb_keymap_unref\0");
+            let xkb_keymap_key_repeats = sym(b"xkb_keymap_key_repeats\0");
+            let xkb_keymap_key_get_syms_by_level = sym(b"xkb_keymap_key_get_syms_by_level\0");
+            let xkb_state_new = sym(b"xkb_state_new\0");
+            let xkb_state_unref = sym(b"xkb_state_unref\0");
+            let xkb_state_update_mask = sym(b"xkb_state_update_mask\0");
+            let xkb_state_key_get_layout = sym(b"xkb_state_key_get_layout\0");
+            let xkb_state_key_get_one_sym = sym(b"xkb_state_key_get_one_sym\0");
+            let xkb_state_key_get_utf8 = sym(b"xkb_state_key_get_utf8\0");
+            let xkb_compose_table_new_from_locale = sym(b"xkb_compose_table_new_from_locale\0");
+            let xkb_compose_table_unref = sym(b"xkb_compose_table_unref\0");
+            let xkb_compose_state_new = sym(b"xkb_compose_state_new\0");
+            let xkb_compose_state_unref = sym(b"xkb_compose_state_unref\0");
+            let xkb_compose_state_feed = sym(b"xkb_compose_state_feed\0");
+            let xkb_compose_state_reset = sym(b"xkb_compose_state_reset\0");
+            let xkb_compose_state_get_status = sym(b"xkb_compose_state_get_status\0");
+            let xkb_compose_state_get_utf8 = sym(b"xkb_compose_state_get_utf8\0");
+
+            if missing {
+                libc::dlclose(lib);
+                return;
+            }
+
+            FNS = Box::into_raw(Box::new(XkbFns {
+                xkb_context_new: mem::transmute(xkb_context_new),
+                xkb_context_unref: mem::transmute(xkb_context_unref),
+                xkb_keymap_new_from_string: mem::transmute(xkb_keymap_new_from_string),
+                xkb_keymap_unref: mem::transmute(xkb_keymap_unref),
+                xkb_keymap_key_repeats: mem::transmute(xkb_keymap_key_repeats),
+                xkb_keymap_key_get_syms_by_level: mem::transmute(xkb_keymap_key_get_syms_by_level),
+                xkb_state_new: mem::transmute(xkb_state_new),
+                xkb_state_unref: mem::transmute(xkb_state_unref),
+                xkb_state_update_mask: mem::transmute(xkb_state_update_mask),
+                xkb_state_key_get_layout: mem::transmute(xkb_state_key_get_layout),
+                xkb_state_key_get_one_sym: mem::transmute(xkb_state_key_get_one_sym),
+                xkb_state_key_get_utf8: mem::transmute(xkb_state_key_get_utf8),
+                xkb_compose_table_new_from_locale: mem::transmute(xkb_compose_table_new_from_locale),
+                xkb_compose_table_unref: mem::transmute(xkb_compose_table_unref),
+                xkb_compose_state_new: mem::transmute(xkb_compose_state_new),
+                xkb_compose_state_unref: mem::transmute(xkb_compose_state_unref),
+                xkb_compose_state_feed: mem::transmute(xkb_compose_state_feed),
+                xkb_compose_state_reset: mem::transmute(xkb_compose_state_reset),
+                xkb_compose_state_get_status: mem::transmute(xkb_compose_state_get_status),
+                xkb_compose_state_get_utf8: mem::transmute(xkb_compose_state_get_utf8)
+            }));
+        });
+
+        FNS.as_ref()
+    }
+}
+
+/// Whether libxkbcommon is installed. The Wayland backend needs it to make sense of the keyboard.
+#[inline]
+pub fn is_available() -> bool {
+    xkb_fns().is_some()
+}
+
+/// The keyboard layout that the compositor sent in a `wl_keyboard` keymap event, which is used to
+/// find out what keys mean under the user's layout and what text they type.
+pub struct Keymap {
+    fns: &'static XkbFns,
+    context: *mut xkb_context,
+    keymap: *mut xkb_keymap,
+    state: *mut xkb_state,
+    /// The compose sequences for the user's locale, which also cover dead keys. These are null if
+    /// the locale doesn't have any.
+    compose_table: *mut xkb_compose_table,
+    compose_state: *mut xkb_compose_state
+}
+
+impl Keymap {
+    /// Load a keymap from the file descriptor in a keymap event. The file descriptor isn't closed.
+    pub fn from_fd(fd: RawFd, size: u32) -> Option<Keymap> {
+        unsafe {
+            let fns = match xkb_fns() {
+                Some(fns) => fns,
+                None      => return None
+            };
+
+            let memory = libc::mmap(ptr::null_mut(), size as usize, libc::PROT_READ, libc::MAP_PRIVATE, fd, 0);
+            if memory == libc::MAP_FAILED {
+                return None;
+            }
+
+            // The keymap is a nul-terminated string, so it can be parsed straight from the mapping
+            let context = (fns.xkb_context_new)(0);
+            let keymap = match context.is_null() {
+                false => (fns.xkb_keymap_new_from_string)(context, memory as *const c_char, XKB_KEYMAP_FORMAT_TEXT_V1, 0),
+                true  => ptr::null_mut()
+            };
+            libc::munmap(memory, size as usize);
+
+            let state = match keymap.is_null() {
+                false => (fns.xkb_state_new)(keymap),
+                true  => ptr::null_mut()
+            };
+
+            if state.is_null() {
+                if !keymap.is_null() {
+                    (fns.xkb_keymap_unref)(keymap);
+                }
+                if !context.is_null() {
+                    (fns.xkb_context_unref)(context);
+                }
+                return None;
+            }
+
+            let compose_table = (fns.xkb_compose_table_new_from_locale)(context, locale().as_ptr(), 0);
+            let compose_state = match compose_table.is_null() {
+                false => (fns.xkb_compose_state_new)(compose_table, 0),
+                true  => ptr::null_mut()
+            };
+
+            Some(
+                Keymap {
+                    fns: fns,
+                    context: context,
+                    keymap: keymap,
+                    state: state,
+                    compose_table: compose_table,
+                    compose_state: compose_state
+                }
+            )
+        }
+    }
+
+    /// Update the state with the values from a `wl_keyboard` modifiers event
+    pub fn update_mask(&mut self, mods_depressed: u32, mods_latched: u32, mods_locked: u32, group: u32) {
+        unsafe{ (self.fns.xkb_state_update_mask)(self.state, mods_depressed, mods_latched, mods_locked, 0, 0, group) };
+    }
+
+    /// Get the key for an evdev keycode under the active layout. Like on X11, this goes by the
+    /// unshifted keysym, except for the numpad numbers when num lock is on.
+    pub fn key(&self, keycode: u32, num_lock: bool) -> Option<Key> {
+        // xkbcommon keycodes are evdev keycodes offset by 8, the same as X keycodes
+        let keycode = keycode + 8;
+
+        let keysym = match self.keysym(keycode, 0) {
+            Some(k @ XKB_KEY_KP_SPACE...XKB_KEY_KP_9) if num_lock =>
+                self.keysym(keycode, 1).or(Some(k)),
+            keysym => keysym
+        };
+
+        keysym.and_then(keymap::key_from_keysym)
+    }
+
+    /// Whether a key repeats while it's held down. Modifiers and lock keys don't.
+    #[inline]
+    pub fn key_repeats(&self, keycode: u32) -> bool {
+        unsafe{ (self.fns.xkb_keymap_key_repeats)(self.keymap, keycode + 8) != 0 }
+    }
+
+    /// Get the text that pressing a key types. Keys that are part of a compose sequence type nothing
+    /// until the sequence is finished.
+    pub fn text(&mut self, keycode: u32) -> String {
+        unsafe {
+            let keycode = keycode + 8;
+
+            if !self.compose_state.is_null() {
+                (self.fns.xkb_compose_state_feed)(self.compose_state, (self.fns.xkb_state_key_get_one_sym)(self.state, keycode));
+
+                match (self.fns.xkb_compose_state_get_status)(self.compose_state) {
+                    XKB_COMPOSE_COMPOSING => return String::new(),
+                    XKB_COMPOSE_COMPOSED  => {
+                        let text = get_utf8(|buffer, size| (self.fns.xkb_compose_state_get_utf8)(self.compose_state, buffer, size));
+                        (self.fns.xkb_compose_state_reset)(self.compose_state);
+                        return text;
+                    }
+                    XKB_COMPOSE_CANCELLED => {
+                        (self.fns.xkb_compose_state_reset)(self.compose_state);
+                        return String::new();
+                    }
+                    _ => ()
+                }
+            }
+
+            get_utf8(|buffer, size| (self.fns.xkb_state_key_get_utf8)(self.state, keycode, buffer, size))
+        }
+    }
+
+    fn keysym(&self, keycode: u32, level: u32) -> Option<u32> {
+        unsafe {
+            let layout = (self.fns.xkb_state_key_get_layout)(self.state, keycode);
+            let mut syms = ptr::null();
+
+            match (self.fns.xkb_keymap_key_get_syms_by_level)(self.keymap, keycode, layout, level, &mut syms) {
+                0 => None,
+                _ => Some(*syms)
+            }
+        }
+    }
+}
+
+impl Drop for Keymap {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.compose_state.is_null() {
+                (self.fns.xkb_compose_state_unref)(self.compose_state);
+            }
+            if !self.compose_table.is_null() {
+                (self.fns.xkb_compose_table_unref)(self.compose_table);
+            }
+            (self.fns.xkb_state_unref)(self.state);
+            (self.fns.xkb_keymap_unref)(self.keymap);
+            (self.fns.xkb_context_unref)(self.context);
+        }
+    }
+}
+
+/// Get a string from one of xkbcommon's functions that write UTF-8 into a buffer. These return the
+/// length that the string needs, without the nul terminator, even when the buffer is too small.
+unsafe fn get_utf8<F: Fn(*mut c_char, usize) -> c_int>(f: F) -> String {
+    let len = f(ptr::null_mut(), 0);
+    if len <= 0 {
+        return String::new();
+    }
+
+    let mut buffer = vec![0u8; len as usize + 1];
+    f(buffer.as_mut_ptr() as *mut c_char, buffer.len());
+    buffer.pop();
+
+    String::from_utf8_lossy(&buffer).into_owned()
+}
+
+/// Get the locale that compose sequences are looked up for, which is chosen by the same environment
+/// variables as `setlocale` uses.
+fn locale() -> CString {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"].iter()
+        .filter_map(|v| env::var(v).ok())
+        .find(|l| !l.is_empty())
+        .unwrap_or("C".to_owned());
+
+    CString::new(locale).unwrap_or(CString::new("C").unwrap())
+}
@@ -19,7 +19,7 @@ use api::osstr;
 use api::win32::Window;
 use api::win32::wrapper::{WindowWrapper, HwndType};
 use error::{TubResult, TubError, GlCreationError, GlCreationResult};
-use config::PixelFormat;
+use config::{PixelFormat, ContextConfig, GlApi};
 
 pub struct GlContext<'w, 'c> {
     hdc: HDC,
@@ -35,8 +35,13 @@ pub struct GlContext<'w, 'c> {
 }
 
 impl<'w, 'c> GlContext<'w, 'c> {
-    pub fn new(window: &'w Window, shared_context: Option<&'c GlContext>) -> GlCreationResult<GlContext<'w, 'c>> {
+    pub fn new(window: &'w Window, shared_context: Option<&'c GlContext>, context_config: &ContextConfig) -> GlCreationResult<GlContext<'w, 'c>> {
         unsafe {
+            // WGL can only create desktop OpenGL contexts
+            if context_config.api != GlApi::Gl || context_config.egl {
+                return Err(GlCreationError::UnsupportedApiError);
+            }
+
             let hdc = window.wrapper.1;
 
             let (context, gl_library) = {
@@ -164,10 +169,24 @@ impl<'w, 'c> GlContext<'w, 'c> {
                             Some(c) => c.context as *const c_void,
                             None    => ptr::null()
                         };
+                    let mut context_attrs = Vec::new();
+                    if let Some((major, minor)) = context_config.version {
+                        context_attrs.push(wgl_ex::CONTEXT_MAJOR_VERSION_ARB as i32);
+                        context_attrs.push(major as i32);
+                        context_attrs.push(wgl_ex::CONTEXT_MINOR_VERSION_ARB as i32);
+                        context_attrs.push(minor as i32);
+
+                        if (major, minor) >= (3, 2) {
+                            context_attrs.push(wgl_ex::CONTEXT_PROFILE_MASK_ARB as i32);
+                            context_attrs.push(wgl_ex::CONTEXT_CORE_PROFILE_BIT_ARB as i32);
+                        }
+                    }
+                    context_attrs.push(0);
+
                     let context = 
                         wgl_ex_fns.CreateContextAttribsARB(hdc as *const _,
                                                            shared_context_ptr,
-                                                           ptr::null());
+                                                           context_attrs.as_ptr());
                     if context == ptr::null_mut() { return Err(GlCreationError::ExtendedCreationError) }
 
                     (context, gl_library)
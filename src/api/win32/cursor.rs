@@ -0,0 +1,79 @@
+use winapi;
+use winapi::{BOOL, DWORD, UINT, HBITMAP, HCURSOR};
+use winapi::windef::HDC;
+
+use std::ptr;
+use std::mem;
+use std::slice;
+use std::os::raw::c_void;
+
+use CustomCursor;
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct ICONINFO {
+    fIcon: BOOL,
+    xHotspot: DWORD,
+    yHotspot: DWORD,
+    hbmMask: HBITMAP,
+    hbmColor: HBITMAP
+}
+
+#[link(name = "user32")]
+extern "system" {
+    fn CreateIconIndirect(info: *mut ICONINFO) -> HCURSOR;
+    fn DestroyCursor(cursor: HCURSOR) -> BOOL;
+}
+
+#[link(name = "gdi32")]
+extern "system" {
+    fn CreateDIBSection(hdc: HDC, info: *const winapi::BITMAPINFO, usage: UINT, bits: *mut *mut c_void,
+                        section: winapi::HANDLE, offset: DWORD) -> HBITMAP;
+    fn CreateBitmap(width: i32, height: i32, planes: UINT, bit_count: UINT, bits: *const c_void) -> HBITMAP;
+    fn DeleteObject(object: *mut c_void) -> BOOL;
+}
+
+/// Make a Windows cursor out of a custom cursor's pixels. Returns null if the cursor couldn't be
+/// made. The cursor has to be destroyed with `destroy` once it isn't needed anymore.
+pub fn create(cursor: &CustomCursor) -> HCURSOR {
+    unsafe {
+        let mut info: winapi::BITMAPINFO = mem::zeroed();
+        info.bmiHeader.biSize = mem::size_of::<winapi::BITMAPINFOHEADER>() as DWORD;
+        info.bmiHeader.biWidth = cursor.width() as winapi::LONG;
+        // A negative height makes the bitmap top-down, like the cursor's pixels are
+        info.bmiHeader.biHeight = -(cursor.height() as winapi::LONG);
+        info.bmiHeader.biPlanes = 1;
+        info.bmiHeader.biBitCount = 32;
+        info.bmiHeader.biCompression = winapi::BI_RGB;
+
+        // 32-bit DIBs store pixels as 0xAARRGGBB, which is the same as the cursor's pixels
+        let mut bits = ptr::null_mut();
+        let color = CreateDIBSection(ptr::null_mut(), &info, winapi::DIB_RGB_COLORS, &mut bits, ptr::null_mut(), 0);
+        if color.is_null() {
+            return ptr::null_mut();
+        }
+        slice::from_raw_parts_mut(bits as *mut u32, cursor.pixels().len()).copy_from_slice(cursor.pixels());
+
+        // Windows still wants a mask, but ignores it since the color bitmap has an alpha channel
+        let mask = CreateBitmap(cursor.width() as i32, cursor.height() as i32, 1, 1, ptr::null());
+
+        let mut icon_info = ICONINFO {
+            fIcon: 0,
+            xHotspot: cursor.hotspot().0,
+            yHotspot: cursor.hotspot().1,
+            hbmMask: mask,
+            hbmColor: color
+        };
+        let handle = CreateIconIndirect(&mut icon_info);
+
+        // The cursor gets its own copies of the bitmaps
+        DeleteObject(color as *mut c_void);
+        DeleteObject(mask as *mut c_void);
+        handle
+    }
+}
+
+#[inline]
+pub fn destroy(cursor: HCURSOR) {
+    unsafe{ DestroyCursor(cursor) };
+}
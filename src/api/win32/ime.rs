@@ -0,0 +1,123 @@
+use winapi;
+use winapi::{DWORD, LONG, BOOL};
+use winapi::windef::HWND;
+
+use std::ptr;
+use std::os::raw::c_void;
+
+type HIMC = *mut c_void;
+
+pub const GCS_COMPSTR: DWORD = 0x0008;
+pub const GCS_CURSORPOS: DWORD = 0x0080;
+pub const GCS_RESULTSTR: DWORD = 0x0800;
+
+const CFS_POINT: DWORD = 0x0002;
+const CFS_CANDIDATEPOS: DWORD = 0x0040;
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct COMPOSITIONFORM {
+    dwStyle: DWORD,
+    ptCurrentPos: winapi::POINT,
+    rcArea: winapi::RECT
+}
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct CANDIDATEFORM {
+    dwIndex: DWORD,
+    dwStyle: DWORD,
+    ptCurrentPos: winapi::POINT,
+    rcArea: winapi::RECT
+}
+
+#[link(name = "imm32")]
+extern "system" {
+    fn ImmGetContext(hwnd: HWND) -> HIMC;
+    fn ImmReleaseContext(hwnd: HWND, himc: HIMC) -> BOOL;
+    fn ImmGetCompositionStringW(himc: HIMC, index: DWORD, buffer: *mut c_void, len: DWORD) -> LONG;
+    fn ImmSetCompositionWindow(himc: HIMC, form: *mut COMPOSITIONFORM) -> BOOL;
+    fn ImmSetCandidateWindow(himc: HIMC, form: *mut CANDIDATEFORM) -> BOOL;
+}
+
+/// Get one of the strings of the window's current composition, where `index` is `GCS_COMPSTR` or
+/// `GCS_RESULTSTR`
+pub fn composition_string(hwnd: HWND, index: DWORD) -> Option<String> {
+    unsafe {
+        let himc = ImmGetContext(hwnd);
+        if himc.is_null() {
+            return None;
+        }
+
+        // The lengths are in bytes, not characters
+        let len = ImmGetCompositionStringW(himc, index, ptr::null_mut(), 0);
+        let string = match len >= 0 {
+            true  => {
+                let mut buffer = vec![0u16; len as usize / 2];
+                ImmGetCompositionStringW(himc, index, buffer.as_mut_ptr() as *mut c_void, len as DWORD);
+                Some(String::from_utf16_lossy(&buffer))
+            }
+            false => None
+        };
+
+        ImmReleaseContext(hwnd, himc);
+        string
+    }
+}
+
+/// Get where the cursor is in the window's current composition, as a byte offset into `text`
+pub fn cursor_pos(hwnd: HWND, text: &str) -> usize {
+    let pos = unsafe {
+        let himc = ImmGetContext(hwnd);
+        if himc.is_null() {
+            return 0;
+        }
+
+        let pos = ImmGetCompositionStringW(himc, GCS_CURSORPOS, ptr::null_mut(), 0);
+        ImmReleaseContext(hwnd, himc);
+        pos.max(0) as usize
+    };
+
+    // Windows measures the position in UTF-16 code units
+    let mut units = 0;
+    for (i, c) in text.char_indices() {
+        if units >= pos {
+            return i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+/// Move the composition and candidate windows so that they're next to a point in the window's
+/// client area
+pub fn set_position(hwnd: HWND, x: i32, y: i32) {
+    unsafe {
+        let himc = ImmGetContext(hwnd);
+        if himc.is_null() {
+            return;
+        }
+
+        let point = winapi::POINT{ x: x, y: y };
+        let mut composition = COMPOSITIONFORM {
+            dwStyle: CFS_POINT,
+            ptCurrentPos: point,
+            rcArea: empty_rect()
+        };
+        let mut candidate = CANDIDATEFORM {
+            dwIndex: 0,
+            dwStyle: CFS_CANDIDATEPOS,
+            ptCurrentPos: point,
+            rcArea: empty_rect()
+        };
+
+        ImmSetCompositionWindow(himc, &mut composition);
+        ImmSetCandidateWindow(himc, &mut candidate);
+        ImmReleaseContext(hwnd, himc);
+    }
+}
+
+#[inline]
+fn empty_rect() -> winapi::RECT {
+    winapi::RECT{ left: 0, top: 0, right: 0, bottom: 0 }
+}
@@ -1,6 +1,12 @@
 pub mod wrapper;
 pub mod os;
-use self::wrapper::{HwndType, WindowWrapper, WindowData, CallbackData, CALLBACK_DATA};
+pub mod soft_buffer;
+mod scancode;
+mod ime;
+mod pointer;
+mod raw_input;
+mod cursor;
+use self::wrapper::{HwndType, WindowWrapper, CallbackData, CALLBACK_DATA};
 
 use winapi;
 use user32;
@@ -13,19 +19,20 @@ use std::sync::mpsc::Receiver;
 use std::thread;
 use std::ops::Deref;
 
+use {Cursor, GrabMode, Rect};
+use api::backend::{WindowBackend, NativeWindow, EventSink};
 use error::TubResult;
 use config::{WindowConfig, PixelFormat};
-use event::Event;
 
 enum ReceiverTagged<'p> {
-    Owned(Receiver<TubResult<WindowData>>),
-    Borrowed(&'p Receiver<TubResult<WindowData>>)
+    Owned(Receiver<TubResult<WindowWrapper>>),
+    Borrowed(&'p Receiver<TubResult<WindowWrapper>>)
 }
 
 impl<'p> Deref for ReceiverTagged<'p> {
-    type Target = Receiver<TubResult<WindowData>>;
+    type Target = Receiver<TubResult<WindowWrapper>>;
 
-    fn deref<'a>(&'a self) -> &'a Receiver<TubResult<WindowData>> {
+    fn deref<'a>(&'a self) -> &'a Receiver<TubResult<WindowWrapper>> {
         use self::ReceiverTagged::*;
 
         match *self {
@@ -35,19 +42,9 @@ impl<'p> Deref for ReceiverTagged<'p> {
     }
 }
 
-/// An internal duplicate of WindowType that holds the internal window
-#[derive(Clone)]
-pub enum WindowType<'p> {
-    Owned(&'p Window<'p>),
-    Child(&'p Window<'p>),
-    Top
-}
-
 pub struct Window<'p> {
     pub wrapper: WindowWrapper,
-    event_receiver: Receiver<Event>,
     window_receiver: ReceiverTagged<'p>,
-    win_type: WindowType<'p>,
     /// Used when setting the pixel format on context creation
     config: WindowConfig,
     pixel_format: PixelFormat
@@ -55,7 +52,7 @@ pub struct Window<'p> {
 
 impl<'p> Window<'p> {
     /// Create a new window with the specified title and config
-    pub fn new(config: WindowConfig, pixel_format: PixelFormat) -> TubResult<Window<'p>> {
+    pub fn new(config: WindowConfig, pixel_format: PixelFormat, sink: EventSink) -> TubResult<Window<'p>> {
         // Channel for the handle to the window
         let (tx, rx) = mpsc::channel();
         let config = Arc::new(config);
@@ -66,15 +63,12 @@ impl<'p> Window<'p> {
                 let wrapper_window = WindowWrapper::new(&config_arc, HwndType::Top);
                 mem::drop(config_arc);
 
-                // Event channel
-                let (sx, rx) = mpsc::channel();
-
                 match wrapper_window {
                     Ok(wr) => {
                         CALLBACK_DATA.with(move |sender| {
-                            let callback_data = CallbackData::new(wr.0, sx, tx.clone());
+                            let callback_data = CallbackData::new(wr.0, sink, tx.clone());
 
-                            tx.send(Ok(WindowData(wr, rx))).unwrap();
+                            tx.send(Ok(wr)).unwrap();
                             *sender.borrow_mut() = Some(callback_data);
                         });
                     }
@@ -95,14 +89,12 @@ impl<'p> Window<'p> {
             }
         });
 
-        let WindowData(wrapper_window, receiver) = try!(rx.recv().unwrap());
+        let wrapper_window = try!(rx.recv().unwrap());
 
         Ok(
             Window {
                 wrapper: wrapper_window,
-                event_receiver: receiver,
                 window_receiver: ReceiverTagged::Owned(rx),
-                win_type: WindowType::Top,
                 config: Arc::try_unwrap(config).unwrap(),
                 pixel_format: pixel_format
             }
@@ -125,18 +117,16 @@ impl<'p> Window<'p> {
     /// when creating a new unowned window, tub spins up a thread to handle receiving
     /// input from the window in a way that does not block the main program's execution.
     /// Owned windows, however, share a thread with their owner. 
-    pub fn new_owned(&'p self, config: WindowConfig, pixel_format: PixelFormat) -> TubResult<Window<'p>> {
+    pub fn new_owned(&'p self, config: WindowConfig, pixel_format: PixelFormat, sink: EventSink) -> TubResult<Window<'p>> {
         unsafe {
-            user32::SendMessageW(self.wrapper.0, wrapper::MSG_NEWOWNEDWINDOW, 0, &config as *const _ as winapi::LPARAM);
+            user32::SendMessageW(self.wrapper.0, wrapper::MSG_NEWOWNEDWINDOW, &sink as *const _ as winapi::WPARAM, &config as *const _ as winapi::LPARAM);
 
-            let win_data = try!(self.window_receiver.recv().unwrap());
+            let wrapper_window = try!(self.window_receiver.recv().unwrap());
 
             Ok(
                 Window {
-                    wrapper: win_data.0,
-                    event_receiver: win_data.1,
+                    wrapper: wrapper_window,
                     window_receiver: ReceiverTagged::Borrowed(&self.window_receiver),
-                    win_type: WindowType::Owned(self),
                     config: config,
                     pixel_format: pixel_format
                 }
@@ -144,18 +134,16 @@ impl<'p> Window<'p> {
         }
     }
 
-    pub fn new_child(&'p self, config: WindowConfig, pixel_format: PixelFormat) -> TubResult<Window<'p>> {
+    pub fn new_child(&'p self, config: WindowConfig, pixel_format: PixelFormat, sink: EventSink) -> TubResult<Window<'p>> {
         unsafe {
-            user32::SendMessageW(self.wrapper.0, wrapper::MSG_NEWCHILDWINDOW, 0, &config as *const _ as winapi::LPARAM);
+            user32::SendMessageW(self.wrapper.0, wrapper::MSG_NEWCHILDWINDOW, &sink as *const _ as winapi::WPARAM, &config as *const _ as winapi::LPARAM);
 
-            let win_data = try!(self.window_receiver.recv().unwrap());
+            let wrapper_window = try!(self.window_receiver.recv().unwrap());
 
             Ok(
                 Window {
-                    wrapper: win_data.0,
-                    event_receiver: win_data.1,
+                    wrapper: wrapper_window,
                     window_receiver: ReceiverTagged::Borrowed(&self.window_receiver),
-                    win_type: WindowType::Child(self),
                     config: config,
                     pixel_format: pixel_format
                 }
@@ -192,55 +180,139 @@ impl<'p> Window<'p> {
         }
     }
 
-    /// Get a reference to this window's owner, if the window is owned.
+    pub fn get_config(&self) -> &WindowConfig {
+        &self.config
+    }
+
+    pub fn get_pixel_format(&self) -> &PixelFormat {
+        &self.pixel_format
+    }
+}
+
+impl<'p> WindowBackend for Window<'p> {
+    fn new_owned<'a>(&'a self, config: WindowConfig, pixel_format: PixelFormat, sink: EventSink) -> TubResult<Box<WindowBackend + 'a>> {
+        Window::new_owned(self, config, pixel_format, sink).map(|w| Box::new(w) as Box<WindowBackend + 'a>)
+    }
+
+    fn new_child<'a>(&'a self, config: WindowConfig, pixel_format: PixelFormat, sink: EventSink) -> TubResult<Box<WindowBackend + 'a>> {
+        Window::new_child(self, config, pixel_format, sink).map(|w| Box::new(w) as Box<WindowBackend + 'a>)
+    }
+
     #[inline]
-    pub fn get_type(&self) -> WindowType {
-        self.win_type.clone()
+    fn set_title(&self, title: &str) {
+        self.wrapper.set_title(title);
     }
 
-    /// Get a non-blocking iterator over the window's events
-    pub fn poll_events(&self) -> PollEventsIter {
-        PollEventsIter {
-            window: self
-        }
+    #[inline]
+    fn show(&self) {
+        self.wrapper.show();
     }
 
-    /// Get a blocking iterator over the window's events
-    pub fn wait_events(&self) -> WaitEventsIter {
-        WaitEventsIter {
-            window: self
-        }
+    #[inline]
+    fn hide(&self) {
+        self.wrapper.hide();
     }
 
-    pub fn get_config(&self) -> &WindowConfig {
-        &self.config
+    #[inline]
+    fn close(&self) {
+        self.wrapper.kill();
     }
 
-    pub fn get_pixel_format(&self) -> &PixelFormat {
-        &self.pixel_format
+    #[inline]
+    fn enable(&self) {
+        self.wrapper.enable();
     }
-}
 
-pub struct PollEventsIter<'w> {
-    window: &'w Window<'w>
-}
+    #[inline]
+    fn disable(&self) {
+        self.wrapper.disable();
+    }
 
-impl<'w> Iterator for PollEventsIter<'w> {
-    type Item = Event;
+    #[inline]
+    fn focus(&self) {
+        self.wrapper.focus();
+    }
 
-    fn next(&mut self) -> Option<Event> {
-        self.window.event_receiver.try_recv().ok()
+    #[inline]
+    fn get_inner_pos(&self) -> Option<(i32, i32)> {
+        self.wrapper.get_inner_pos()
     }
-}
 
-pub struct WaitEventsIter<'w> {
-    window: &'w Window<'w>
-}
+    #[inline]
+    fn get_outer_pos(&self) -> Option<(i32, i32)> {
+        self.wrapper.get_outer_pos()
+    }
+
+    #[inline]
+    fn get_inner_size(&self) -> Option<(u32, u32)> {
+        self.wrapper.get_inner_size()
+    }
+
+    #[inline]
+    fn get_outer_size(&self) -> Option<(u32, u32)> {
+        self.wrapper.get_outer_size()
+    }
+
+    #[inline]
+    fn set_pos(&self, x: i32, y: i32) -> Option<()> {
+        self.wrapper.set_pos(x, y)
+    }
+
+    #[inline]
+    fn set_inner_size(&self, x: u32, y: u32) -> Option<()> {
+        self.wrapper.set_inner_size(x, y)
+    }
+
+    #[inline]
+    fn is_active(&self) -> bool {
+        self.wrapper.is_active()
+    }
+
+    #[inline]
+    fn set_cursor(&self, cursor: &Cursor) {
+        match *cursor {
+            Cursor::System(cursor_type) => self.wrapper.set_cursor(cursor_type),
+            Cursor::Custom(ref cursor)  => self.wrapper.set_custom_cursor(cursor)
+        }
+    }
+
+    #[inline]
+    fn set_cursor_grab(&self, mode: GrabMode) -> Option<()> {
+        self.wrapper.set_cursor_grab(mode, None);
+        Some(())
+    }
+
+    #[inline]
+    fn confine_cursor(&self, rect: Option<Rect>) -> Option<()> {
+        match rect {
+            Some(_) => self.wrapper.set_cursor_grab(GrabMode::Confined, rect),
+            None    => self.wrapper.set_cursor_grab(GrabMode::None, None)
+        }
+        Some(())
+    }
 
-impl<'w> Iterator for WaitEventsIter<'w> {
-    type Item = Event;
+    #[inline]
+    fn set_ime_position(&self, x: i32, y: i32) {
+        self.wrapper.set_ime_position(x, y);
+    }
 
-    fn next(&mut self) -> Option<Event> {
-        self.window.event_receiver.recv().ok()
+    #[inline]
+    fn set_cursor_pos(&self, x: i32, y: i32) {
+        Window::set_cursor_pos(self, x, y);
     }
-}
\ No newline at end of file
+
+    #[inline]
+    fn get_config(&self) -> &WindowConfig {
+        Window::get_config(self)
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> &PixelFormat {
+        Window::get_pixel_format(self)
+    }
+
+    #[inline]
+    fn native<'a>(&'a self) -> NativeWindow<'a> {
+        NativeWindow::Win32(self)
+    }
+}
@@ -0,0 +1,233 @@
+use winapi;
+use winapi::{DWORD, UINT, BOOL, HANDLE};
+use winapi::windef::HWND;
+use kernel32;
+
+use std::mem;
+use std::ptr;
+use std::sync::{Once, ONCE_INIT};
+
+use api::osstr;
+
+pub const WM_POINTERUPDATE: UINT = 0x0245;
+pub const WM_POINTERDOWN: UINT = 0x0246;
+pub const WM_POINTERUP: UINT = 0x0247;
+pub const WM_POINTERCAPTURECHANGED: UINT = 0x024C;
+
+const PT_TOUCH: DWORD = 2;
+const PT_PEN: DWORD = 3;
+
+const POINTER_FLAG_INCONTACT: u32 = 0x0004;
+const POINTER_FLAG_CANCELED: u32 = 0x8000;
+const TOUCH_MASK_PRESSURE: u32 = 0x0004;
+
+const PEN_FLAG_BARREL: u32 = 0x0001;
+const PEN_FLAG_INVERTED: u32 = 0x0002;
+const PEN_FLAG_ERASER: u32 = 0x0004;
+
+/// The largest pressure that a touch or pen can have
+const MAX_PRESSURE: f32 = 1024.0;
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct POINTER_INFO {
+    pointerType: DWORD,
+    pointerId: u32,
+    frameId: u32,
+    pointerFlags: u32,
+    sourceDevice: HANDLE,
+    hwndTarget: HWND,
+    ptPixelLocation: winapi::POINT,
+    ptHimetricLocation: winapi::POINT,
+    ptPixelLocationRaw: winapi::POINT,
+    ptHimetricLocationRaw: winapi::POINT,
+    dwTime: DWORD,
+    historyCount: u32,
+    InputData: i32,
+    dwKeyStates: DWORD,
+    PerformanceCount: u64,
+    ButtonChangeType: i32
+}
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct POINTER_TOUCH_INFO {
+    pointerInfo: POINTER_INFO,
+    touchFlags: u32,
+    touchMask: u32,
+    rcContact: winapi::RECT,
+    rcContactRaw: winapi::RECT,
+    orientation: u32,
+    pressure: u32
+}
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct POINTER_PEN_INFO {
+    pointerInfo: POINTER_INFO,
+    penFlags: u32,
+    penMask: u32,
+    pressure: u32,
+    rotation: u32,
+    tiltX: i32,
+    tiltY: i32
+}
+
+/// The pointer functions that Windows 8 added. They're loaded at runtime so that tub still runs on
+/// Windows 7, which never sends pointer messages in the first place.
+struct PointerFns {
+    get_pointer_type: unsafe extern "system" fn(u32, *mut DWORD) -> BOOL,
+    get_pointer_touch_info: unsafe extern "system" fn(u32, *mut POINTER_TOUCH_INFO) -> BOOL,
+    get_pointer_pen_info: unsafe extern "system" fn(u32, *mut POINTER_PEN_INFO) -> BOOL,
+    get_pointer_device_rects: unsafe extern "system" fn(HANDLE, *mut winapi::RECT, *mut winapi::RECT) -> BOOL
+}
+
+static LOAD_FNS: Once = ONCE_INIT;
+/// The loaded functions, which are never freed once they're loaded
+static mut FNS: *const PointerFns = ptr::null();
+
+fn pointer_fns() -> Option<&'static PointerFns> {
+    unsafe {
+        LOAD_FNS.call_once(|| {
+            let user32 = kernel32::GetModuleHandleW(osstr("user32.dll").as_ptr());
+            if user32.is_null() {
+                return;
+            }
+
+            let get_pointer_type = kernel32::GetProcAddress(user32, b"GetPointerType\0".as_ptr() as *const _);
+            let get_pointer_touch_info = kernel32::GetProcAddress(user32, b"GetPointerTouchInfo\0".as_ptr() as *const _);
+            let get_pointer_pen_info = kernel32::GetProcAddress(user32, b"GetPointerPenInfo\0".as_ptr() as *const _);
+            let get_pointer_device_rects = kernel32::GetProcAddress(user32, b"GetPointerDeviceRects\0".as_ptr() as *const _);
+            if !get_pointer_type.is_null() && !get_pointer_touch_info.is_null() &&
+               !get_pointer_pen_info.is_null() && !get_pointer_device_rects.is_null() {
+                FNS = Box::into_raw(Box::new(PointerFns {
+                    get_pointer_type: mem::transmute(get_pointer_type),
+                    get_pointer_touch_info: mem::transmute(get_pointer_touch_info),
+                    get_pointer_pen_info: mem::transmute(get_pointer_pen_info),
+                    get_pointer_device_rects: mem::transmute(get_pointer_device_rects)
+                }));
+            }
+        });
+
+        FNS.as_ref()
+    }
+}
+
+/// A touch on a touchscreen, as reported by a pointer message
+pub struct TouchInfo {
+    /// The touch's position in screen coordinates
+    pub pos: winapi::POINT,
+    pub pressure: Option<f32>,
+    pub cancelled: bool
+}
+
+/// A pen on a tablet, as reported by a pointer message
+pub struct PenInfo {
+    /// The tablet that the pen is on
+    pub device: HANDLE,
+    /// The pen's position in screen coordinates, with subpixel precision
+    pub pos: (f64, f64),
+    pub pressure: f32,
+    pub tilt_x: f32,
+    pub tilt_y: f32,
+    pub rotation: f32,
+    pub eraser: bool,
+    pub tip: bool,
+    pub barrel: bool
+}
+
+/// Get the pointer ID that a pointer message is for
+#[inline]
+pub fn pointer_id(wparam: winapi::WPARAM) -> u32 {
+    (wparam & 0xFFFF) as u32
+}
+
+/// Get the state of a pointer if it's a touch, or `None` if it's a mouse or pen
+pub fn touch_info(pointer_id: u32) -> Option<TouchInfo> {
+    let fns = match pointer_fns() {
+        Some(f) if pointer_type(f, pointer_id) == Some(PT_TOUCH) => f,
+        _ => return None
+    };
+
+    unsafe {
+        let mut info: POINTER_TOUCH_INFO = mem::zeroed();
+        if (fns.get_pointer_touch_info)(pointer_id, &mut info) == 0 {
+            return None;
+        }
+
+        Some(
+            TouchInfo {
+                pos: info.pointerInfo.ptPixelLocation,
+                pressure: match info.touchMask & TOUCH_MASK_PRESSURE {
+                    0 => None,
+                    _ => Some(info.pressure as f32 / MAX_PRESSURE)
+                },
+                cancelled: info.pointerInfo.pointerFlags & POINTER_FLAG_CANCELED != 0
+            }
+        )
+    }
+}
+
+/// Get the state of a pointer if it's a pen, or `None` if it's a mouse or touch. Values that the pen
+/// doesn't report are 0, which is what Windows sets them to.
+pub fn pen_info(pointer_id: u32) -> Option<PenInfo> {
+    let fns = match pointer_fns() {
+        Some(f) if pointer_type(f, pointer_id) == Some(PT_PEN) => f,
+        _ => return None
+    };
+
+    unsafe {
+        let mut info: POINTER_PEN_INFO = mem::zeroed();
+        if (fns.get_pointer_pen_info)(pointer_id, &mut info) == 0 {
+            return None;
+        }
+        let pointer = &info.pointerInfo;
+
+        // The pixel location is rounded, but the HIMETRIC location can be mapped onto the screen
+        // to get the position between pixels
+        let mut device_rect = empty_rect();
+        let mut display_rect = empty_rect();
+        let pos = match (fns.get_pointer_device_rects)(pointer.sourceDevice, &mut device_rect, &mut display_rect) {
+            0 => (pointer.ptPixelLocation.x as f64, pointer.ptPixelLocation.y as f64),
+            _ => (
+                map_range(pointer.ptHimetricLocation.x, device_rect.left, device_rect.right, display_rect.left, display_rect.right),
+                map_range(pointer.ptHimetricLocation.y, device_rect.top, device_rect.bottom, display_rect.top, display_rect.bottom)
+            )
+        };
+
+        Some(
+            PenInfo {
+                device: pointer.sourceDevice,
+                pos: pos,
+                pressure: info.pressure as f32 / MAX_PRESSURE,
+                tilt_x: info.tiltX as f32,
+                tilt_y: info.tiltY as f32,
+                rotation: info.rotation as f32,
+                eraser: info.penFlags & (PEN_FLAG_INVERTED | PEN_FLAG_ERASER) != 0,
+                tip: pointer.pointerFlags & POINTER_FLAG_INCONTACT != 0,
+                barrel: info.penFlags & PEN_FLAG_BARREL != 0
+            }
+        )
+    }
+}
+
+fn pointer_type(fns: &PointerFns, pointer_id: u32) -> Option<DWORD> {
+    let mut pointer_type = 0;
+    match unsafe{ (fns.get_pointer_type)(pointer_id, &mut pointer_type) } {
+        0 => None,
+        _ => Some(pointer_type)
+    }
+}
+
+/// Map a value from one range onto another
+fn map_range(value: i32, from_min: i32, from_max: i32, to_min: i32, to_max: i32) -> f64 {
+    match from_max - from_min {
+        0   => to_min as f64,
+        len => to_min as f64 + (value - from_min) as f64 * (to_max - to_min) as f64 / len as f64
+    }
+}
+
+#[inline]
+fn empty_rect() -> winapi::RECT {
+    winapi::RECT{ left: 0, top: 0, right: 0, bottom: 0 }
+}
@@ -0,0 +1,94 @@
+use winapi::{DWORD, UINT, BOOL, HANDLE, WPARAM, LPARAM};
+use winapi::windef::HWND;
+
+use std::ptr;
+use std::mem;
+use std::os::raw::c_void;
+
+pub const WM_INPUT: UINT = 0x00FF;
+
+const RID_INPUT: UINT = 0x10000003;
+const RIM_TYPEMOUSE: DWORD = 0;
+const MOUSE_MOVE_ABSOLUTE: u16 = 0x0001;
+
+// The HID usage of mice
+const HID_USAGE_PAGE_GENERIC: u16 = 0x01;
+const HID_USAGE_GENERIC_MOUSE: u16 = 0x02;
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct RAWINPUTDEVICE {
+    usUsagePage: u16,
+    usUsage: u16,
+    dwFlags: DWORD,
+    hwndTarget: HWND
+}
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct RAWINPUTHEADER {
+    dwType: DWORD,
+    dwSize: DWORD,
+    hDevice: HANDLE,
+    wParam: WPARAM
+}
+
+#[repr(C)]
+#[allow(non_snake_case)]
+struct RAWMOUSE {
+    usFlags: u16,
+    ulButtons: u32,
+    ulRawButtons: u32,
+    lLastX: i32,
+    lLastY: i32,
+    ulExtraInformation: u32
+}
+
+/// Raw input from a mouse. The real `RAWINPUT` is a union of this and the other kinds of devices,
+/// but only mice are registered for.
+#[repr(C)]
+#[allow(non_snake_case)]
+struct RAWINPUT {
+    header: RAWINPUTHEADER,
+    mouse: RAWMOUSE
+}
+
+#[link(name = "user32")]
+extern "system" {
+    fn RegisterRawInputDevices(devices: *const RAWINPUTDEVICE, count: UINT, size: UINT) -> BOOL;
+    fn GetRawInputData(raw_input: *mut c_void, command: UINT, data: *mut c_void, size: *mut UINT, header_size: UINT) -> UINT;
+}
+
+/// Ask for the raw input of mice. Windows sends it to whichever window has focus as `WM_INPUT`
+/// messages.
+pub fn register_mouse() {
+    let device = RAWINPUTDEVICE {
+        usUsagePage: HID_USAGE_PAGE_GENERIC,
+        usUsage: HID_USAGE_GENERIC_MOUSE,
+        dwFlags: 0,
+        hwndTarget: ptr::null_mut()
+    };
+
+    unsafe{ RegisterRawInputDevices(&device, 1, mem::size_of::<RAWINPUTDEVICE>() as UINT) };
+}
+
+/// Get how far the mouse moved from a `WM_INPUT` message, if it moved. Tablets and other devices
+/// that report absolute positions are left out.
+pub fn mouse_delta(lparam: LPARAM) -> Option<(i32, i32)> {
+    unsafe {
+        let mut input: RAWINPUT = mem::zeroed();
+        let mut size = mem::size_of::<RAWINPUT>() as UINT;
+        let read = GetRawInputData(lparam as *mut c_void, RID_INPUT, &mut input as *mut _ as *mut c_void,
+                                   &mut size, mem::size_of::<RAWINPUTHEADER>() as UINT);
+
+        if read == UINT::max_value() || input.header.dwType != RIM_TYPEMOUSE {
+            return None;
+        }
+
+        let mouse = &input.mouse;
+        match mouse.usFlags & MOUSE_MOVE_ABSOLUTE == 0 && (mouse.lLastX != 0 || mouse.lLastY != 0) {
+            true  => Some((mouse.lLastX, mouse.lLastY)),
+            false => None
+        }
+    }
+}
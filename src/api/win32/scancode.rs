@@ -0,0 +1,171 @@
+use winapi::LPARAM;
+
+use event::{ScanCode, PhysicalKey};
+
+/// Get the scancode from the `lparam` of a key message. Bits 16-23 hold the set 1 scancode, and bit
+/// 24 is set for extended keys, which share scancodes with other keys and are told apart by an
+/// `0xE0` prefix byte.
+pub fn scancode_from_lparam(lparam: LPARAM) -> ScanCode {
+    let code = (lparam >> 16 & 0xFF) as u32;
+    let raw = match lparam & 0x01000000 {
+        0 => code,
+        _ => code | 0xE000
+    };
+
+    ScanCode {
+        raw: raw,
+        key: physical_key_from_scancode(raw)
+    }
+}
+
+pub fn physical_key_from_scancode(scancode: u32) -> Option<PhysicalKey> {
+    use event::PhysicalKey::*;
+
+    match scancode {
+        0x01    => Some(Escape  ),
+        0x02    => Some(Key1    ),
+        0x03    => Some(Key2    ),
+        0x04    => Some(Key3    ),
+        0x05    => Some(Key4    ),
+        0x06    => Some(Key5    ),
+        0x07    => Some(Key6    ),
+        0x08    => Some(Key7    ),
+        0x09    => Some(Key8    ),
+        0x0A    => Some(Key9    ),
+        0x0B    => Some(Key0    ),
+        0x0C    => Some(Minus   ),
+        0x0D    => Some(Equal   ),
+        0x0E    => Some(Back    ),
+        0x0F    => Some(Tab     ),
+
+        0x10    => Some(Q),
+        0x11    => Some(W),
+        0x12    => Some(E),
+        0x13    => Some(R),
+        0x14    => Some(T),
+        0x15    => Some(Y),
+        0x16    => Some(U),
+        0x17    => Some(I),
+        0x18    => Some(O),
+        0x19    => Some(P),
+        0x1A    => Some(LBrac   ),
+        0x1B    => Some(RBrac   ),
+        0x1C    => Some(Enter   ),
+        0x1D    => Some(LCtrl   ),
+
+        0x1E    => Some(A),
+        0x1F    => Some(S),
+        0x20    => Some(D),
+        0x21    => Some(F),
+        0x22    => Some(G),
+        0x23    => Some(H),
+        0x24    => Some(J),
+        0x25    => Some(K),
+        0x26    => Some(L),
+        0x27    => Some(Semi    ),
+        0x28    => Some(Quote   ),
+        0x29    => Some(Tilde   ),
+        0x2A    => Some(LShift  ),
+        0x2B    => Some(Pipe    ),
+
+        0x2C    => Some(Z),
+        0x2D    => Some(X),
+        0x2E    => Some(C),
+        0x2F    => Some(V),
+        0x30    => Some(B),
+        0x31    => Some(N),
+        0x32    => Some(M),
+        0x33    => Some(Comma   ),
+        0x34    => Some(Dot     ),
+        0x35    => Some(Slash   ),
+        0x36    => Some(RShift  ),
+        0x37    => Some(NumStar ),
+        0x38    => Some(LAlt    ),
+        0x39    => Some(Space   ),
+        0x3A    => Some(Caps    ),
+
+        0x3B    => Some(F1      ),
+        0x3C    => Some(F2      ),
+        0x3D    => Some(F3      ),
+        0x3E    => Some(F4      ),
+        0x3F    => Some(F5      ),
+        0x40    => Some(F6      ),
+        0x41    => Some(F7      ),
+        0x42    => Some(F8      ),
+        0x43    => Some(F9      ),
+        0x44    => Some(F10     ),
+
+        // Windows reports pause without the extended bit and num lock with it, even though it's
+        // the other way around on the keyboard.
+        0x45    => Some(Pause   ),
+        0x46    => Some(Scroll  ),
+        0x47    => Some(Num7    ),
+        0x48    => Some(Num8    ),
+        0x49    => Some(Num9    ),
+        0x4A    => Some(NumSub  ),
+        0x4B    => Some(Num4    ),
+        0x4C    => Some(Num5    ),
+        0x4D    => Some(Num6    ),
+        0x4E    => Some(NumPlus ),
+        0x4F    => Some(Num1    ),
+        0x50    => Some(Num2    ),
+        0x51    => Some(Num3    ),
+        0x52    => Some(Num0    ),
+        0x53    => Some(NumDot  ),
+        // Print screen while alt is held down
+        0x54    => Some(PrntScr ),
+
+        0x56    => Some(NonUsBackslash),
+        0x57    => Some(F11     ),
+        0x58    => Some(F12     ),
+        0x59    => Some(NumEqual),
+        0x64    => Some(F13     ),
+        0x65    => Some(F14     ),
+        0x66    => Some(F15     ),
+        0x67    => Some(F16     ),
+        0x68    => Some(F17     ),
+        0x69    => Some(F18     ),
+        0x6A    => Some(F19     ),
+        0x6B    => Some(F20     ),
+        0x6C    => Some(F21     ),
+        0x6D    => Some(F22     ),
+        0x6E    => Some(F23     ),
+        0x70    => Some(Kana    ),
+        0x73    => Some(IntlRo  ),
+        0x76    => Some(F24     ),
+        0x79    => Some(Convert ),
+        0x7B    => Some(NonConvert),
+        0x7D    => Some(IntlYen ),
+        0x7E    => Some(NumComma),
+        0xF1    => Some(Lang2   ),
+        0xF2    => Some(Lang1   ),
+
+        0xE01C  => Some(NumEnter),
+        0xE01D  => Some(RCtrl   ),
+        0xE020  => Some(Mute    ),
+        0xE02E  => Some(VolDown ),
+        0xE030  => Some(VolUp   ),
+        0xE035  => Some(NumSlash),
+        0xE037  => Some(PrntScr ),
+        0xE038  => Some(RAlt    ),
+        0xE045  => Some(Num     ),
+        // Ctrl+Pause, which keyboards send as Break
+        0xE046  => Some(Pause   ),
+        0xE047  => Some(Home    ),
+        0xE048  => Some(UArrow  ),
+        0xE049  => Some(PgUp    ),
+        0xE04B  => Some(LArrow  ),
+        0xE04D  => Some(RArrow  ),
+        0xE04F  => Some(End     ),
+        0xE050  => Some(DArrow  ),
+        0xE051  => Some(PgDn    ),
+        0xE052  => Some(Insert  ),
+        0xE053  => Some(Delete  ),
+        0xE05B  => Some(LWin    ),
+        0xE05C  => Some(RWin    ),
+        0xE05D  => Some(Apps    ),
+        0xE05E  => Some(Power   ),
+
+        _       => None
+    }
+}
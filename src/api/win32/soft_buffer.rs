@@ -0,0 +1,61 @@
+use winapi;
+use gdi32;
+
+use winapi::windef::HDC;
+
+use std::mem;
+use std::marker::PhantomData;
+
+use super::Window;
+
+/// A buffer of pixels that gets copied into a window with SetDIBitsToDevice. Windows' 32-bit DIBs
+/// store each pixel as `0x00RRGGBB`, so the pixels can be handed over without converting them.
+pub struct SoftBuffer<'w> {
+    hdc: HDC,
+    pixels: Vec<u32>,
+    size: (u32, u32),
+    /// Guarantees that this won't live longer than the window that created it
+    window_lifetime: PhantomData<&'w ()>
+}
+
+impl<'w> SoftBuffer<'w> {
+    pub fn new(window: &'w Window) -> SoftBuffer<'w> {
+        SoftBuffer {
+            hdc: window.wrapper.1,
+            pixels: Vec::new(),
+            size: (0, 0),
+            window_lifetime: PhantomData
+        }
+    }
+
+    /// Replace the pixels with new ones of the given size, cleared to black
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.pixels = vec![0; width as usize * height as usize];
+        self.size = (width, height);
+    }
+
+    #[inline]
+    pub fn pixels_mut(&mut self) -> &mut [u32] {
+        &mut self.pixels
+    }
+
+    pub fn present(&self) {
+        if self.size.0 == 0 || self.size.1 == 0 {
+            return;
+        }
+
+        unsafe {
+            let mut info: winapi::BITMAPINFO = mem::zeroed();
+            info.bmiHeader.biSize = mem::size_of::<winapi::BITMAPINFOHEADER>() as winapi::DWORD;
+            info.bmiHeader.biWidth = self.size.0 as winapi::LONG;
+            // A negative height makes the bitmap top-down, like the buffer is
+            info.bmiHeader.biHeight = -(self.size.1 as winapi::LONG);
+            info.bmiHeader.biPlanes = 1;
+            info.bmiHeader.biBitCount = 32;
+            info.bmiHeader.biCompression = winapi::BI_RGB;
+
+            gdi32::SetDIBitsToDevice(self.hdc, 0, 0, self.size.0, self.size.1, 0, 0, 0, self.size.1,
+                                     self.pixels.as_ptr() as *const _, &info, winapi::DIB_RGB_COLORS);
+        }
+    }
+}
@@ -9,19 +9,26 @@ use winapi::winuser::WNDCLASSEXW;
 
 use std::ptr;
 use std::mem;
+use std::char;
 use std::ops::Drop;
 use std::os::windows::ffi::OsStrExt;
 use std::path::PathBuf;
 use std::cell::RefCell;
-use std::sync::mpsc::{Sender, Receiver};
+use std::collections::HashMap;
+use std::sync::mpsc::Sender;
 use api::osstr;
-
-use num::FromPrimitive;
-
-use CursorType;
+use api::backend::EventSink;
+use super::scancode::scancode_from_lparam;
+use super::ime;
+use super::pointer;
+use super::raw_input;
+use super::cursor;
+
+use {CursorType, CustomCursor, GrabMode, Rect};
+use keymap;
 use config::WindowConfig;
 use error::{TubResult, TubError};
-use event::{Event, VKeyCode};
+use event::{Event, Key, PhysicalKey, ScanCode, ModifiersState};
 
 #[derive(Clone)]
 pub struct WindowWrapper( pub HWND, pub HDC );
@@ -120,6 +127,8 @@ impl WindowWrapper {
                 return Err(TubError::OsError(format!("Error: {}", ::std::io::Error::last_os_error())));
             }
 
+            raw_input::register_mouse();
+
             // If the window should be borderless, make it borderless
             if config.borderless {
                 user32::SetWindowLongW(window_handle, -16, 0);
@@ -360,6 +369,24 @@ impl WindowWrapper {
         }
     }
 
+    #[inline]
+    pub fn set_custom_cursor(&self, cursor: &CustomCursor) {
+        unsafe{ user32::SendMessageW(self.0, MSG_SETCUSTOMCURSOR, 0, cursor as *const _ as LPARAM) };
+    }
+
+    /// Set how the window grabs the cursor, and the part of the client area that a confined cursor
+    /// is kept in if it isn't the whole client area
+    #[inline]
+    pub fn set_cursor_grab(&self, mode: GrabMode, rect: Option<Rect>) {
+        unsafe{ user32::SendMessageW(self.0, MSG_SETGRAB, mode as WPARAM, &rect as *const _ as LPARAM) };
+    }
+
+    /// Move the input method's windows next to a point in the client area
+    #[inline]
+    pub fn set_ime_position(&self, x: i32, y: i32) {
+        unsafe{ user32::SendMessageW(self.0, MSG_SETIMEPOS, x as WPARAM, y as LPARAM) };
+    }
+
     pub fn get_style(&self) -> u32{
         unsafe{ user32::GetWindowLongW(self.0, -16) as u32 }
     }
@@ -412,7 +439,7 @@ pub struct CallbackData {
     /// A cached index so that the program does not have to search through all of the
     /// window vertex to get the proper window information
     win_index: usize,
-    win_sender: Sender<TubResult<WindowData>>,
+    win_sender: Sender<TubResult<WindowWrapper>>,
     /// The last position of the mouse. This is used to catch duplicate WM_MOUSEHOVER
     /// messages.
     last_mpos: LPARAM
@@ -420,9 +447,9 @@ pub struct CallbackData {
 
 impl CallbackData {
     #[inline]
-    pub fn new(vec_window: HWND, event_sender: Sender<Event>, sender: Sender<TubResult<WindowData>>) -> CallbackData {
+    pub fn new(vec_window: HWND, sink: EventSink, sender: Sender<TubResult<WindowWrapper>>) -> CallbackData {
         let mut data_vector = Vec::with_capacity(4);
-        data_vector.push(WindowDataIntern::new(vec_window, event_sender));
+        data_vector.push(WindowDataIntern::new(vec_window, sink));
 
         CallbackData {
             win_vec: data_vector,
@@ -460,27 +487,38 @@ impl CallbackData {
 }
 
 /// Struct that contains information about the window wrapper to the callback.
-/// Stuff like the raw window and the event sender are only used by the callback
+/// Stuff like the raw window and the event sink are only used by the callback
 /// function, and as such they do not need to be exposed. 
 struct WindowDataIntern {
     window: HWND,
-    sender: Sender<Event>,
-    cursor: winapi::HCURSOR
+    sink: EventSink,
+    cursor: winapi::HCURSOR,
+    /// The first half of a UTF-16 surrogate pair, if the last WM_CHAR message was one
+    high_surrogate: Option<u16>,
+    /// Whether an input method composition has started and hasn't been committed yet
+    ime_composing: bool,
+    grab: GrabMode,
+    confine_rect: Option<Rect>,
+    /// The cursors made for the custom cursors that the window has used, by cursor ID
+    cursors: HashMap<usize, winapi::HCURSOR>
 }
 
 impl WindowDataIntern {
     #[inline]
-    fn new(window: HWND, sender: Sender<Event>) -> WindowDataIntern {
+    fn new(window: HWND, sink: EventSink) -> WindowDataIntern {
         WindowDataIntern {
             window: window,
-            sender: sender,
-            cursor: unsafe{ user32::LoadCursorW(ptr::null_mut(), winapi::IDC_ARROW) }
+            sink: sink,
+            cursor: unsafe{ user32::LoadCursorW(ptr::null_mut(), winapi::IDC_ARROW) },
+            high_surrogate: None,
+            ime_composing: false,
+            grab: GrabMode::None,
+            confine_rect: None,
+            cursors: HashMap::new()
         }
     }
 }
 
-pub struct WindowData( pub WindowWrapper, pub Receiver<Event> );
-
 pub enum HwndType {
     Owned(HWND),
     Child(HWND),
@@ -500,11 +538,41 @@ impl HwndType {
 }
 
 
+/// The amount that a mouse wheel rotates by for one notch
+const WHEEL_DELTA: f32 = 120.0;
+
 pub const MSG_NEWOWNEDWINDOW: UINT = 0xADD;
 pub const MSG_NEWCHILDWINDOW: UINT = 0xADDC;
 pub const MSG_GAINFOCUS: UINT = 71913;
 pub const MSG_SETCURSOR: UINT = 32118;
 pub const MSG_ISACTIVEWIN: UINT = 0xAC20;
+pub const MSG_SETIMEPOS: UINT = 0xAC21;
+pub const MSG_SETGRAB: UINT = 0xAC22;
+pub const MSG_SETCUSTOMCURSOR: UINT = 0xAC23;
+
+/// Send a pen event, with the pen's position in client coordinates
+unsafe fn send_pen_event(hwnd: HWND, pen: &pointer::PenInfo) {
+    use event::{DeviceId, PenButtons};
+
+    let mut origin = winapi::POINT{ x: 0, y: 0 };
+    user32::ClientToScreen(hwnd, &mut origin);
+
+    send_event(hwnd, Event::Pen {
+        device: DeviceId(pen.device as u64),
+        x: pen.pos.0 - origin.x as f64,
+        y: pen.pos.1 - origin.y as f64,
+        pressure: pen.pressure,
+        tilt_x: pen.tilt_x,
+        tilt_y: pen.tilt_y,
+        rotation: pen.rotation,
+        eraser: pen.eraser,
+        buttons: PenButtons {
+            tip: pen.tip,
+            barrel: pen.barrel,
+            secondary_barrel: false
+        }
+    });
+}
 
 fn send_event(source: HWND, event: Event) {
     CALLBACK_DATA.with(|data| {
@@ -517,16 +585,123 @@ fn send_event(source: HWND, event: Event) {
 
         match index {
             -1  => (),
-            i   => {vector[i as usize].sender.send(event).ok();}
+            i   => vector[i as usize].sink.send(event)
         }
     });
 }
 
+/// Get how the window grabs the cursor, and the rectangle that it's confined to if there is one
+fn window_grab(hwnd: HWND) -> (GrabMode, Option<Rect>) {
+    CALLBACK_DATA.with(|data| {
+        let mut data = data.borrow_mut();
+        match *data {
+            Some(ref mut d) => match d.get_window_index(hwnd) {
+                -1 => (GrabMode::None, None),
+                i  => (d.win_vec[i as usize].grab, d.win_vec[i as usize].confine_rect)
+            },
+            None => (GrabMode::None, None)
+        }
+    })
+}
+
+/// Clip the cursor to match a window's grab mode. Windows releases the clip whenever another window
+/// becomes active, so this is done again whenever the window gets focus. There's no way to lock the
+/// cursor, so it gets clipped to a single pixel and hidden instead.
+unsafe fn apply_grab(hwnd: HWND, mode: GrabMode, rect: Option<Rect>) {
+    let mut client = winapi::RECT{ left: 0, top: 0, right: 0, bottom: 0 };
+    user32::GetClientRect(hwnd, &mut client);
+
+    let mut top_left = winapi::POINT{ x: client.left, y: client.top };
+    let mut bottom_right = winapi::POINT{ x: client.right, y: client.bottom };
+    user32::ClientToScreen(hwnd, &mut top_left);
+    user32::ClientToScreen(hwnd, &mut bottom_right);
+
+    let clip = match mode {
+        GrabMode::None      => {
+            user32::ClipCursor(ptr::null());
+            return;
+        }
+
+        GrabMode::Confined  => match rect {
+            // Windows lets the cursor reach the right and bottom edges, so a rectangle that reaches
+            // past the client area is cut down to it
+            Some(rect) => winapi::RECT {
+                left: (top_left.x + rect.x).max(top_left.x),
+                top: (top_left.y + rect.y).max(top_left.y),
+                right: (top_left.x + rect.x + rect.width as i32).min(bottom_right.x),
+                bottom: (top_left.y + rect.y + rect.height as i32).min(bottom_right.y)
+            },
+            None => winapi::RECT{ left: top_left.x, top: top_left.y, right: bottom_right.x, bottom: bottom_right.y }
+        },
+
+        GrabMode::Locked    => {
+            // Lock the cursor where it is, or in the window if it isn't over the window
+            let mut pos = winapi::POINT{ x: 0, y: 0 };
+            user32::GetCursorPos(&mut pos);
+            let x = pos.x.max(top_left.x).min(bottom_right.x - 1);
+            let y = pos.y.max(top_left.y).min(bottom_right.y - 1);
+
+            user32::SetCursor(ptr::null_mut());
+            winapi::RECT{ left: x, top: y, right: x + 1, bottom: y + 1 }
+        }
+    };
+
+    user32::ClipCursor(&clip);
+}
+
+/// Set whether a window has a composition going, returning whether it had one before
+fn set_ime_composing(hwnd: HWND, composing: bool) -> bool {
+    CALLBACK_DATA.with(|data| {
+        let mut data = data.borrow_mut();
+        match *data {
+            Some(ref mut d) => match d.get_window_index(hwnd) {
+                -1 => false,
+                i  => mem::replace(&mut d.win_vec[i as usize].ime_composing, composing)
+            },
+            None => false
+        }
+    })
+}
+
+/// Get the state of the modifier keys as of the message that's currently being handled
+fn modifiers_state() -> ModifiersState {
+    unsafe {
+        let down = |vk| user32::GetKeyState(vk) as u16 & 0x8000 != 0;
+        let toggled = |vk| user32::GetKeyState(vk) & 1 != 0;
+
+        ModifiersState {
+            shift: down(winapi::VK_SHIFT),
+            ctrl: down(winapi::VK_CONTROL),
+            alt: down(winapi::VK_MENU),
+            logo: down(winapi::VK_LWIN) || down(winapi::VK_RWIN),
+            caps_lock: toggled(winapi::VK_CAPITAL),
+            num_lock: toggled(winapi::VK_NUMLOCK)
+        }
+    }
+}
+
+/// Get the key for a key message. Windows sends the same virtual key codes for both shift, control
+/// and alt keys, so those are told apart by their scancodes instead.
+fn key_from_message(wparam: WPARAM, scancode: ScanCode) -> Option<Key> {
+    match (wparam as i32, scancode.key) {
+        (winapi::VK_SHIFT, Some(PhysicalKey::RShift))   => Some(Key::RShift),
+        (winapi::VK_SHIFT, _)                           => Some(Key::LShift),
+        (winapi::VK_CONTROL, Some(PhysicalKey::RCtrl))  => Some(Key::RCtrl),
+        (winapi::VK_CONTROL, _)                         => Some(Key::LCtrl),
+        (winapi::VK_MENU, Some(PhysicalKey::RAlt))      => Some(Key::RAlt),
+        (winapi::VK_MENU, _)                            => Some(Key::LAlt),
+        _ => keymap::key_from_vk(wparam as u32)
+    }
+}
+
 unsafe extern "system" fn callback(hwnd: HWND, msg: UINT,
                                    wparam: WPARAM, lparam: LPARAM)
                                    -> winapi::LRESULT {
     match msg {
-        winapi::WM_KEYDOWN  => {
+        // Keys pressed while alt is held down come in as system keys, which still have to go
+        // through DefWindowProc so that shortcuts like Alt+F4 keep working.
+        winapi::WM_KEYDOWN  |
+        winapi::WM_SYSKEYDOWN   => {
             use event::Event::KeyInput;
             use event::PressState;
 
@@ -537,26 +712,129 @@ unsafe extern "system" fn callback(hwnd: HWND, msg: UINT,
                 }
             };
 
-            match VKeyCode::from_u64(wparam) {
-                Some(k) => send_event(hwnd, KeyInput(press_state, k)),
-                None    => ()
+            let scancode = scancode_from_lparam(lparam);
+            send_event(hwnd, KeyInput(press_state, key_from_message(wparam, scancode), scancode, modifiers_state()));
+
+            match msg {
+                winapi::WM_SYSKEYDOWN => user32::DefWindowProcW(hwnd, msg, wparam, lparam),
+                _                     => 0
             }
+        }
+
+        // TranslateMessage has already taken care of the keyboard layout and dead keys, but characters
+        // outside of the BMP come in as two messages, one for each half of the surrogate pair.
+        winapi::WM_CHAR     => {
+            let unit = wparam as u16;
+
+            let c = CALLBACK_DATA.with(|data| {
+                let mut data = data.borrow_mut();
+                let win_data = match *data {
+                    Some(ref mut d) => match d.get_window_index(hwnd) {
+                        -1 => return None,
+                        i  => &mut d.win_vec[i as usize]
+                    },
+                    None => return None
+                };
 
+                match unit {
+                    0xD800...0xDBFF => {
+                        win_data.high_surrogate = Some(unit);
+                        None
+                    }
+
+                    0xDC00...0xDFFF => win_data.high_surrogate.take().and_then(|high| {
+                        let code = 0x10000 + ((high as u32 - 0xD800) << 10) + (unit as u32 - 0xDC00);
+                        char::from_u32(code)
+                    }),
+
+                    _ => {
+                        win_data.high_surrogate = None;
+                        char::from_u32(unit as u32)
+                    }
+                }
+            });
+
+            if let Some(c) = c {
+                send_event(hwnd, Event::ReceivedCharacter(c));
+            }
             0
         }
 
-        winapi::WM_KEYUP    => {
-            use event::Event::KeyInput;
-            use event::PressState;
+        // The app draws compositions itself from the composition events, so these don't go through
+        // DefWindowProc, which would open the system's composition window. The candidate window
+        // still comes from WM_IME_NOTIFY.
+        winapi::WM_IME_STARTCOMPOSITION => {
+            set_ime_composing(hwnd, true);
+            send_event(hwnd, Event::ImeCompositionStart);
+            0
+        }
 
-            match VKeyCode::from_u64(wparam) {
-                Some(k) => send_event(hwnd, KeyInput(PressState::Released, k)),
-                None    => ()
+        winapi::WM_IME_COMPOSITION  => {
+            let flags = lparam as DWORD;
+
+            // The result is sent as characters here instead of letting DefWindowProc turn it into
+            // WM_CHAR messages, so that they come right after the commit event.
+            if flags & ime::GCS_RESULTSTR != 0 {
+                if let Some(text) = ime::composition_string(hwnd, ime::GCS_RESULTSTR) {
+                    set_ime_composing(hwnd, false);
+                    send_event(hwnd, Event::ImeCompositionCommit(text.clone()));
+                    for c in text.chars() {
+                        send_event(hwnd, Event::ReceivedCharacter(c));
+                    }
+                }
             }
 
+            // Some input methods start a new composition in the same message that commits the
+            // last one, without another WM_IME_STARTCOMPOSITION
+            if flags & ime::GCS_COMPSTR != 0 {
+                if let Some(text) = ime::composition_string(hwnd, ime::GCS_COMPSTR) {
+                    let was_composing = set_ime_composing(hwnd, true);
+
+                    if !was_composing && text.is_empty() {
+                        set_ime_composing(hwnd, false);
+                    }
+                    else {
+                        if !was_composing {
+                            send_event(hwnd, Event::ImeCompositionStart);
+                        }
+
+                        let cursor = match flags & ime::GCS_CURSORPOS {
+                            0 => None,
+                            _ => {
+                                let pos = ime::cursor_pos(hwnd, &text);
+                                Some((pos, pos))
+                            }
+                        };
+                        send_event(hwnd, Event::ImeCompositionUpdate(text, cursor));
+                    }
+                }
+            }
+
+            0
+        }
+
+        winapi::WM_IME_ENDCOMPOSITION   => {
+            // Compositions that end without a result were cancelled
+            if set_ime_composing(hwnd, false) {
+                send_event(hwnd, Event::ImeCompositionCommit(String::new()));
+            }
             0
         }
 
+        winapi::WM_KEYUP    |
+        winapi::WM_SYSKEYUP => {
+            use event::Event::KeyInput;
+            use event::PressState;
+
+            let scancode = scancode_from_lparam(lparam);
+            send_event(hwnd, KeyInput(PressState::Released, key_from_message(wparam, scancode), scancode, modifiers_state()));
+
+            match msg {
+                winapi::WM_SYSKEYUP => user32::DefWindowProcW(hwnd, msg, wparam, lparam),
+                _                   => 0
+            }
+        }
+
         winapi::WM_SETCURSOR=> {
             let ok = CALLBACK_DATA.with(|data| {
                 let mut data = data.borrow_mut();
@@ -566,7 +844,11 @@ unsafe extern "system" fn callback(hwnd: HWND, msg: UINT,
                 };
 
                 if data.last_mpos != LPARAM::max_value() {
-                    user32::SetCursor(data.win_vec[index].cursor);
+                    let window = &data.win_vec[index];
+                    match window.grab {
+                        GrabMode::Locked => user32::SetCursor(ptr::null_mut()),
+                        _                => user32::SetCursor(window.cursor)
+                    };
                     0
                 }
                 else {
@@ -584,7 +866,7 @@ unsafe extern "system" fn callback(hwnd: HWND, msg: UINT,
             use event::ClickType;
             use event::MButton::*;
 
-            send_event(hwnd, Event::MButtonInput(ClickType::Single, Left));
+            send_event(hwnd, Event::MButtonInput(ClickType::Single, Left, modifiers_state()));
 
             0
         }
@@ -593,7 +875,7 @@ unsafe extern "system" fn callback(hwnd: HWND, msg: UINT,
             use event::ClickType;
             use event::MButton::*;
 
-            send_event(hwnd, Event::MButtonInput(ClickType::Released, Left));
+            send_event(hwnd, Event::MButtonInput(ClickType::Released, Left, modifiers_state()));
 
             0
         }
@@ -602,7 +884,7 @@ unsafe extern "system" fn callback(hwnd: HWND, msg: UINT,
             use event::ClickType;
             use event::MButton::*;
 
-            send_event(hwnd, Event::MButtonInput(ClickType::Double, Left));
+            send_event(hwnd, Event::MButtonInput(ClickType::Double, Left, modifiers_state()));
 
             0
         }
@@ -611,7 +893,7 @@ unsafe extern "system" fn callback(hwnd: HWND, msg: UINT,
             use event::ClickType;
             use event::MButton::*;
 
-            send_event(hwnd, Event::MButtonInput(ClickType::Single, Right));
+            send_event(hwnd, Event::MButtonInput(ClickType::Single, Right, modifiers_state()));
 
             0
         }
@@ -620,7 +902,7 @@ unsafe extern "system" fn callback(hwnd: HWND, msg: UINT,
             use event::ClickType;
             use event::MButton::*;
 
-            send_event(hwnd, Event::MButtonInput(ClickType::Released, Right));
+            send_event(hwnd, Event::MButtonInput(ClickType::Released, Right, modifiers_state()));
 
             0
         }
@@ -629,7 +911,7 @@ unsafe extern "system" fn callback(hwnd: HWND, msg: UINT,
             use event::ClickType;
             use event::MButton::*;
 
-            send_event(hwnd, Event::MButtonInput(ClickType::Double, Right));
+            send_event(hwnd, Event::MButtonInput(ClickType::Double, Right, modifiers_state()));
 
             0
         }
@@ -638,7 +920,7 @@ unsafe extern "system" fn callback(hwnd: HWND, msg: UINT,
             use event::ClickType;
             use event::MButton::*;
 
-            send_event(hwnd, Event::MButtonInput(ClickType::Single, Middle));
+            send_event(hwnd, Event::MButtonInput(ClickType::Single, Middle, modifiers_state()));
 
             0
         }
@@ -647,7 +929,7 @@ unsafe extern "system" fn callback(hwnd: HWND, msg: UINT,
             use event::ClickType;
             use event::MButton::*;
 
-            send_event(hwnd, Event::MButtonInput(ClickType::Released, Middle));
+            send_event(hwnd, Event::MButtonInput(ClickType::Released, Middle, modifiers_state()));
 
             0
         }
@@ -656,7 +938,7 @@ unsafe extern "system" fn callback(hwnd: HWND, msg: UINT,
             use event::ClickType;
             use event::MButton::*;
 
-            send_event(hwnd, Event::MButtonInput(ClickType::Double, Middle));
+            send_event(hwnd, Event::MButtonInput(ClickType::Double, Middle, modifiers_state()));
 
             0
         }
@@ -666,8 +948,8 @@ unsafe extern "system" fn callback(hwnd: HWND, msg: UINT,
             use event::MButton::*;
 
             match wparam >> 16 & 0xFFFF {
-                1 => send_event(hwnd, Event::MButtonInput(ClickType::Single, Button4)),
-                2 => send_event(hwnd, Event::MButtonInput(ClickType::Single, Button5)),
+                1 => send_event(hwnd, Event::MButtonInput(ClickType::Single, Button4, modifiers_state())),
+                2 => send_event(hwnd, Event::MButtonInput(ClickType::Single, Button5, modifiers_state())),
                 _ => panic!("A new mouse button approaches...")
             }
 
@@ -679,8 +961,8 @@ unsafe extern "system" fn callback(hwnd: HWND, msg: UINT,
             use event::MButton::*;
 
             match wparam >> 16 & 0xFFFF {
-                1 => send_event(hwnd, Event::MButtonInput(ClickType::Double, Button4)),
-                2 => send_event(hwnd, Event::MButtonInput(ClickType::Double, Button5)),
+                1 => send_event(hwnd, Event::MButtonInput(ClickType::Double, Button4, modifiers_state())),
+                2 => send_event(hwnd, Event::MButtonInput(ClickType::Double, Button5, modifiers_state())),
                 _ => panic!("A new mouse button approaches...")
             }
 
@@ -692,8 +974,8 @@ unsafe extern "system" fn callback(hwnd: HWND, msg: UINT,
             use event::MButton::*;
 
             match wparam >> 16 & 0xFFFF {
-                1 => send_event(hwnd, Event::MButtonInput(ClickType::Released, Button4)),
-                2 => send_event(hwnd, Event::MButtonInput(ClickType::Released, Button5)),
+                1 => send_event(hwnd, Event::MButtonInput(ClickType::Released, Button4, modifiers_state())),
+                2 => send_event(hwnd, Event::MButtonInput(ClickType::Released, Button5, modifiers_state())),
                 _ => panic!("A new mouse button approaches...")
             }
 
@@ -737,6 +1019,97 @@ unsafe extern "system" fn callback(hwnd: HWND, msg: UINT,
             })
         }
 
+        // The wheel's rotation is in multiples of WHEEL_DELTA, which is one notch. High-resolution
+        // wheels and touchpads send smaller amounts.
+        winapi::WM_MOUSEWHEEL   |
+        winapi::WM_MOUSEHWHEEL  => {
+            use event::{ScrollDelta, TouchPhase};
+
+            let delta = (wparam >> 16) as i16 as f32 / WHEEL_DELTA;
+            let delta = match msg {
+                winapi::WM_MOUSEWHEEL => ScrollDelta::Lines(0.0, delta),
+                _                     => ScrollDelta::Lines(delta, 0.0)
+            };
+
+            send_event(hwnd, Event::MouseWheel(delta, TouchPhase::Moved));
+
+            0
+        }
+
+        // Mice and pens go through DefWindowProc, which turns them into mouse messages, although pens
+        // get their own event first. Touches are handled here instead, so that they don't get
+        // turned into mouse messages too.
+        pointer::WM_POINTERDOWN     |
+        pointer::WM_POINTERUPDATE   |
+        pointer::WM_POINTERUP       |
+        pointer::WM_POINTERCAPTURECHANGED   => {
+            use event::TouchPhase;
+
+            let id = pointer::pointer_id(wparam);
+            let touch = match pointer::touch_info(id) {
+                Some(t) => t,
+                None    => {
+                    if msg != pointer::WM_POINTERCAPTURECHANGED {
+                        if let Some(pen) = pointer::pen_info(id) {
+                            send_pen_event(hwnd, &pen);
+                        }
+                    }
+                    return user32::DefWindowProcW(hwnd, msg, wparam, lparam);
+                }
+            };
+
+            let phase = match msg {
+                pointer::WM_POINTERDOWN                     => TouchPhase::Started,
+                pointer::WM_POINTERUPDATE                   => TouchPhase::Moved,
+                pointer::WM_POINTERUP if !touch.cancelled   => TouchPhase::Ended,
+                _                                           => TouchPhase::Cancelled
+            };
+
+            let mut pos = touch.pos;
+            user32::ScreenToClient(hwnd, &mut pos);
+            send_event(hwnd, Event::Touch{ id: id as u64, phase: phase, x: pos.x, y: pos.y, pressure: touch.pressure });
+
+            0
+        }
+
+        winapi::WM_PAINT        => {
+            // Painting has to validate the window, or Windows keeps sending WM_PAINT
+            let mut paint: winapi::PAINTSTRUCT = mem::zeroed();
+            user32::BeginPaint(hwnd, &mut paint);
+            user32::EndPaint(hwnd, &paint);
+            send_event(hwnd, Event::RedrawRequested);
+
+            0
+        }
+
+        // Raw input still has to go through DefWindowProc, which cleans it up
+        raw_input::WM_INPUT     => {
+            if let Some((dx, dy)) = raw_input::mouse_delta(lparam) {
+                send_event(hwnd, Event::MouseMotion(dx as f64, dy as f64));
+            }
+
+            user32::DefWindowProcW(hwnd, msg, wparam, lparam)
+        }
+
+        winapi::WM_SETFOCUS     => {
+            match window_grab(hwnd) {
+                (GrabMode::None, _) => (),
+                (mode, rect)        => apply_grab(hwnd, mode, rect)
+            }
+            send_event(hwnd, Event::Focused(true));
+
+            0
+        }
+
+        winapi::WM_KILLFOCUS    => {
+            if window_grab(hwnd).0 != GrabMode::None {
+                apply_grab(hwnd, GrabMode::None, None);
+            }
+            send_event(hwnd, Event::Focused(false));
+
+            0
+        }
+
         winapi::WM_MOUSELEAVE   => {
             CALLBACK_DATA.with(|data| {
                 let mut data = data.borrow_mut();
@@ -768,12 +1141,25 @@ unsafe extern "system" fn callback(hwnd: HWND, msg: UINT,
             };
 
             send_event(hwnd, Event::Resized(resize_type, lparam as u32 >> 16, lparam as u32 & 0xFFFF));
+
+            // The clip rectangle doesn't follow the window around
+            if let (GrabMode::Confined, rect) = window_grab(hwnd) {
+                if hwnd == user32::GetFocus() {
+                    apply_grab(hwnd, GrabMode::Confined, rect);
+                }
+            }
             0
         }
 
         winapi::WM_MOVE     => {
             send_event(hwnd, Event::Moved(lparam as i32 >> 16, lparam as i16 as i32));
 
+            if let (GrabMode::Confined, rect) = window_grab(hwnd) {
+                if hwnd == user32::GetFocus() {
+                    apply_grab(hwnd, GrabMode::Confined, rect);
+                }
+            }
+
             0
         }
 
@@ -801,6 +1187,70 @@ unsafe extern "system" fn callback(hwnd: HWND, msg: UINT,
             0
         }
 
+        MSG_SETCUSTOMCURSOR => {
+            // The sender is blocked until this returns, so the cursor is still alive
+            let custom = &*(lparam as *const CustomCursor);
+
+            CALLBACK_DATA.with(|data| {
+                let mut data = data.borrow_mut();
+                if let Some(ref mut d) = *data {
+                    let index = d.get_window_index(hwnd) as usize;
+                    let window = &mut d.win_vec[index];
+
+                    let cursor = match window.cursors.get(&custom.id()) {
+                        Some(&c) => c,
+                        None     => {
+                            let c = cursor::create(custom);
+                            if !c.is_null() {
+                                window.cursors.insert(custom.id(), c);
+                            }
+                            c
+                        }
+                    };
+
+                    if !cursor.is_null() {
+                        window.cursor = cursor;
+                        if hwnd == user32::GetActiveWindow() {
+                            user32::SetCursor(cursor);
+                        }
+                    }
+                }
+            });
+
+            0
+        }
+
+        MSG_SETGRAB         => {
+            let mode = match wparam {
+                1 => GrabMode::Confined,
+                2 => GrabMode::Locked,
+                _ => GrabMode::None
+            };
+            // The sender is blocked until this returns, so the rectangle is still alive
+            let rect = *(lparam as *const Option<Rect>);
+
+            CALLBACK_DATA.with(|data| {
+                let mut data = data.borrow_mut();
+                if let Some(ref mut d) = *data {
+                    let index = d.get_window_index(hwnd) as usize;
+                    d.win_vec[index].grab = mode;
+                    d.win_vec[index].confine_rect = rect;
+                }
+            });
+
+            if hwnd == user32::GetFocus() {
+                apply_grab(hwnd, mode, rect);
+            }
+
+            0
+        }
+
+        MSG_SETIMEPOS       => {
+            ime::set_position(hwnd, wparam as i32, lparam as i32);
+
+            0
+        }
+
         MSG_GAINFOCUS       => {
             user32::SetFocus(hwnd);
 
@@ -809,12 +1259,12 @@ unsafe extern "system" fn callback(hwnd: HWND, msg: UINT,
 
         MSG_NEWOWNEDWINDOW |
         MSG_NEWCHILDWINDOW  => {
-            use std::sync::mpsc;
-
             // For this message, the pointer to the window config is stored in the
-            // LPARAM parameter. This turns them into proper pointers
+            // LPARAM parameter and the pointer to the window's event sink is stored
+            // in the WPARAM parameter. This turns them into proper pointers
             // and gets the objects from the pointers.
             let config = &*(lparam as *const WindowConfig);
+            let sink = (*(wparam as *const EventSink)).clone();
             let parent_hwnd = match msg {
                 MSG_NEWOWNEDWINDOW => HwndType::Owned(hwnd),
                 MSG_NEWCHILDWINDOW => HwndType::Child(hwnd),
@@ -822,8 +1272,6 @@ unsafe extern "system" fn callback(hwnd: HWND, msg: UINT,
             };
 
             let wrapper_window = WindowWrapper::new(config, parent_hwnd);
-            // The window's event sender/reciever pair
-            let (tx, rx) = mpsc::channel();
 
             CALLBACK_DATA.with(move |data| {
                 let mut data = data.borrow_mut();
@@ -832,9 +1280,9 @@ unsafe extern "system" fn callback(hwnd: HWND, msg: UINT,
                     match wrapper_window {
                         Ok(wr) => {
                             // Add the window data to the win_vec
-                            data.win_vec.push(WindowDataIntern::new(wr.0, tx));
-                            // Send a window data wrapper along the sender                         
-                            data.win_sender.send(Ok(WindowData(wr, rx))).ok();
+                            data.win_vec.push(WindowDataIntern::new(wr.0, sink));
+                            // Send the window wrapper along the sender
+                            data.win_sender.send(Ok(wr)).ok();
                         }
 
                         Err(e) => {
@@ -860,9 +1308,15 @@ unsafe extern "system" fn callback(hwnd: HWND, msg: UINT,
             0
         }
 
-        winapi::WM_DESTROY  => {
-            use event::Event::Closed;
+        // DefWindowProc would destroy the window straight away, so the app gets to decide whether
+        // that happens instead
+        winapi::WM_CLOSE    => {
+            send_event(hwnd, Event::CloseRequested);
+
+            0
+        }
 
+        winapi::WM_DESTROY  => {
             CALLBACK_DATA.with(|data| {
                 let mut data = data.borrow_mut();
 
@@ -872,10 +1326,16 @@ unsafe extern "system" fn callback(hwnd: HWND, msg: UINT,
                 };
 
                 // If this window's information is still in the vector, remove it from
-                // the vector and send the closed message for this window. 
+                // the vector and send the destroyed message for this window. 
                 match index {
                     -1  => (),
-                    i   => {vector.remove(i as usize).sender.send(Closed).ok();}
+                    i   => {
+                        let window = vector.remove(i as usize);
+                        for &c in window.cursors.values() {
+                            cursor::destroy(c);
+                        }
+                        window.sink.send(Event::Destroyed);
+                    }
                 }
             });
 
@@ -0,0 +1,187 @@
+use x11::xlib;
+
+use std::ptr;
+use std::char;
+use std::slice;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_ulong, c_void};
+
+use api::backend::EventSink;
+use event::Event;
+
+/// The state of a window's input method composition. Xlib calls the preedit callbacks with a
+/// pointer to this, so it's boxed to keep it from moving.
+pub struct ImeState {
+    sink: EventSink,
+    /// The text being composed. XIM measures everything in characters, so it's easiest to keep it
+    /// as characters too.
+    text: Vec<char>,
+    caret: usize,
+    composing: bool,
+    /// Set when the input method has finished composing but hasn't sent the result yet
+    commit_pending: bool
+}
+
+impl ImeState {
+    pub fn new(sink: EventSink) -> Box<ImeState> {
+        Box::new(
+            ImeState {
+                sink: sink,
+                text: Vec::new(),
+                caret: 0,
+                composing: false,
+                commit_pending: false
+            }
+        )
+    }
+
+    /// Handle text that was typed into the window. Input methods send the text that a composition
+    /// produces the same way as ordinary typing, so if there's a composition that hasn't been
+    /// committed yet, this is what commits it.
+    pub fn received_text(&mut self, text: &str) {
+        if self.composing || self.commit_pending {
+            self.composing = false;
+            self.commit_pending = false;
+            self.text.clear();
+            self.caret = 0;
+            self.sink.send(Event::ImeCompositionCommit(text.to_owned()));
+        }
+    }
+
+    fn send_update(&self) {
+        let caret = self.text[..self.caret].iter().map(|c| c.len_utf8()).sum();
+        self.sink.send(Event::ImeCompositionUpdate(self.text.iter().cloned().collect(), Some((caret, caret))));
+    }
+}
+
+/// Create an input context that reports compositions through `state`. If the input method can't
+/// report compositions, this falls back to a context that the input method draws compositions for
+/// on its own.
+pub unsafe fn create_ic(im: xlib::XIM, window: xlib::Window, state: *mut ImeState) -> xlib::XIC {
+    let callback = |f: unsafe extern "C" fn(xlib::XIM, xlib::XPointer, xlib::XPointer)| {
+        xlib::XIMCallback {
+            client_data: state as xlib::XPointer,
+            callback: Some(f)
+        }
+    };
+    let start = callback(preedit_start);
+    let done = callback(preedit_done);
+    let draw = callback(preedit_draw);
+    let caret = callback(preedit_caret);
+
+    let attributes = xlib::XVaCreateNestedList(0,
+                                               xlib::XNPreeditStartCallback_0.as_ptr(), &start,
+                                               xlib::XNPreeditDoneCallback_0.as_ptr(), &done,
+                                               xlib::XNPreeditDrawCallback_0.as_ptr(), &draw,
+                                               xlib::XNPreeditCaretCallback_0.as_ptr(), &caret,
+                                               ptr::null_mut::<c_void>());
+
+    let ic = xlib::XCreateIC(im,
+                             xlib::XNInputStyle_0.as_ptr(), (xlib::XIMPreeditCallbacks | xlib::XIMStatusNothing) as c_ulong,
+                             xlib::XNClientWindow_0.as_ptr(), window,
+                             xlib::XNFocusWindow_0.as_ptr(), window,
+                             xlib::XNPreeditAttributes_0.as_ptr(), attributes,
+                             ptr::null_mut::<c_void>());
+    xlib::XFree(attributes);
+
+    if !ic.is_null() {
+        return ic;
+    }
+
+    xlib::XCreateIC(im,
+                    xlib::XNInputStyle_0.as_ptr(), (xlib::XIMPreeditNothing | xlib::XIMStatusNothing) as c_ulong,
+                    xlib::XNClientWindow_0.as_ptr(), window,
+                    xlib::XNFocusWindow_0.as_ptr(), window,
+                    ptr::null_mut::<c_void>())
+}
+
+/// Move the input method's candidate window so that it's next to the given point in the window
+pub unsafe fn set_spot(ic: xlib::XIC, x: i32, y: i32) {
+    let spot = xlib::XPoint {
+        x: x as i16,
+        y: y as i16
+    };
+
+    let attributes = xlib::XVaCreateNestedList(0, xlib::XNSpotLocation_0.as_ptr(), &spot, ptr::null_mut::<c_void>());
+    xlib::XSetICValues(ic, xlib::XNPreeditAttributes_0.as_ptr(), attributes, ptr::null_mut::<c_void>());
+    xlib::XFree(attributes);
+}
+
+unsafe extern "C" fn preedit_start(_: xlib::XIM, client_data: xlib::XPointer, _: xlib::XPointer) {
+    let state = &mut *(client_data as *mut ImeState);
+
+    state.composing = true;
+    state.commit_pending = false;
+    state.text.clear();
+    state.caret = 0;
+    state.sink.send(Event::ImeCompositionStart);
+}
+
+unsafe extern "C" fn preedit_done(_: xlib::XIM, client_data: xlib::XPointer, _: xlib::XPointer) {
+    let state = &mut *(client_data as *mut ImeState);
+
+    if state.composing {
+        state.composing = false;
+
+        // Clearing the text before finishing means that the composition was cancelled. Otherwise,
+        // the result is on its way as a key press.
+        match state.text.is_empty() {
+            true  => state.sink.send(Event::ImeCompositionCommit(String::new())),
+            false => state.commit_pending = true
+        }
+    }
+}
+
+unsafe extern "C" fn preedit_draw(_: xlib::XIM, client_data: xlib::XPointer, call_data: xlib::XPointer) {
+    let state = &mut *(client_data as *mut ImeState);
+    let draw = &*(call_data as *const xlib::XIMPreeditDrawCallbackStruct);
+
+    // The callback replaces `chg_length` characters starting at `chg_first` with the new text,
+    // which is null if characters are only being deleted.
+    let new_text: Vec<char> = match draw.text.is_null() {
+        false => text_chars(&*draw.text),
+        true  => Vec::new()
+    };
+
+    let first = (draw.chg_first.max(0) as usize).min(state.text.len());
+    let last = (first + draw.chg_length.max(0) as usize).min(state.text.len());
+    state.text.splice(first..last, new_text);
+    state.caret = (draw.caret.max(0) as usize).min(state.text.len());
+
+    state.send_update();
+}
+
+unsafe extern "C" fn preedit_caret(_: xlib::XIM, client_data: xlib::XPointer, call_data: xlib::XPointer) {
+    let state = &mut *(client_data as *mut ImeState);
+    let caret = &mut *(call_data as *mut xlib::XIMPreeditCaretCallbackStruct);
+
+    // The other directions depend on how the text is laid out, which only the app knows
+    if caret.direction == xlib::XIMCaretDirection::XIMAbsolutePosition {
+        state.caret = (caret.position.max(0) as usize).min(state.text.len());
+        state.send_update();
+    }
+    caret.position = state.caret as i32;
+}
+
+/// Get the characters in an XIM text, which is either a multibyte string in the locale's encoding
+/// or a wide string.
+unsafe fn text_chars(text: &xlib::XIMText) -> Vec<char> {
+    match text.encoding_is_wchar {
+        xlib::False => {
+            let string = text.string.multi_byte;
+            if string.is_null() {
+                return Vec::new();
+            }
+            String::from_utf8_lossy(CStr::from_ptr(string as *const c_char).to_bytes()).chars().collect()
+        }
+
+        _ => {
+            // The x11 crate declares the wide string as a single wchar_t instead of a pointer
+            let string = *(&text.string as *const _ as *const *const u32);
+            if string.is_null() {
+                return Vec::new();
+            }
+            slice::from_raw_parts(string, text.length as usize).iter().filter_map(|&c| char::from_u32(c)).collect()
+        }
+    }
+}
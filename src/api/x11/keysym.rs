@@ -0,0 +1,25 @@
+use x11::xlib;
+use x11::keysym::{XK_KP_Space, XK_KP_9};
+
+use std::os::raw::c_uint;
+
+use event::Key;
+use keymap;
+
+/// Get the key for a key event, or `None` for keysyms that don't have a `Key`.
+pub fn key_from_event(xkey: &mut xlib::XKeyEvent) -> Option<Key> {
+    unsafe {
+        // Index 0 is the unshifted keysym, which is what keys correspond to. The exception is the
+        // numpad: keys distinguish between the numbers and the navigation keys based on num lock, so
+        // the numpad numbers are taken from index 1 when num lock is on.
+        let keysym = xlib::XLookupKeysym(xkey, 0) as c_uint;
+
+        let keysym = match keysym {
+            XK_KP_Space...XK_KP_9 if xkey.state & xlib::Mod2Mask != 0 =>
+                xlib::XLookupKeysym(xkey, 1) as c_uint,
+            _ => keysym
+        };
+
+        keymap::key_from_keysym(keysym)
+    }
+}
@@ -0,0 +1,322 @@
+pub mod wrapper;
+pub mod soft_buffer;
+mod keysym;
+mod ime;
+mod xinput;
+use self::wrapper::{ParentType, WindowWrapper, XConnection, CallbackData};
+
+use x11::xlib;
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use {Cursor, GrabMode, Rect};
+use api::glx::{self, FbConfig};
+use api::backend::{WindowBackend, NativeWindow, EventSink};
+use error::{TubResult, GlCreationResult};
+use config::{WindowConfig, PixelFormat};
+
+/// An X window. Unlike win32 windows, X windows don't need to borrow anything from their owner,
+/// since the connection they share is reference-counted.
+pub struct Window {
+    pub wrapper: WindowWrapper,
+    /// Shared with the connection's event thread, which uses it to route events to the
+    /// correct window.
+    callback_data: Arc<Mutex<CallbackData>>,
+    /// The GLX framebuffer configuration that the window was created with, or the reason that
+    /// one couldn't be found. This is used when creating an OpenGL context for the window.
+    pub fb_config: GlCreationResult<FbConfig>,
+    config: WindowConfig,
+    pixel_format: PixelFormat
+}
+
+impl Window {
+    /// Create a new window with the specified title and config
+    pub fn new(config: WindowConfig, pixel_format: PixelFormat, sink: EventSink) -> TubResult<Window> {
+        let conn = Arc::new(try!(XConnection::new()));
+        let fb_config = glx::choose_fb_config(&conn, &pixel_format, config.transparent);
+        let visual = choose_visual(&conn, &config, &fb_config);
+
+        let wrapper = try!(WindowWrapper::new(&conn, &config, visual.as_ref(), ParentType::Top));
+
+        let callback_data = Arc::new(Mutex::new(CallbackData::new(wrapper.0, sink)));
+
+        // Like on win32, each top-level window gets a thread that receives its events and sends
+        // them on to the events loop. Owned and child windows share the
+        // connection, and thus the thread, of their owner.
+        {
+            let conn = conn.clone();
+            let callback_data = callback_data.clone();
+            let top_window = wrapper.0;
+
+            thread::spawn(move || wrapper::event_loop(conn, callback_data, top_window));
+        }
+
+        Ok(
+            Window {
+                wrapper: wrapper,
+                callback_data: callback_data,
+                fb_config: fb_config,
+                config: config,
+                pixel_format: pixel_format
+            }
+        )
+    }
+
+    /// Creates a window that is owned by the calling window. Owned windows are always drawn on
+    /// top of their owner, don't appear on the taskbar and share a connection with their owner.
+    pub fn new_owned(&self, config: WindowConfig, pixel_format: PixelFormat, sink: EventSink) -> TubResult<Window> {
+        self.new_dependent(config, pixel_format, sink, ParentType::Owned(self.wrapper.0))
+    }
+
+    pub fn new_child(&self, config: WindowConfig, pixel_format: PixelFormat, sink: EventSink) -> TubResult<Window> {
+        self.new_dependent(config, pixel_format, sink, ParentType::Child(self.wrapper.0))
+    }
+
+    fn new_dependent(&self, config: WindowConfig, pixel_format: PixelFormat, sink: EventSink, parent: ParentType) -> TubResult<Window> {
+        let conn = self.wrapper.1.clone();
+        let fb_config = glx::choose_fb_config(&conn, &pixel_format, config.transparent);
+        let visual = choose_visual(&conn, &config, &fb_config);
+
+        let wrapper = try!(WindowWrapper::new(&conn, &config, visual.as_ref(), parent));
+
+        self.callback_data.lock().unwrap().add_window(wrapper.0, sink);
+
+        Ok(
+            Window {
+                wrapper: wrapper,
+                callback_data: self.callback_data.clone(),
+                fb_config: fb_config,
+                config: config,
+                pixel_format: pixel_format
+            }
+        )
+    }
+
+    /// Allow the window to take user input.
+    #[inline]
+    pub fn enable(&self) {
+        self.callback_data.lock().unwrap().set_enabled(self.wrapper.0, true);
+    }
+
+    /// Disallow the window from taking user input.
+    #[inline]
+    pub fn disable(&self) {
+        self.callback_data.lock().unwrap().set_enabled(self.wrapper.0, false);
+    }
+
+    /// Sets the cursor position relative to window space. Like on win32, this will fail if the
+    /// window isn't currently active or the cursor is outside of the window's client area.
+    #[inline]
+    pub fn set_cursor_pos(&self, x: i32, y: i32) {
+        let cursor_in_client = {
+            let size = match self.wrapper.get_inner_size() {
+                Some(s) => (s.0 as i32, s.1 as i32),
+                None    => return
+            };
+            let (cx, cy) = match self.wrapper.get_cursor_pos() {
+                Some(p) => p,
+                None    => return
+            };
+
+            let (xmin, ymin) = match self.wrapper.get_inner_pos() {
+                Some(p) => p,
+                None    => return
+            };
+            let (xmax, ymax) = (xmin + size.0, ymin + size.1);
+
+            xmin < cx && cx < xmax &&
+            ymin < cy && cy < ymax
+        };
+
+        if self.wrapper.is_active() && cursor_in_client {
+            self.wrapper.warp_cursor(x, y);
+        }
+    }
+
+    /// Grab the cursor while the window has focus. X can grab the cursor on any window, so this
+    /// always succeeds.
+    #[inline]
+    pub fn set_cursor_grab(&self, mode: GrabMode) -> Option<()> {
+        self.callback_data.lock().unwrap().set_cursor_grab(&self.wrapper.1, self.wrapper.0, mode, None);
+        Some(())
+    }
+
+    /// Confine the cursor to part of the window while the window has focus. This uses the same
+    /// grab as `set_cursor_grab`, so it always succeeds too.
+    #[inline]
+    pub fn confine_cursor(&self, rect: Option<Rect>) -> Option<()> {
+        let mode = match rect {
+            Some(_) => GrabMode::Confined,
+            None    => GrabMode::None
+        };
+        self.callback_data.lock().unwrap().set_cursor_grab(&self.wrapper.1, self.wrapper.0, mode, rect);
+        Some(())
+    }
+
+    /// Move the input method's candidate window next to a point in window space
+    #[inline]
+    pub fn set_ime_position(&self, x: i32, y: i32) {
+        self.callback_data.lock().unwrap().set_ime_position(&self.wrapper.1, self.wrapper.0, x, y);
+    }
+
+    pub fn get_config(&self) -> &WindowConfig {
+        &self.config
+    }
+
+    pub fn get_pixel_format(&self) -> &PixelFormat {
+        &self.pixel_format
+    }
+}
+
+impl WindowBackend for Window {
+    fn new_owned<'a>(&'a self, config: WindowConfig, pixel_format: PixelFormat, sink: EventSink) -> TubResult<Box<WindowBackend + 'a>> {
+        Window::new_owned(self, config, pixel_format, sink).map(|w| Box::new(w) as Box<WindowBackend>)
+    }
+
+    fn new_child<'a>(&'a self, config: WindowConfig, pixel_format: PixelFormat, sink: EventSink) -> TubResult<Box<WindowBackend + 'a>> {
+        Window::new_child(self, config, pixel_format, sink).map(|w| Box::new(w) as Box<WindowBackend>)
+    }
+
+    #[inline]
+    fn set_title(&self, title: &str) {
+        self.wrapper.set_title(title);
+    }
+
+    #[inline]
+    fn show(&self) {
+        self.wrapper.show();
+    }
+
+    #[inline]
+    fn hide(&self) {
+        self.wrapper.hide();
+    }
+
+    #[inline]
+    fn close(&self) {
+        self.wrapper.kill();
+    }
+
+    #[inline]
+    fn enable(&self) {
+        Window::enable(self);
+    }
+
+    #[inline]
+    fn disable(&self) {
+        Window::disable(self);
+    }
+
+    #[inline]
+    fn focus(&self) {
+        self.wrapper.focus();
+    }
+
+    #[inline]
+    fn get_inner_pos(&self) -> Option<(i32, i32)> {
+        self.wrapper.get_inner_pos()
+    }
+
+    #[inline]
+    fn get_outer_pos(&self) -> Option<(i32, i32)> {
+        self.wrapper.get_outer_pos()
+    }
+
+    #[inline]
+    fn get_inner_size(&self) -> Option<(u32, u32)> {
+        self.wrapper.get_inner_size()
+    }
+
+    #[inline]
+    fn get_outer_size(&self) -> Option<(u32, u32)> {
+        self.wrapper.get_outer_size()
+    }
+
+    #[inline]
+    fn set_pos(&self, x: i32, y: i32) -> Option<()> {
+        self.wrapper.set_pos(x, y)
+    }
+
+    #[inline]
+    fn set_inner_size(&self, x: u32, y: u32) -> Option<()> {
+        self.wrapper.set_inner_size(x, y)
+    }
+
+    #[inline]
+    fn is_active(&self) -> bool {
+        self.wrapper.is_active()
+    }
+
+    #[inline]
+    fn set_cursor(&self, cursor: &Cursor) {
+        match *cursor {
+            Cursor::System(cursor_type) => self.wrapper.set_cursor(cursor_type),
+            Cursor::Custom(ref cursor)  => self.callback_data.lock().unwrap().set_custom_cursor(&self.wrapper.1, self.wrapper.0, cursor)
+        }
+    }
+
+    #[inline]
+    fn set_cursor_pos(&self, x: i32, y: i32) {
+        Window::set_cursor_pos(self, x, y);
+    }
+
+    #[inline]
+    fn set_cursor_grab(&self, mode: GrabMode) -> Option<()> {
+        Window::set_cursor_grab(self, mode)
+    }
+
+    #[inline]
+    fn confine_cursor(&self, rect: Option<Rect>) -> Option<()> {
+        Window::confine_cursor(self, rect)
+    }
+
+    #[inline]
+    fn set_ime_position(&self, x: i32, y: i32) {
+        Window::set_ime_position(self, x, y);
+    }
+
+    #[inline]
+    fn get_config(&self) -> &WindowConfig {
+        Window::get_config(self)
+    }
+
+    #[inline]
+    fn get_pixel_format(&self) -> &PixelFormat {
+        Window::get_pixel_format(self)
+    }
+
+    #[inline]
+    fn native<'a>(&'a self) -> NativeWindow<'a> {
+        NativeWindow::X11(self)
+    }
+}
+
+impl Drop for Window {
+    fn drop(&mut self) {
+        self.wrapper.kill();
+    }
+}
+
+/// Pick the visual that the window gets created with. `None` means that the window inherits the
+/// visual of its parent.
+fn choose_visual(conn: &XConnection, config: &WindowConfig, fb_config: &GlCreationResult<FbConfig>) -> Option<xlib::XVisualInfo> {
+    unsafe {
+        // If there's a GLX configuration for the pixel format, the window has to use its visual or
+        // OpenGL won't be able to draw to it.
+        if let Ok(ref fb_config) = *fb_config {
+            return Some(fb_config.visual_info);
+        }
+
+        // Transparent windows need a visual with an alpha channel, which the default visual
+        // usually doesn't have.
+        if config.transparent {
+            let mut visual_info = ::std::mem::zeroed();
+            if xlib::XMatchVisualInfo(conn.display, conn.screen, 32, xlib::TrueColor, &mut visual_info) != 0 {
+                return Some(visual_info);
+            }
+        }
+
+        None
+    }
+}
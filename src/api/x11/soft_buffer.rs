@@ -0,0 +1,250 @@
+use x11::xlib;
+use libc;
+
+use std::ptr;
+use std::mem;
+use std::slice;
+use std::ffi::CStr;
+use std::marker::PhantomData;
+use std::os::raw::{c_char, c_int, c_uint, c_ulong};
+
+use error::{TubResult, TubError};
+use super::Window;
+
+/// The MIT-SHM segment description, which the x11 crate doesn't include
+#[repr(C)]
+struct XShmSegmentInfo {
+    shmseg: c_ulong,
+    shmid: c_int,
+    shmaddr: *mut c_char,
+    read_only: c_int
+}
+
+#[link(name = "Xext")]
+extern "C" {
+    fn XShmQueryExtension(display: *mut xlib::Display) -> c_int;
+    fn XShmCreateImage(display: *mut xlib::Display, visual: *mut xlib::Visual, depth: c_uint, format: c_int, data: *mut c_char,
+                       shminfo: *mut XShmSegmentInfo, width: c_uint, height: c_uint) -> *mut xlib::XImage;
+    fn XShmAttach(display: *mut xlib::Display, shminfo: *mut XShmSegmentInfo) -> c_int;
+    fn XShmDetach(display: *mut xlib::Display, shminfo: *mut XShmSegmentInfo) -> c_int;
+    fn XShmPutImage(display: *mut xlib::Display, drawable: xlib::Drawable, gc: xlib::GC, image: *mut xlib::XImage, src_x: c_int, src_y: c_int,
+                    dst_x: c_int, dst_y: c_int, width: c_uint, height: c_uint, send_event: c_int) -> c_int;
+}
+
+/// A buffer of pixels that gets copied into an X window with XPutImage. If the X server is running
+/// on the same machine, the pixels are kept in memory shared with the server through the MIT-SHM
+/// extension, which saves sending the entire image over the connection every time it's presented.
+pub struct SoftBuffer<'w> {
+    display: *mut xlib::Display,
+    window: xlib::Window,
+    gc: xlib::GC,
+    visual: *mut xlib::Visual,
+    depth: c_int,
+    use_shm: bool,
+    image: Option<Image>,
+    /// Guarantees that this won't live longer than the window that created it
+    window_lifetime: PhantomData<&'w ()>
+}
+
+struct Image {
+    display: *mut xlib::Display,
+    ximage: *mut xlib::XImage,
+    size: (u32, u32),
+    storage: ImageStorage
+}
+
+enum ImageStorage {
+    /// The shared memory segment's info has to stay in one place, since the image keeps a pointer
+    /// to it.
+    Shm(Box<XShmSegmentInfo>),
+    Owned(Vec<u32>)
+}
+
+impl<'w> SoftBuffer<'w> {
+    pub fn new(window: &'w Window) -> TubResult<SoftBuffer<'w>> {
+        unsafe {
+            let display = window.wrapper.1.display;
+
+            let mut attributes = mem::uninitialized();
+            xlib::XGetWindowAttributes(display, window.wrapper.0, &mut attributes);
+
+            // Pixels get copied straight into the image, so the visual has to have the same layout
+            // as the buffer.
+            let visual = &*attributes.visual;
+            if visual.red_mask != 0xFF0000 || visual.green_mask != 0xFF00 || visual.blue_mask != 0xFF {
+                return Err(TubError::OsError("The window's visual doesn't use 8-bit RGB color".to_owned()));
+            }
+            if bits_per_pixel(display, attributes.depth) != Some(32) {
+                return Err(TubError::OsError("The window's visual doesn't use 32-bit pixels".to_owned()));
+            }
+
+            Ok(
+                SoftBuffer {
+                    display: display,
+                    window: window.wrapper.0,
+                    gc: xlib::XCreateGC(display, window.wrapper.0, 0, ptr::null_mut()),
+                    visual: attributes.visual,
+                    depth: attributes.depth,
+                    use_shm: shm_available(display),
+                    image: None,
+                    window_lifetime: PhantomData
+                }
+            )
+        }
+    }
+
+    /// Replace the image with a new one of the given size. The new image is cleared to black.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.image = None;
+
+        if width != 0 && height != 0 {
+            self.image = unsafe {
+                match self.use_shm {
+                    true  => self.create_shm_image(width, height).or_else(|| self.create_owned_image(width, height)),
+                    false => self.create_owned_image(width, height)
+                }
+            };
+        }
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [u32] {
+        match self.image {
+            Some(Image{storage: ImageStorage::Owned(ref mut pixels), ..}) => pixels,
+            Some(Image{storage: ImageStorage::Shm(ref shminfo), size, ..}) => unsafe {
+                slice::from_raw_parts_mut(shminfo.shmaddr as *mut u32, (size.0 * size.1) as usize)
+            },
+            None => &mut []
+        }
+    }
+
+    /// Copy the image into the window. With MIT-SHM, this blocks until the server is done reading the
+    /// image, so that it doesn't get drawn to in the middle of being copied.
+    pub fn present(&self) {
+        let image = match self.image {
+            Some(ref image) => image,
+            None            => return
+        };
+
+        unsafe {
+            match image.storage {
+                ImageStorage::Shm(_) => {
+                    XShmPutImage(self.display, self.window, self.gc, image.ximage, 0, 0, 0, 0, image.size.0, image.size.1, xlib::False);
+                    xlib::XSync(self.display, xlib::False);
+                }
+
+                ImageStorage::Owned(_) => {
+                    xlib::XPutImage(self.display, self.window, self.gc, image.ximage, 0, 0, 0, 0, image.size.0, image.size.1);
+                    xlib::XFlush(self.display);
+                }
+            }
+        }
+    }
+
+    unsafe fn create_shm_image(&self, width: u32, height: u32) -> Option<Image> {
+        let mut shminfo = Box::new(mem::zeroed::<XShmSegmentInfo>());
+
+        let ximage = XShmCreateImage(self.display, self.visual, self.depth as c_uint, xlib::ZPixmap, ptr::null_mut(), &mut *shminfo, width, height);
+        if ximage.is_null() {
+            return None;
+        }
+
+        let len = (*ximage).bytes_per_line as usize * height as usize;
+        shminfo.shmid = libc::shmget(libc::IPC_PRIVATE, len, libc::IPC_CREAT | 0o600);
+        if shminfo.shmid == -1 {
+            xlib::XDestroyImage(ximage);
+            return None;
+        }
+
+        shminfo.shmaddr = libc::shmat(shminfo.shmid, ptr::null(), 0) as *mut c_char;
+        // The segment is only actually removed once both we and the server have detached from it,
+        // so marking it for removal now makes sure that it doesn't outlive the process.
+        libc::shmctl(shminfo.shmid, libc::IPC_RMID, ptr::null_mut());
+        if shminfo.shmaddr as isize == -1 {
+            xlib::XDestroyImage(ximage);
+            return None;
+        }
+
+        (*ximage).data = shminfo.shmaddr;
+        XShmAttach(self.display, &mut *shminfo);
+        xlib::XSync(self.display, xlib::False);
+
+        Some(
+            Image {
+                display: self.display,
+                ximage: ximage,
+                size: (width, height),
+                storage: ImageStorage::Shm(shminfo)
+            }
+        )
+    }
+
+    unsafe fn create_owned_image(&self, width: u32, height: u32) -> Option<Image> {
+        let mut pixels = vec![0; width as usize * height as usize];
+
+        let ximage = xlib::XCreateImage(self.display, self.visual, self.depth as c_uint, xlib::ZPixmap, 0,
+                                        pixels.as_mut_ptr() as *mut c_char, width, height, 32, 0);
+        if ximage.is_null() {
+            return None;
+        }
+
+        Some(
+            Image {
+                display: self.display,
+                ximage: ximage,
+                size: (width, height),
+                storage: ImageStorage::Owned(pixels)
+            }
+        )
+    }
+}
+
+impl<'w> Drop for SoftBuffer<'w> {
+    fn drop(&mut self) {
+        self.image = None;
+        unsafe{ xlib::XFreeGC(self.display, self.gc) };
+    }
+}
+
+impl Drop for Image {
+    fn drop(&mut self) {
+        unsafe {
+            if let ImageStorage::Shm(ref mut shminfo) = self.storage {
+                XShmDetach(self.display, &mut **shminfo);
+                xlib::XSync(self.display, xlib::False);
+                libc::shmdt(shminfo.shmaddr as *const _);
+            }
+
+            // XDestroyImage frees the image's data, which isn't Xlib's to free
+            (*self.ximage).data = ptr::null_mut();
+            xlib::XDestroyImage(self.ximage);
+        }
+    }
+}
+
+/// Whether or not images can be shared with the X server. MIT-SHM only works when the server is on
+/// the same machine, which is the case when the display name has no host name in it.
+fn shm_available(display: *mut xlib::Display) -> bool {
+    unsafe {
+        let name = CStr::from_ptr(xlib::XDisplayString(display)).to_string_lossy();
+        let local = name.starts_with(':') || name.starts_with("unix:");
+
+        local && XShmQueryExtension(display) != 0
+    }
+}
+
+/// Get how many bits each pixel takes up in images with the given depth
+fn bits_per_pixel(display: *mut xlib::Display, depth: c_int) -> Option<c_int> {
+    unsafe {
+        let mut count = 0;
+        let formats = xlib::XListPixmapFormats(display, &mut count);
+        if formats.is_null() {
+            return None;
+        }
+
+        let bits = slice::from_raw_parts(formats, count as usize).iter()
+            .find(|f| f.depth == depth)
+            .map(|f| f.bits_per_pixel);
+        xlib::XFree(formats as *mut _);
+        bits
+    }
+}
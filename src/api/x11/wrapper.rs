@@ -0,0 +1,1489 @@
+use x11::xlib;
+use x11::xinput2;
+use x11::xcursor;
+use libc;
+
+use std::ptr;
+use std::mem;
+use std::slice;
+use std::ops::Drop;
+use std::ffi::CString;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Once, ONCE_INIT};
+use std::os::raw::{c_int, c_long, c_uint, c_ulong, c_uchar, c_char, c_void};
+
+use {CursorType, CustomCursor, GrabMode, Rect};
+use api::ico;
+use api::xcursor::premultiply;
+use api::backend::EventSink;
+use api::evdev;
+use config::{WindowConfig, InitialState};
+use error::{TubResult, TubError};
+use event::{Event, ResizeType, ModifiersState, ScanCode, ScrollDelta, TouchPhase};
+use super::keysym;
+use super::ime::{self, ImeState};
+use super::xinput::{self, ScrollAxis, RangeAxis, Pen};
+
+/// The size that windows are created with when `WindowConfig::size` is `None`. Win32 lets the
+/// OS pick the size, but X has no equivalent so we have to choose one ourselves.
+const DEFAULT_SIZE: (u32, u32) = (800, 600);
+
+/// The maximum number of milliseconds between two clicks for them to count as a double click.
+const DOUBLE_CLICK_TIME: xlib::Time = 500;
+
+/// The `initial_state` WM hint for iconified windows, which the x11 crate doesn't include
+const ICONIC_STATE: c_int = 3;
+
+/// The events that every window listens for
+const EVENT_MASK: c_long = xlib::KeyPressMask | xlib::KeyReleaseMask |
+                           xlib::ButtonPressMask | xlib::ButtonReleaseMask |
+                           xlib::PointerMotionMask | xlib::EnterWindowMask |
+                           xlib::LeaveWindowMask | xlib::StructureNotifyMask |
+                           xlib::PropertyChangeMask | xlib::FocusChangeMask |
+                           xlib::ExposureMask;
+
+/// A connection to the X server. Every top-level window opens its own connection, which is then
+/// shared with the window's event thread and any owned or child windows.
+pub struct XConnection {
+    pub display: *mut xlib::Display,
+    pub screen: c_int,
+    pub root: xlib::Window,
+    pub wm_protocols: xlib::Atom,
+    pub wm_delete_window: xlib::Atom,
+    /// The input method that typed text goes through, or null if none could be opened
+    pub im: xlib::XIM,
+    /// The major opcode of the XInput extension, which smooth scrolling comes from. This is `None`
+    /// if the server doesn't support XInput 2.1, in which case scrolling comes from the core
+    /// scroll wheel buttons.
+    pub xinput: Option<c_int>,
+    /// Whether the server supports XInput 2.2, which touch events come from
+    pub xinput_touch: bool
+}
+
+unsafe impl Send for XConnection {}
+unsafe impl Sync for XConnection {}
+
+impl XConnection {
+    pub fn new() -> TubResult<XConnection> {
+        init_threads();
+
+        unsafe {
+            let display = xlib::XOpenDisplay(ptr::null());
+            if display == ptr::null_mut() {
+                return Err(TubError::OsError("Could not open a connection to the X server".to_owned()));
+            }
+
+            // By default, X sends a release event before every repeated press event. Turning on detectable
+            // autorepeat suppresses those releases, which lets us tell held keys apart from new presses.
+            xlib::XkbSetDetectableAutoRepeat(display, xlib::True, ptr::null_mut());
+
+            let screen = xlib::XDefaultScreen(display);
+
+            let xinput = xinput::query_opcode(display);
+            let mut conn = XConnection {
+                display: display,
+                screen: screen,
+                root: xlib::XRootWindow(display, screen),
+                wm_protocols: 0,
+                wm_delete_window: 0,
+                im: open_input_method(display),
+                xinput: xinput.map(|x| x.0),
+                xinput_touch: xinput.map_or(false, |x| x.1)
+            };
+
+            if conn.xinput.is_some() {
+                xinput::select_raw_motion(display, conn.root);
+            }
+
+            conn.wm_protocols = conn.atom("WM_PROTOCOLS");
+            conn.wm_delete_window = conn.atom("WM_DELETE_WINDOW");
+
+            Ok(conn)
+        }
+    }
+
+    pub fn atom(&self, name: &str) -> xlib::Atom {
+        let name = CString::new(name).unwrap();
+        unsafe{ xlib::XInternAtom(self.display, name.as_ptr(), xlib::False) }
+    }
+
+    #[inline]
+    pub fn flush(&self) {
+        unsafe{ xlib::XFlush(self.display) };
+    }
+}
+
+impl Drop for XConnection {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.im.is_null() {
+                xlib::XCloseIM(self.im);
+            }
+            xlib::XCloseDisplay(self.display);
+        }
+    }
+}
+
+/// Open the input method that the user has configured, which handles dead keys, compose sequences
+/// and the like. If that fails, fall back to Xlib's built-in input method, which still knows about
+/// compose sequences.
+unsafe fn open_input_method(display: *mut xlib::Display) -> xlib::XIM {
+    for modifiers in &[&b"\0"[..], &b"@im=none\0"[..]] {
+        xlib::XSetLocaleModifiers(modifiers.as_ptr() as *const c_char);
+
+        let im = xlib::XOpenIM(display, ptr::null_mut(), ptr::null_mut(), ptr::null_mut());
+        if !im.is_null() {
+            return im;
+        }
+    }
+
+    ptr::null_mut()
+}
+
+static INIT_THREADS: Once = ONCE_INIT;
+
+/// Xlib has to be told that it's going to be used from multiple threads before any connections are
+/// opened, which is done here along with the rest of the process-wide setup.
+fn init_threads() {
+    INIT_THREADS.call_once(|| {
+        unsafe {
+            xlib::XInitThreads();
+            xlib::XSetErrorHandler(Some(error_handler));
+
+            // Input methods and compose sequences depend on the locale's character set, and Rust
+            // programs start out in the "C" locale.
+            libc::setlocale(libc::LC_CTYPE, b"\0".as_ptr() as *const c_char);
+        }
+    });
+}
+
+/// The default Xlib error handler kills the process on any error, including harmless ones like trying
+/// to destroy a window that the user has already closed. This ignores them instead.
+unsafe extern "C" fn error_handler(_: *mut xlib::Display, _: *mut xlib::XErrorEvent) -> c_int {
+    0
+}
+
+#[derive(Clone)]
+pub struct WindowWrapper( pub xlib::Window, pub Arc<XConnection> );
+
+impl WindowWrapper {
+    pub fn new(conn: &Arc<XConnection>, config: &WindowConfig, visual: Option<&xlib::XVisualInfo>, owner: ParentType) -> TubResult<WindowWrapper> {
+        unsafe {
+            let display = conn.display;
+
+            let parent = match owner {
+                ParentType::Child(p) => p,
+                _                    => conn.root
+            };
+
+            let size = match config.size {
+                Some(s) => (s.0 as u32, s.1 as u32),
+                None    => DEFAULT_SIZE
+            };
+
+            let mut attributes: xlib::XSetWindowAttributes = mem::zeroed();
+            attributes.event_mask = EVENT_MASK;
+            let mut value_mask = xlib::CWEventMask;
+
+            // Windows using a visual other than their parent's need their own colormap, and X
+            // raises a BadMatch error if the border pixel isn't also set.
+            let (depth, visual) = match visual {
+                Some(v) => {
+                    attributes.colormap = xlib::XCreateColormap(display, conn.root, v.visual, xlib::AllocNone);
+                    attributes.border_pixel = 0;
+                    value_mask |= xlib::CWColormap | xlib::CWBorderPixel;
+
+                    (v.depth, v.visual)
+                }
+
+                None => (xlib::CopyFromParent as c_int, ptr::null_mut())
+            };
+
+            let window = xlib::XCreateWindow(
+                display,
+                parent,
+                0,
+                0,
+                size.0,
+                size.1,
+                0,
+                depth,
+                xlib::InputOutput as c_uint,
+                visual,
+                value_mask,
+                &mut attributes
+            );
+
+            if window == 0 {
+                return Err(TubError::OsError("Could not create X window".to_owned()));
+            }
+
+            if conn.xinput.is_some() {
+                xinput::select_events(display, window, conn.xinput_touch);
+            }
+
+            let wrapper = WindowWrapper( window, conn.clone() );
+            wrapper.set_title(&config.name);
+
+            if let ParentType::Child(_) = owner {
+                // Child windows are drawn inside of their parent, so the window manager doesn't see them
+                // and none of the hints below apply.
+                return Ok(wrapper);
+            }
+
+            // Ask the window manager to send us a message when the close button is clicked instead of
+            // having it kill the connection.
+            let mut protocols = [conn.wm_delete_window];
+            xlib::XSetWMProtocols(display, window, protocols.as_mut_ptr(), 1);
+
+            if let ParentType::Owned(o) = owner {
+                xlib::XSetTransientForHint(display, window, o);
+            }
+
+            if !config.resizable {
+                let hints = xlib::XAllocSizeHints();
+                (*hints).flags = xlib::PMinSize | xlib::PMaxSize;
+                (*hints).min_width = size.0 as c_int;
+                (*hints).max_width = size.0 as c_int;
+                (*hints).min_height = size.1 as c_int;
+                (*hints).max_height = size.1 as c_int;
+
+                xlib::XSetWMNormalHints(display, window, hints);
+                xlib::XFree(hints as *mut _);
+            }
+
+            // Window decorations and functions are set through the Motif hints, which most window managers
+            // still respect. The hints are a struct of five longs: flags, functions, decorations, input mode
+            // and status.
+            {
+                const MWM_HINTS_FUNCTIONS: c_ulong = 1 << 0;
+                const MWM_HINTS_DECORATIONS: c_ulong = 1 << 1;
+
+                const MWM_FUNC_RESIZE: c_ulong = 1 << 1;
+                const MWM_FUNC_MOVE: c_ulong = 1 << 2;
+                const MWM_FUNC_MINIMIZE: c_ulong = 1 << 3;
+                const MWM_FUNC_MAXIMIZE: c_ulong = 1 << 4;
+                const MWM_FUNC_CLOSE: c_ulong = 1 << 5;
+
+                let mut functions = MWM_FUNC_MOVE | MWM_FUNC_CLOSE;
+                if config.resizable {
+                    functions |= MWM_FUNC_RESIZE;
+
+                    if config.maximizable {
+                        functions |= MWM_FUNC_MAXIMIZE;
+                    }
+                }
+
+                if config.minimizable {
+                    functions |= MWM_FUNC_MINIMIZE;
+                }
+
+                let decorations = match config.borderless {
+                    true  => 0,
+                    false => 1
+                };
+
+                let hints: [c_ulong; 5] = [MWM_HINTS_FUNCTIONS | MWM_HINTS_DECORATIONS, functions, decorations, 0, 0];
+                let motif_hints = conn.atom("_MOTIF_WM_HINTS");
+                wrapper.set_property(motif_hints, motif_hints, 32, &hints);
+            }
+
+            // The initial window state. The window manager reads this when the window is first mapped.
+            {
+                let mut states = Vec::new();
+
+                if config.topmost {
+                    states.push(conn.atom("_NET_WM_STATE_ABOVE"));
+                }
+
+                if config.tool_window {
+                    states.push(conn.atom("_NET_WM_STATE_SKIP_TASKBAR"));
+
+                    let utility = [conn.atom("_NET_WM_WINDOW_TYPE_UTILITY")];
+                    wrapper.set_property(conn.atom("_NET_WM_WINDOW_TYPE"), xlib::XA_ATOM, 32, &utility);
+                }
+                else if let ParentType::Owned(_) = owner {
+                    states.push(conn.atom("_NET_WM_STATE_SKIP_TASKBAR"));
+                }
+
+                match config.initial_state {
+                    InitialState::Windowed  => (),
+                    InitialState::Maximized => {
+                        states.push(conn.atom("_NET_WM_STATE_MAXIMIZED_VERT"));
+                        states.push(conn.atom("_NET_WM_STATE_MAXIMIZED_HORZ"));
+                    }
+                    InitialState::Minimized => {
+                        let hints = xlib::XAllocWMHints();
+                        (*hints).flags = xlib::StateHint;
+                        (*hints).initial_state = ICONIC_STATE;
+
+                        xlib::XSetWMHints(display, window, hints);
+                        xlib::XFree(hints as *mut _);
+                    }
+                }
+
+                if states.len() > 0 {
+                    wrapper.set_property(conn.atom("_NET_WM_STATE"), xlib::XA_ATOM, 32, &states);
+                }
+            }
+
+            if let Some(ref p) = config.icon {
+                let images = match ico::load_ico(p) {
+                    Some(i) => i,
+                    None    => return Err(TubError::IconLoadError(32))
+                };
+
+                if images.len() == 0 {
+                    return Err(TubError::IconLoadError(32));
+                }
+
+                // _NET_WM_ICON is a list of images, each of which is the width and height followed by the
+                // ARGB pixels.
+                let mut icon_data: Vec<c_ulong> = Vec::new();
+                for image in images {
+                    icon_data.push(image.width as c_ulong);
+                    icon_data.push(image.height as c_ulong);
+                    icon_data.extend(image.pixels.iter().map(|p| *p as c_ulong));
+                }
+
+                wrapper.set_property(conn.atom("_NET_WM_ICON"), xlib::XA_CARDINAL, 32, &icon_data);
+            }
+
+            Ok(wrapper)
+        }
+    }
+
+    #[inline]
+    pub fn set_title(&self, title: &str) {
+        unsafe {
+            let c_title = CString::new(title.replace('\0', "")).unwrap();
+            xlib::XStoreName(self.1.display, self.0, c_title.as_ptr() as *mut c_char);
+
+            // XStoreName only handles Latin-1 text, so the UTF-8 title also gets set through the EWMH property.
+            self.set_property(self.1.atom("_NET_WM_NAME"), self.1.atom("UTF8_STRING"), 8, title.as_bytes());
+            self.1.flush();
+        }
+    }
+
+    #[inline]
+    pub fn show(&self) {
+        unsafe {
+            xlib::XMapRaised(self.1.display, self.0);
+            self.1.flush();
+        }
+    }
+
+    #[inline]
+    pub fn hide(&self) {
+        unsafe {
+            xlib::XUnmapWindow(self.1.display, self.0);
+            self.1.flush();
+        }
+    }
+
+    #[inline]
+    pub fn focus(&self) {
+        unsafe {
+            xlib::XSetInputFocus(self.1.display, self.0, xlib::RevertToParent, xlib::CurrentTime);
+            self.1.flush();
+        }
+    }
+
+    #[inline]
+    pub fn get_inner_pos(&self) -> Option<(i32, i32)> {
+        unsafe {
+            let (mut x, mut y) = (0, 0);
+            let mut child = 0;
+
+            match xlib::XTranslateCoordinates(self.1.display, self.0, self.1.root, 0, 0, &mut x, &mut y, &mut child) {
+                0 => None,
+                _ => Some((x as i32, y as i32))
+            }
+        }
+    }
+
+    #[inline]
+    pub fn get_outer_pos(&self) -> Option<(i32, i32)> {
+        let (left, _, top, _) = self.get_frame_extents();
+
+        self.get_inner_pos().map(|(x, y)| (x - left as i32, y - top as i32))
+    }
+
+    #[inline]
+    pub fn get_inner_size(&self) -> Option<(u32, u32)> {
+        unsafe {
+            let mut attributes = mem::uninitialized();
+
+            match xlib::XGetWindowAttributes(self.1.display, self.0, &mut attributes) {
+                0 => None,
+                _ => Some((attributes.width as u32, attributes.height as u32))
+            }
+        }
+    }
+
+    #[inline]
+    pub fn get_outer_size(&self) -> Option<(u32, u32)> {
+        let (left, right, top, bottom) = self.get_frame_extents();
+
+        self.get_inner_size().map(|(w, h)| (w + left + right, h + top + bottom))
+    }
+
+    #[inline]
+    pub fn set_pos(&self, x: i32, y: i32) -> Option<()> {
+        unsafe {
+            xlib::XMoveWindow(self.1.display, self.0, x as c_int, y as c_int);
+            self.1.flush();
+        }
+
+        Some(())
+    }
+
+    #[inline]
+    pub fn set_inner_size(&self, x: u32, y: u32) -> Option<()> {
+        unsafe {
+            xlib::XResizeWindow(self.1.display, self.0, x as c_uint, y as c_uint);
+            self.1.flush();
+        }
+
+        Some(())
+    }
+
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        unsafe {
+            let mut focused = 0;
+            let mut revert_to = 0;
+
+            xlib::XGetInputFocus(self.1.display, &mut focused, &mut revert_to);
+            focused == self.0
+        }
+    }
+
+    #[inline]
+    pub fn set_cursor(&self, cursor_type: CursorType) {
+        use CursorType::*;
+
+        // Cursor shapes from X11/cursorfont.h
+        let shape = match cursor_type {
+            AppStarting     => 150, // XC_watch
+            Arrow           => 68,  // XC_left_ptr
+            Crosshair       => 34,  // XC_crosshair
+            Hand            => 60,  // XC_hand2
+            Help            => 92,  // XC_question_arrow
+            Text            => 152, // XC_xterm
+            Prohibited      => 0,   // XC_X_cursor
+            ResizeAll       => 52,  // XC_fleur
+            ResizeNESW      => 12,  // XC_bottom_left_corner
+            ResizeNWSE      => 14,  // XC_bottom_right_corner
+            ResizeVertical  => 116, // XC_sb_v_double_arrow
+            ResizeHoriz     => 108, // XC_sb_h_double_arrow
+            UpArrow         => 114, // XC_sb_up_arrow
+            Wait            => 150, // XC_watch
+            Invisible       => {
+                self.set_invisible_cursor();
+                return;
+            }
+        };
+
+        unsafe {
+            let cursor = xlib::XCreateFontCursor(self.1.display, shape);
+            xlib::XDefineCursor(self.1.display, self.0, cursor);
+            // The server keeps the cursor alive as long as a window is using it
+            xlib::XFreeCursor(self.1.display, cursor);
+            self.1.flush();
+        }
+    }
+
+    fn set_invisible_cursor(&self) {
+        unsafe {
+            let cursor = invisible_cursor(self.1.display, self.0);
+            xlib::XDefineCursor(self.1.display, self.0, cursor);
+
+            xlib::XFreeCursor(self.1.display, cursor);
+            self.1.flush();
+        }
+    }
+
+    /// Move the cursor to a position relative to this window's client area
+    pub fn warp_cursor(&self, x: i32, y: i32) {
+        unsafe {
+            xlib::XWarpPointer(self.1.display, 0, self.0, 0, 0, 0, 0, x as c_int, y as c_int);
+            self.1.flush();
+        }
+    }
+
+    /// Get the cursor position relative to the root window
+    pub fn get_cursor_pos(&self) -> Option<(i32, i32)> {
+        unsafe {
+            let (mut root, mut child) = (0, 0);
+            let (mut root_x, mut root_y, mut win_x, mut win_y) = (0, 0, 0, 0);
+            let mut mask = 0;
+
+            match xlib::XQueryPointer(self.1.display, self.0, &mut root, &mut child,
+                                      &mut root_x, &mut root_y, &mut win_x, &mut win_y, &mut mask) {
+                0 => None,
+                _ => Some((root_x as i32, root_y as i32))
+            }
+        }
+    }
+
+    /// Get the size of the window manager's frame around the window, as `(left, right, top, bottom)`.
+    /// If there is no window manager or it doesn't support `_NET_FRAME_EXTENTS`, this is all zeroes.
+    fn get_frame_extents(&self) -> (u32, u32, u32, u32) {
+        let extents = get_property::<c_ulong>(&self.1, self.0, self.1.atom("_NET_FRAME_EXTENTS"), xlib::XA_CARDINAL);
+
+        match extents {
+            Some(ref e) if e.len() == 4 => (e[0] as u32, e[1] as u32, e[2] as u32, e[3] as u32),
+            _ => (0, 0, 0, 0)
+        }
+    }
+
+    /// Replace a property on the window. `format` is the size of each element in bits, and must be 8,
+    /// 16 or 32. Note that Xlib expects 32-bit data to be passed as an array of longs.
+    fn set_property<T>(&self, property: xlib::Atom, ty: xlib::Atom, format: c_int, data: &[T]) {
+        unsafe {
+            xlib::XChangeProperty(
+                self.1.display,
+                self.0,
+                property,
+                ty,
+                format,
+                xlib::PropModeReplace,
+                data.as_ptr() as *const c_uchar,
+                data.len() as c_int
+            );
+        }
+    }
+
+    pub fn kill(&self) {
+        unsafe {
+            xlib::XDestroyWindow(self.1.display, self.0);
+            self.1.flush();
+        }
+    }
+}
+
+/// Read a property off of a window. Like with `set_property`, 32-bit properties are returned as longs.
+fn get_property<T: Copy>(conn: &XConnection, window: xlib::Window, property: xlib::Atom, ty: xlib::Atom) -> Option<Vec<T>> {
+    unsafe {
+        let mut actual_type = 0;
+        let mut actual_format = 0;
+        let mut item_count = 0;
+        let mut bytes_after = 0;
+        let mut data = ptr::null_mut();
+
+        let result = xlib::XGetWindowProperty(
+            conn.display,
+            window,
+            property,
+            0,
+            c_long::max_value(),
+            xlib::False,
+            ty,
+            &mut actual_type,
+            &mut actual_format,
+            &mut item_count,
+            &mut bytes_after,
+            &mut data
+        );
+
+        if result != 0 || data == ptr::null_mut() {
+            return None;
+        }
+
+        let vec = match actual_type == ty {
+            true  => slice::from_raw_parts(data as *const T, item_count as usize).to_vec(),
+            false => Vec::new()
+        };
+        xlib::XFree(data as *mut _);
+
+        Some(vec)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum ParentType {
+    Owned(xlib::Window),
+    Child(xlib::Window),
+    Top
+}
+
+/// The keycodes of all of the keys that are currently held down, used to tell held keys apart from
+/// newly pressed ones.
+#[derive(Default)]
+struct KeysDown(Vec<c_uint>);
+
+impl KeysDown {
+    fn press(&mut self, keycode: c_uint) -> ::event::PressState {
+        use event::PressState;
+
+        match self.0.contains(&keycode) {
+            false => {
+                self.0.push(keycode);
+                PressState::Pressed
+            }
+            true  => PressState::Held
+        }
+    }
+
+    #[inline]
+    fn release(&mut self, keycode: c_uint) {
+        self.0.retain(|k| *k != keycode);
+    }
+
+    /// Forget every key. Keys that are released while none of the connection's windows have focus
+    /// never send a release event, so this has to be done whenever focus leaves.
+    #[inline]
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+/// State shared between a connection's event thread and the windows using that connection. Unlike
+/// on win32, owned and child windows are created on the calling thread and registered here afterwards,
+/// so this has to live behind a mutex instead of in a thread local.
+pub struct CallbackData {
+    win_vec: Vec<WindowDataIntern>,
+    keys_down: KeysDown,
+    /// The scroll axes of each XInput device that has sent a motion event, by device ID
+    scroll_devices: HashMap<c_int, Vec<ScrollAxis>>,
+    /// The pressure axis of each XInput device that has sent a touch event, by device ID
+    touch_devices: HashMap<c_int, Option<RangeAxis>>,
+    /// Every XInput device that has sent a pointer event, by device ID, along with its state if
+    /// it's the pen of a tablet
+    pen_devices: HashMap<c_int, Option<Pen>>,
+    /// Whether each XInput device that has sent a raw motion event moves the pointer relatively,
+    /// by device ID
+    relative_devices: HashMap<c_int, bool>
+}
+
+impl CallbackData {
+    #[inline]
+    pub fn new(window: xlib::Window, sink: EventSink) -> CallbackData {
+        let mut data_vector = Vec::with_capacity(4);
+        data_vector.push(WindowDataIntern::new(window, sink));
+
+        CallbackData {
+            win_vec: data_vector,
+            keys_down: KeysDown::default(),
+            scroll_devices: HashMap::new(),
+            touch_devices: HashMap::new(),
+            pen_devices: HashMap::new(),
+            relative_devices: HashMap::new()
+        }
+    }
+
+    #[inline]
+    pub fn add_window(&mut self, window: xlib::Window, sink: EventSink) {
+        self.win_vec.push(WindowDataIntern::new(window, sink));
+    }
+
+    /// Set whether or not the window takes user input. X has no concept of a disabled window, so
+    /// input events for disabled windows are dropped here instead.
+    #[inline]
+    pub fn set_enabled(&mut self, window: xlib::Window, enabled: bool) {
+        if let Some(w) = self.get_window(window) {
+            w.enabled = enabled;
+        }
+    }
+
+    /// Move the input method's candidate window next to a point in the window
+    pub fn set_ime_position(&mut self, conn: &XConnection, window: xlib::Window, x: i32, y: i32) {
+        if let Some(w) = self.get_window(window) {
+            unsafe {
+                let ic = w.input_context(conn);
+                if !ic.is_null() {
+                    ime::set_spot(ic, x, y);
+                    conn.flush();
+                }
+            }
+        }
+    }
+
+    /// Set how the window grabs the cursor, and the part of the window that a confined cursor is
+    /// kept in if it isn't the whole window. The grab only takes effect while the window has focus.
+    pub fn set_cursor_grab(&mut self, conn: &XConnection, window: xlib::Window, mode: GrabMode, rect: Option<Rect>) {
+        if let Some(w) = self.get_window(window) {
+            w.grab = mode;
+            unsafe {
+                w.set_confine_rect(conn, rect);
+                if w.focused {
+                    w.update_grab(conn);
+                }
+            }
+        }
+    }
+
+    /// Show a custom cursor over the window. The X cursor is made the first time that the window
+    /// uses the cursor and kept until the window is destroyed. If the X cursor can't be made, the
+    /// window shows the default cursor instead, and making it is tried again the next time.
+    pub fn set_custom_cursor(&mut self, conn: &XConnection, window: xlib::Window, cursor: &CustomCursor) {
+        if let Some(w) = self.get_window(window) {
+            unsafe {
+                let x_cursor = match w.cursors.get(&cursor.id()) {
+                    Some(&x_cursor) => x_cursor,
+                    None            => custom_cursor(conn.display, cursor)
+                };
+
+                if x_cursor != 0 {
+                    w.cursors.insert(cursor.id(), x_cursor);
+                    xlib::XDefineCursor(conn.display, window, x_cursor);
+                } else {
+                    xlib::XUndefineCursor(conn.display, window);
+                }
+                conn.flush();
+            }
+        }
+    }
+
+    fn get_window(&mut self, window: xlib::Window) -> Option<&mut WindowDataIntern> {
+        self.win_vec.iter_mut().find(|w| w.window == window)
+    }
+}
+
+/// X has no invisible cursor, so this makes one out of an empty bitmap. The cursor has to be freed
+/// once it's been used.
+unsafe fn invisible_cursor(display: *mut xlib::Display, window: xlib::Window) -> xlib::Cursor {
+    let data = 0 as c_char;
+    let pixmap = xlib::XCreateBitmapFromData(display, window, &data, 1, 1);
+    let mut color = mem::zeroed();
+
+    let cursor = xlib::XCreatePixmapCursor(display, pixmap, pixmap, &mut color, &mut color, 0, 0);
+    xlib::XFreePixmap(display, pixmap);
+    cursor
+}
+
+/// Make an X cursor out of a custom cursor's pixels. Returns 0 if the cursor couldn't be made.
+unsafe fn custom_cursor(display: *mut xlib::Display, cursor: &CustomCursor) -> xlib::Cursor {
+    let image = xcursor::XcursorImageCreate(cursor.width() as c_int, cursor.height() as c_int);
+    if image.is_null() {
+        return 0;
+    }
+
+    (*image).xhot = cursor.hotspot().0;
+    (*image).yhot = cursor.hotspot().1;
+    let pixels = slice::from_raw_parts_mut((*image).pixels, cursor.pixels().len());
+    for (pixel, &argb) in pixels.iter_mut().zip(cursor.pixels()) {
+        *pixel = premultiply(argb);
+    }
+
+    // Servers without the RENDER extension can't show ARGB cursors, so libXcursor falls back to a
+    // two-color cursor made from the image
+    let x_cursor = xcursor::XcursorImageLoadCursor(display, image);
+    xcursor::XcursorImageDestroy(image);
+    x_cursor
+}
+
+/// Struct that contains information about the window wrapper to the event thread.
+struct WindowDataIntern {
+    window: xlib::Window,
+    sink: EventSink,
+    enabled: bool,
+    /// The last known position and size of the window, used to figure out whether a ConfigureNotify
+    /// event was a move, a resize or both.
+    pos: (i32, i32),
+    size: (u32, u32),
+    state: ResizeType,
+    /// The button and time of the last mouse press, used to detect double clicks
+    last_click: Option<(c_uint, xlib::Time)>,
+    /// The window's input context, which is created the first time it's needed
+    ic: xlib::XIC,
+    /// The composition that the input context reports through its callbacks
+    ime: Box<ImeState>,
+    focused: bool,
+    grab: GrabMode,
+    /// Whether the server has actually given the window the grab. Grabbing fails while another
+    /// client has the pointer grabbed, like the window manager while a window is being clicked on.
+    grabbed: bool,
+    /// Where the cursor was when it was locked, which it gets moved back to whenever it moves
+    lock_pos: (i32, i32),
+    /// An input-only child window covering the rectangle that the cursor is confined to, or 0 if
+    /// the cursor is confined to the whole window. The grab's `confine_to` has to be a window.
+    confine_window: xlib::Window,
+    /// The X cursors made for the custom cursors that the window has used, by cursor ID
+    cursors: HashMap<usize, xlib::Cursor>,
+    /// The last position of every touch on the window that hasn't ended yet, by touch ID
+    touches: HashMap<u64, (i32, i32)>
+}
+
+// The input context is only ever used while the callback data is locked, and Xlib is initialized
+// for threads
+unsafe impl Send for WindowDataIntern {}
+
+impl WindowDataIntern {
+    #[inline]
+    fn new(window: xlib::Window, sink: EventSink) -> WindowDataIntern {
+        WindowDataIntern {
+            window: window,
+            ime: ImeState::new(sink.clone()),
+            sink: sink,
+            enabled: true,
+            pos: (i32::min_value(), i32::min_value()),
+            size: (0, 0),
+            state: ResizeType::Changed,
+            last_click: None,
+            ic: ptr::null_mut(),
+            focused: false,
+            grab: GrabMode::None,
+            grabbed: false,
+            lock_pos: (0, 0),
+            confine_window: 0,
+            cursors: HashMap::new(),
+            touches: HashMap::new()
+        }
+    }
+
+    /// Cancel every touch on the window, for when the window stops getting the touches' events
+    fn cancel_touches(&mut self) {
+        let touches: Vec<_> = self.touches.drain().collect();
+        for (id, (x, y)) in touches {
+            send_event(self, Event::Touch{ id: id, phase: TouchPhase::Cancelled, x: x, y: y, pressure: None }, true);
+        }
+    }
+
+    /// Create, move or destroy the child window that the cursor is confined to
+    unsafe fn set_confine_rect(&mut self, conn: &XConnection, rect: Option<Rect>) {
+        match rect {
+            Some(rect) => {
+                let (width, height) = (rect.width.max(1), rect.height.max(1));
+                if self.confine_window == 0 {
+                    // The child doesn't select any events, so the pointer's events go on to the
+                    // window as if it wasn't there
+                    let mut attributes: xlib::XSetWindowAttributes = mem::zeroed();
+                    self.confine_window = xlib::XCreateWindow(conn.display, self.window, rect.x, rect.y, width, height, 0, 0,
+                                                              xlib::InputOnly as c_uint, ptr::null_mut(), 0, &mut attributes);
+                    xlib::XMapWindow(conn.display, self.confine_window);
+                } else {
+                    xlib::XMoveResizeWindow(conn.display, self.confine_window, rect.x, rect.y, width, height);
+                }
+            }
+
+            None if self.confine_window != 0 => {
+                xlib::XDestroyWindow(conn.display, self.confine_window);
+                self.confine_window = 0;
+            }
+
+            None => ()
+        }
+    }
+
+    /// Grab or release the cursor to match the window's grab mode. X can only confine the cursor, so
+    /// locking it hides it and moves it back every time it moves.
+    unsafe fn update_grab(&mut self, conn: &XConnection) {
+        if self.grab == GrabMode::None || !self.focused {
+            if self.grabbed {
+                xlib::XUngrabPointer(conn.display, xlib::CurrentTime);
+                self.grabbed = false;
+            }
+            conn.flush();
+            return;
+        }
+
+        let cursor = match self.grab {
+            GrabMode::Locked => invisible_cursor(conn.display, self.window),
+            _                => 0
+        };
+
+        // With owner_events set, the window's events are delivered as usual while it's grabbed,
+        // including XInput's events
+        let mask = (xlib::ButtonPressMask | xlib::ButtonReleaseMask | xlib::PointerMotionMask) as c_uint;
+        let confine_to = match self.confine_window {
+            0      => self.window,
+            window => window
+        };
+        self.grabbed = xlib::XGrabPointer(conn.display, self.window, xlib::True, mask, xlib::GrabModeAsync,
+                                          xlib::GrabModeAsync, confine_to, cursor, xlib::CurrentTime) == xlib::GrabSuccess;
+
+        if cursor != 0 {
+            xlib::XFreeCursor(conn.display, cursor);
+        }
+
+        if self.grab == GrabMode::Locked {
+            let (mut root, mut child) = (0, 0);
+            let (mut root_x, mut root_y, mut x, mut y) = (0, 0, 0, 0);
+            let mut mask = 0;
+            xlib::XQueryPointer(conn.display, self.window, &mut root, &mut child, &mut root_x, &mut root_y, &mut x, &mut y, &mut mask);
+            self.lock_pos = (x, y);
+        }
+
+        conn.flush();
+    }
+
+    /// Handle the pointer moving over the window. Returns false if the cursor is locked, in which
+    /// case it gets moved back and the motion shouldn't be reported.
+    unsafe fn pointer_moved(&mut self, conn: &XConnection, pos: (i32, i32)) -> bool {
+        // Grabbing can fail while another client has the pointer, so keep trying until it works
+        if self.focused && self.grab != GrabMode::None && !self.grabbed {
+            self.update_grab(conn);
+        }
+
+        if self.grabbed && self.grab == GrabMode::Locked {
+            if pos != self.lock_pos {
+                xlib::XWarpPointer(conn.display, 0, self.window, 0, 0, 0, 0, self.lock_pos.0, self.lock_pos.1);
+                conn.flush();
+            }
+            return false;
+        }
+
+        true
+    }
+
+    /// Get the window's input context, creating it if it doesn't exist yet. This is null if the
+    /// connection has no input method or the context couldn't be created.
+    unsafe fn input_context(&mut self, conn: &XConnection) -> xlib::XIC {
+        if self.ic.is_null() && !conn.im.is_null() {
+            self.ic = ime::create_ic(conn.im, self.window, &mut *self.ime);
+
+            // Some input methods need events that the window doesn't ask for on its own
+            if !self.ic.is_null() {
+                let mut filter_events: c_ulong = 0;
+                xlib::XGetICValues(self.ic, xlib::XNFilterEvents_0.as_ptr(), &mut filter_events, ptr::null_mut::<c_void>());
+                xlib::XSelectInput(conn.display, self.window, EVENT_MASK | filter_events as c_long);
+            }
+        }
+
+        self.ic
+    }
+}
+
+
+
+/// The body of a connection's event thread. This runs until the top-level window is destroyed.
+pub fn event_loop(conn: Arc<XConnection>, data: Arc<Mutex<CallbackData>>, top_window: xlib::Window) {
+    unsafe {
+        let mut xev = mem::uninitialized();
+
+        loop {
+            xlib::XNextEvent(conn.display, &mut xev);
+
+            if !callback(&conn, &data, &mut xev, top_window) {
+                break;
+            }
+        }
+    }
+}
+
+/// Translate a single X event into a tub event and send it to the corresponding window. Returns false
+/// once the top-level window has been destroyed.
+unsafe fn callback(conn: &XConnection, data: &Mutex<CallbackData>, xev: &mut xlib::XEvent, top_window: xlib::Window) -> bool {
+    // The input method gets to see every event first. It swallows its own messages along with the
+    // key presses that start dead key and compose sequences.
+    if xlib::XFilterEvent(xev, 0) == xlib::True {
+        return true;
+    }
+
+    let mut data = data.lock().unwrap();
+    let data = &mut *data;
+
+    // XInput's events don't have a window in the same place as the core events
+    if xev.get_type() == xlib::GenericEvent {
+        xinput_callback(conn, data, xev);
+        return true;
+    }
+
+    let window = {
+        let xany: &xlib::XAnyEvent = xev.as_ref();
+        xany.window
+    };
+    let index = match data.win_vec.iter().position(|w| w.window == window) {
+        Some(i) => i,
+        None    => return true
+    };
+
+    match xev.get_type() {
+        xlib::KeyPress      => {
+            use event::Event::KeyInput;
+
+            let xkey: &mut xlib::XKeyEvent = xev.as_mut();
+            let press_state = data.keys_down.press(xkey.keycode);
+
+            let key = keysym::key_from_event(xkey);
+            send_event(&data.win_vec[index], KeyInput(press_state, key, scancode_from_x(xkey.keycode), modifiers_from_state(xkey.state)), true);
+
+            let win_data = &mut data.win_vec[index];
+            let text = lookup_string(win_data.input_context(conn), xkey);
+            if !text.is_empty() {
+                win_data.ime.received_text(&text);
+            }
+            for c in text.chars() {
+                send_event(win_data, Event::ReceivedCharacter(c), true);
+            }
+        }
+
+        xlib::KeyRelease    => {
+            use event::Event::KeyInput;
+            use event::PressState;
+
+            let xkey: &mut xlib::XKeyEvent = xev.as_mut();
+            data.keys_down.release(xkey.keycode);
+
+            let key = keysym::key_from_event(xkey);
+            send_event(&data.win_vec[index], KeyInput(PressState::Released, key, scancode_from_x(xkey.keycode), modifiers_from_state(xkey.state)), true);
+        }
+
+        xlib::ButtonPress   => {
+            let xbutton: &xlib::XButtonEvent = xev.as_ref();
+            button_press(&mut data.win_vec[index], xbutton.button, xbutton.time, xbutton.state);
+        }
+
+        xlib::ButtonRelease => {
+            let xbutton: &xlib::XButtonEvent = xev.as_ref();
+            button_release(&data.win_vec[index], xbutton.button, xbutton.state);
+        }
+
+        xlib::MotionNotify  => {
+            let xmotion: &xlib::XMotionEvent = xev.as_ref();
+            let win_data = &mut data.win_vec[index];
+            if win_data.pointer_moved(conn, (xmotion.x as i32, xmotion.y as i32)) {
+                send_event(win_data, Event::MouseMoved(xmotion.x as i32, xmotion.y as i32), true);
+            }
+        }
+
+        xlib::Expose        => {
+            // Each damaged rectangle gets its own event, and `count` is how many more are coming
+            let xexpose: &xlib::XExposeEvent = xev.as_ref();
+            if xexpose.count == 0 {
+                send_event(&data.win_vec[index], Event::RedrawRequested, false);
+            }
+        }
+
+        // Focus events with the NotifyPointer detail are about the window under the pointer, which
+        // only gets the keyboard's events when nothing else has focus. They don't change which
+        // window has focus.
+        xlib::FocusIn       => {
+            let xfocus: &xlib::XFocusChangeEvent = xev.as_ref();
+            if xfocus.detail != xlib::NotifyPointer {
+                let win_data = &mut data.win_vec[index];
+                let ic = win_data.input_context(conn);
+                if !ic.is_null() {
+                    xlib::XSetICFocus(ic);
+                }
+
+                win_data.focused = true;
+                win_data.update_grab(conn);
+                send_event(win_data, Event::Focused(true), false);
+            }
+        }
+
+        xlib::FocusOut      => {
+            data.keys_down.clear();
+
+            let xfocus: &xlib::XFocusChangeEvent = xev.as_ref();
+            if xfocus.detail != xlib::NotifyPointer {
+                let win_data = &mut data.win_vec[index];
+                if !win_data.ic.is_null() {
+                    xlib::XUnsetICFocus(win_data.ic);
+                }
+
+                win_data.focused = false;
+                win_data.update_grab(conn);
+                send_event(win_data, Event::Focused(false), false);
+            }
+        }
+
+        // Moving in or out of a child window like the confine window doesn't leave the window
+        xlib::EnterNotify   => {
+            let xcrossing: &xlib::XCrossingEvent = xev.as_ref();
+            if xcrossing.detail != xlib::NotifyInferior {
+                for axes in data.scroll_devices.values_mut() {
+                    xinput::reset_scroll_axes(axes);
+                }
+                send_event(&data.win_vec[index], Event::MouseEnter, true);
+            }
+        }
+
+        xlib::LeaveNotify   => {
+            let xcrossing: &xlib::XCrossingEvent = xev.as_ref();
+            if xcrossing.detail != xlib::NotifyInferior {
+                // Another client grabbing the pointer takes the touches with it
+                if xcrossing.mode == xlib::NotifyGrab {
+                    data.win_vec[index].cancel_touches();
+                }
+                send_event(&data.win_vec[index], Event::MouseLeave, true);
+            }
+        }
+
+        // Unmapped windows don't get touch events, so there won't be an end to the touches
+        xlib::UnmapNotify   => {
+            let xunmap: &xlib::XUnmapEvent = xev.as_ref();
+            if xunmap.window == window {
+                data.win_vec[index].cancel_touches();
+            }
+        }
+
+        xlib::ConfigureNotify   => {
+            let xconfigure: &xlib::XConfigureEvent = xev.as_ref();
+            let win_data = &mut data.win_vec[index];
+
+            let size = (xconfigure.width as u32, xconfigure.height as u32);
+            if size != win_data.size {
+                win_data.size = size;
+                send_event(win_data, Event::Resized(win_data.state, size.0, size.1), false);
+            }
+
+            // The coordinates in the event are relative to the window manager's frame, so the
+            // actual position has to be queried from the server.
+            let (mut x, mut y) = (0, 0);
+            let mut child = 0;
+            xlib::XTranslateCoordinates(conn.display, window, conn.root, 0, 0, &mut x, &mut y, &mut child);
+
+            let pos = (x as i32, y as i32);
+            if pos != win_data.pos {
+                win_data.pos = pos;
+                send_event(win_data, Event::Moved(pos.0, pos.1), false);
+            }
+        }
+
+        xlib::PropertyNotify    => {
+            let xproperty: &xlib::XPropertyEvent = xev.as_ref();
+
+            if xproperty.atom == conn.atom("_NET_WM_STATE") {
+                let state = get_window_state(conn, window);
+                let win_data = &mut data.win_vec[index];
+
+                if state != win_data.state {
+                    win_data.state = state;
+                    send_event(win_data, Event::Resized(state, win_data.size.0, win_data.size.1), false);
+                }
+            }
+        }
+
+        xlib::ClientMessage     => {
+            let xclient: &xlib::XClientMessageEvent = xev.as_ref();
+
+            // The close button was pressed. The window manager leaves it up to us whether the window
+            // actually gets destroyed.
+            if xclient.message_type == conn.wm_protocols &&
+               xclient.data.get_long(0) as xlib::Atom == conn.wm_delete_window {
+                send_event(&data.win_vec[index], Event::CloseRequested, false);
+            }
+        }
+
+        xlib::DestroyNotify     => {
+            let xdestroy: &xlib::XDestroyWindowEvent = xev.as_ref();
+
+            // Destroy events get sent to both the destroyed window and its parent, so make sure that
+            // this is the window that was actually destroyed.
+            if xdestroy.window == window {
+                let win_data = data.win_vec.remove(index);
+                if !win_data.ic.is_null() {
+                    xlib::XDestroyIC(win_data.ic);
+                }
+                for &cursor in win_data.cursors.values() {
+                    xlib::XFreeCursor(conn.display, cursor);
+                }
+                win_data.sink.send(Event::Destroyed);
+
+                if window == top_window {
+                    return false;
+                }
+            }
+        }
+
+        _ => ()
+    }
+
+    true
+}
+
+/// Translate an XInput event. When the server supports smooth scrolling, windows get these instead
+/// of the core button and motion events.
+unsafe fn xinput_callback(conn: &XConnection, data: &mut CallbackData, xev: &mut xlib::XEvent) {
+    let cookie: &mut xlib::XGenericEventCookie = xev.as_mut();
+    if Some(cookie.extension) != conn.xinput || xlib::XGetEventData(conn.display, cookie) == xlib::False {
+        return;
+    }
+
+    match cookie.evtype {
+        xinput2::XI_ButtonPress     |
+        xinput2::XI_ButtonRelease   |
+        xinput2::XI_Motion          => {
+            let xidevice = &*(cookie.data as *const xinput2::XIDeviceEvent);
+            let state = xidevice.mods.effective as c_uint;
+            let button = xidevice.detail as c_uint;
+
+            if let Some(win_data) = data.win_vec.iter_mut().find(|w| w.window == xidevice.event) {
+                let display = conn.display;
+
+                let pen = data.pen_devices.entry(xidevice.sourceid)
+                    .or_insert_with(|| xinput::query_pen(display, xidevice.sourceid));
+                if let Some(ref mut pen) = *pen {
+                    pen.update(&xidevice.valuators);
+                    send_event(win_data, pen_event(pen, xidevice, cookie.evtype), true);
+                }
+
+                match cookie.evtype {
+                    // Smooth scrolling devices also send the scroll wheel buttons for the sake of
+                    // older clients, which would count the scrolling twice.
+                    xinput2::XI_ButtonPress if xidevice.flags & xinput2::XIPointerEmulated != 0 => (),
+                    xinput2::XI_ButtonPress     => button_press(win_data, button, xidevice.time, state),
+                    xinput2::XI_ButtonRelease   => button_release(win_data, button, state),
+
+                    _ => {
+                        let pos = (xidevice.event_x as i32, xidevice.event_y as i32);
+                        if xinput::moved_pointer(&xidevice.valuators) && win_data.pointer_moved(conn, pos) {
+                            send_event(win_data, Event::MouseMoved(pos.0, pos.1), true);
+                        }
+
+                        let axes = data.scroll_devices.entry(xidevice.sourceid)
+                            .or_insert_with(|| xinput::query_scroll_axes(display, xidevice.sourceid));
+
+                        // XInput's vertical axis points down, and tub's points up
+                        let (x, y) = xinput::scroll_delta(axes, &xidevice.valuators);
+                        if x != 0.0 || y != 0.0 {
+                            send_event(win_data, Event::MouseWheel(ScrollDelta::Lines(x as f32, -y as f32), TouchPhase::Moved), true);
+                        }
+                    }
+                }
+            }
+        }
+
+        xinput2::XI_TouchBegin      |
+        xinput2::XI_TouchUpdate     |
+        xinput2::XI_TouchEnd        => {
+            let xidevice = &*(cookie.data as *const xinput2::XIDeviceEvent);
+
+            if let Some(win_data) = data.win_vec.iter_mut().find(|w| w.window == xidevice.event) {
+                let phase = match cookie.evtype {
+                    xinput2::XI_TouchBegin  => TouchPhase::Started,
+                    xinput2::XI_TouchUpdate => TouchPhase::Moved,
+                    _                       => TouchPhase::Ended
+                };
+
+                let id = xinput::touch_id(xidevice.sourceid, xidevice.detail);
+                let pos = (xidevice.event_x as i32, xidevice.event_y as i32);
+                match phase {
+                    TouchPhase::Ended => win_data.touches.remove(&id),
+                    _                 => win_data.touches.insert(id, pos)
+                };
+
+                let display = conn.display;
+                let pressure = data.touch_devices.entry(xidevice.sourceid)
+                    .or_insert_with(|| xinput::query_pressure_axis(display, xidevice.sourceid))
+                    .as_ref()
+                    .and_then(|axis| axis.value(&xidevice.valuators))
+                    .map(|p| p as f32);
+
+                send_event(win_data, Event::Touch {
+                    id: id,
+                    phase: phase,
+                    x: pos.0,
+                    y: pos.1,
+                    pressure: pressure
+                }, true);
+            }
+        }
+
+        // Raw motion comes from every pointer on the screen, so it only goes to the focused window
+        xinput2::XI_RawMotion       => {
+            let raw = &*(cookie.data as *const xinput2::XIRawEvent);
+
+            if let Some(win_data) = data.win_vec.iter().find(|w| w.focused) {
+                let display = conn.display;
+                let relative = *data.relative_devices.entry(raw.sourceid)
+                    .or_insert_with(|| xinput::query_relative(display, raw.sourceid));
+
+                let (dx, dy) = xinput::raw_delta(raw);
+                if relative && (dx != 0.0 || dy != 0.0) {
+                    send_event(win_data, Event::MouseMotion(dx, dy), true);
+                }
+            }
+        }
+
+        // Sent when a master device switches to a different physical device, which may have
+        // different scroll axes
+        xinput2::XI_DeviceChanged   => {
+            let changed = &*(cookie.data as *const xinput2::XIDeviceChangedEvent);
+            data.scroll_devices.insert(changed.sourceid, xinput::scroll_axes(changed.classes, changed.num_classes));
+        }
+
+        _ => ()
+    }
+
+    xlib::XFreeEventData(conn.display, cookie);
+}
+
+/// Make the event for a pen's XInput event
+unsafe fn pen_event(pen: &Pen, xidevice: &xinput2::XIDeviceEvent, evtype: c_int) -> Event {
+    use event::{DeviceId, PenButtons};
+
+    // The button state is from before the event, so it doesn't include the button that the event
+    // is for
+    let button_down = |button| match evtype {
+        xinput2::XI_ButtonPress   if xidevice.detail == button => true,
+        xinput2::XI_ButtonRelease if xidevice.detail == button => false,
+        _ => xinput::button_down(&xidevice.buttons, button)
+    };
+
+    Event::Pen {
+        device: DeviceId(xidevice.sourceid as u64),
+        x: xidevice.event_x,
+        y: xidevice.event_y,
+        pressure: pen.state.pressure,
+        tilt_x: pen.state.tilt_x,
+        tilt_y: pen.state.tilt_y,
+        rotation: pen.state.rotation,
+        eraser: pen.eraser,
+        buttons: PenButtons {
+            tip: button_down(1),
+            barrel: button_down(2),
+            secondary_barrel: button_down(3)
+        }
+    }
+}
+
+/// Handle a mouse button being pressed, which includes the scroll wheel's buttons
+fn button_press(win_data: &mut WindowDataIntern, button: c_uint, time: xlib::Time, state: c_uint) {
+    use event::ClickType;
+
+    if let Some(delta) = scroll_from_button(button) {
+        send_event(win_data, Event::MouseWheel(delta, TouchPhase::Moved), true);
+    }
+
+    if let Some(mbutton) = mbutton_from_x(button) {
+        // X doesn't report double clicks, so they have to be detected by hand
+        let click_type = match win_data.last_click {
+            Some((b, t)) if b == button && time.wrapping_sub(t) < DOUBLE_CLICK_TIME => {
+                win_data.last_click = None;
+                ClickType::Double
+            }
+
+            _ => {
+                win_data.last_click = Some((button, time));
+                ClickType::Single
+            }
+        };
+
+        send_event(win_data, Event::MButtonInput(click_type, mbutton, modifiers_from_state(state)), true);
+    }
+}
+
+fn button_release(win_data: &WindowDataIntern, button: c_uint, state: c_uint) {
+    use event::ClickType;
+
+    if let Some(mbutton) = mbutton_from_x(button) {
+        send_event(win_data, Event::MButtonInput(ClickType::Released, mbutton, modifiers_from_state(state)), true);
+    }
+}
+
+/// Send an event to a window. Input events are dropped if the window is disabled.
+#[inline]
+fn send_event(win_data: &WindowDataIntern, event: Event, is_input: bool) {
+    if win_data.enabled || !is_input {
+        win_data.sink.send(event);
+    }
+}
+
+/// Get the text typed by a key press. This goes through the window's input context if it has one,
+/// and otherwise falls back to `XLookupString`, which only knows about Latin-1.
+unsafe fn lookup_string(ic: xlib::XIC, xkey: &mut xlib::XKeyEvent) -> String {
+    let mut buffer = vec![0u8; 32];
+
+    if ic.is_null() {
+        let len = xlib::XLookupString(xkey, buffer.as_mut_ptr() as *mut c_char, buffer.len() as c_int, ptr::null_mut(), ptr::null_mut());
+        return buffer[..len as usize].iter().map(|&b| b as char).collect();
+    }
+
+    let mut status = 0;
+    let mut len = xlib::Xutf8LookupString(ic, xkey, buffer.as_mut_ptr() as *mut c_char, buffer.len() as c_int, ptr::null_mut(), &mut status);
+
+    // Input methods can commit more text than fits in the buffer, in which case the length that's
+    // returned is the size of buffer that's needed.
+    if status == xlib::XBufferOverflow {
+        buffer = vec![0; len as usize];
+        len = xlib::Xutf8LookupString(ic, xkey, buffer.as_mut_ptr() as *mut c_char, buffer.len() as c_int, ptr::null_mut(), &mut status);
+    }
+
+    match status {
+        xlib::XLookupChars |
+        xlib::XLookupBoth  => String::from_utf8_lossy(&buffer[..len as usize]).into_owned(),
+        _                  => String::new()
+    }
+}
+
+/// Get the scancode for an X keycode. Servers that use the evdev or libinput drivers, which is every
+/// modern one, make keycodes by adding 8 to the evdev keycode.
+#[inline]
+fn scancode_from_x(keycode: c_uint) -> ScanCode {
+    evdev::scancode_from_evdev(keycode.wrapping_sub(8))
+}
+
+/// Get the modifiers from the `state` of a key or button event. X reports the state from just
+/// before the event, so pressing a modifier key doesn't show up in its own event. Alt, num lock and
+/// the logo key aren't fixed modifiers in X, but nearly every keymap puts them on Mod1, Mod2 and
+/// Mod4.
+fn modifiers_from_state(state: c_uint) -> ModifiersState {
+    ModifiersState {
+        shift: state & xlib::ShiftMask != 0,
+        ctrl: state & xlib::ControlMask != 0,
+        alt: state & xlib::Mod1Mask != 0,
+        logo: state & xlib::Mod4Mask != 0,
+        caps_lock: state & xlib::LockMask != 0,
+        num_lock: state & xlib::Mod2Mask != 0
+    }
+}
+
+/// Get the scrolling that one click of the scroll wheel buttons makes. Buttons 4 and 5 scroll up
+/// and down, and 6 and 7 scroll left and right.
+fn scroll_from_button(button: c_uint) -> Option<ScrollDelta> {
+    match button {
+        4 => Some(ScrollDelta::Lines(0.0, 1.0)),
+        5 => Some(ScrollDelta::Lines(0.0, -1.0)),
+        6 => Some(ScrollDelta::Lines(-1.0, 0.0)),
+        7 => Some(ScrollDelta::Lines(1.0, 0.0)),
+        _ => None
+    }
+}
+
+fn mbutton_from_x(button: c_uint) -> Option<::event::MButton> {
+    use event::MButton::*;
+
+    match button {
+        xlib::Button1 => Some(Left),
+        xlib::Button2 => Some(Middle),
+        xlib::Button3 => Some(Right),
+        // Buttons 4 through 7 are the scroll wheel, and 8 and 9 are the back and forward buttons.
+        8 => Some(Button4),
+        9 => Some(Button5),
+        _ => None
+    }
+}
+
+/// Figure out whether the window is currently minimized or maximized from its `_NET_WM_STATE`
+fn get_window_state(conn: &XConnection, window: xlib::Window) -> ResizeType {
+    let states = get_property::<c_ulong>(conn, window, conn.atom("_NET_WM_STATE"), xlib::XA_ATOM).unwrap_or(Vec::new());
+
+    let has_state = |name: &str| {
+        let atom = conn.atom(name);
+        states.iter().any(|s| *s as xlib::Atom == atom)
+    };
+
+    if has_state("_NET_WM_STATE_HIDDEN") {
+        ResizeType::Minimized
+    }
+    else if has_state("_NET_WM_STATE_MAXIMIZED_VERT") && has_state("_NET_WM_STATE_MAXIMIZED_HORZ") {
+        ResizeType::Maximized
+    }
+    else {
+        ResizeType::Changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KeysDown;
+    use event::PressState;
+
+    #[test]
+    fn keys_down_after_focus_loss() {
+        let mut keys = KeysDown::default();
+        assert_eq!(keys.press(38), PressState::Pressed);
+        assert_eq!(keys.press(38), PressState::Held);
+
+        // The key is released while another window has focus, so there's no release for it
+        keys.clear();
+        assert_eq!(keys.press(38), PressState::Pressed);
+        keys.release(38);
+        assert_eq!(keys.press(38), PressState::Pressed);
+    }
+}
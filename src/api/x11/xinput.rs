@@ -0,0 +1,320 @@
+use x11::xlib;
+use x11::xinput2;
+
+use std::slice;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+
+/// A valuator that a device scrolls with. XInput reports these as absolute values that change by
+/// `increment` for every step of scrolling, so the amount scrolled has to be worked out from the
+/// difference between events.
+pub struct ScrollAxis {
+    number: c_int,
+    horizontal: bool,
+    increment: f64,
+    /// The value from the last event, or `None` if it's not known yet
+    value: Option<f64>
+}
+
+/// A valuator that reports an absolute value within a range, like how hard a touch is pressing
+pub struct RangeAxis {
+    number: c_int,
+    min: f64,
+    max: f64
+}
+
+impl RangeAxis {
+    /// Get the axis's value from an event, scaled to be from 0 to 1
+    pub unsafe fn value(&self, valuators: &xinput2::XIValuatorState) -> Option<f64> {
+        valuator_value(valuators, self.number).map(|v| (v - self.min) / (self.max - self.min))
+    }
+}
+
+/// The pen of a tablet. Events only have the values of the valuators that changed, so the pen
+/// remembers the rest from earlier events.
+pub struct Pen {
+    pressure: Option<RangeAxis>,
+    tilt_x: Option<c_int>,
+    tilt_y: Option<c_int>,
+    rotation: Option<RangeAxis>,
+    pub eraser: bool,
+    pub state: PenState
+}
+
+/// The values that a pen reported most recently
+#[derive(Default)]
+pub struct PenState {
+    pub pressure: f32,
+    pub tilt_x: f32,
+    pub tilt_y: f32,
+    pub rotation: f32
+}
+
+impl Pen {
+    /// Remember the values from an event
+    pub unsafe fn update(&mut self, valuators: &xinput2::XIValuatorState) {
+        let state = &mut self.state;
+        if let Some(pressure) = self.pressure.as_ref().and_then(|a| a.value(valuators)) {
+            state.pressure = pressure as f32;
+        }
+
+        // The tablet drivers report tilt in degrees already
+        if let Some(tilt_x) = self.tilt_x.and_then(|n| valuator_value(valuators, n)) {
+            state.tilt_x = tilt_x.max(-90.0).min(90.0) as f32;
+        }
+        if let Some(tilt_y) = self.tilt_y.and_then(|n| valuator_value(valuators, n)) {
+            state.tilt_y = tilt_y.max(-90.0).min(90.0) as f32;
+        }
+
+        if let Some(rotation) = self.rotation.as_ref().and_then(|a| a.value(valuators)) {
+            state.rotation = rotation as f32 * 360.0;
+        }
+    }
+}
+
+/// Get the major opcode of the XInput extension, if the server supports version 2.1, which is the
+/// first version with smooth scrolling, along with whether it supports version 2.2, which added
+/// touch events.
+pub unsafe fn query_opcode(display: *mut xlib::Display) -> Option<(c_int, bool)> {
+    let (mut opcode, mut event, mut error) = (0, 0, 0);
+    let name = b"XInputExtension\0";
+    if xlib::XQueryExtension(display, name.as_ptr() as *const c_char, &mut opcode, &mut event, &mut error) == xlib::False {
+        return None;
+    }
+
+    // The server replies with the highest version that both it and the client support
+    let (mut major, mut minor) = (2, 2);
+    match xinput2::XIQueryVersion(display, &mut major, &mut minor) == xlib::Success as c_int && (major, minor) >= (2, 1) {
+        true  => Some((opcode, (major, minor) >= (2, 2))),
+        false => None
+    }
+}
+
+/// Select the XInput events that a window handles instead of the core button and motion events.
+/// The server stops sending the core events to the window once these are selected, and selecting
+/// touch events stops it from turning touches into pointer events.
+pub unsafe fn select_events(display: *mut xlib::Display, window: xlib::Window, touch: bool) {
+    let mut mask = [0u8; 3];
+    xinput2::XISetMask(&mut mask, xinput2::XI_ButtonPress);
+    xinput2::XISetMask(&mut mask, xinput2::XI_ButtonRelease);
+    xinput2::XISetMask(&mut mask, xinput2::XI_Motion);
+    xinput2::XISetMask(&mut mask, xinput2::XI_DeviceChanged);
+
+    // The touch events can only be selected all together
+    if touch {
+        xinput2::XISetMask(&mut mask, xinput2::XI_TouchBegin);
+        xinput2::XISetMask(&mut mask, xinput2::XI_TouchUpdate);
+        xinput2::XISetMask(&mut mask, xinput2::XI_TouchEnd);
+    }
+
+    let mut event_mask = xinput2::XIEventMask {
+        deviceid: xinput2::XIAllMasterDevices,
+        mask_len: mask.len() as c_int,
+        mask: mask.as_mut_ptr()
+    };
+    xinput2::XISelectEvents(display, window, &mut event_mask, 1);
+}
+
+/// Select the raw motion events of every pointer on the screen. Raw events can only be selected on
+/// the root window, and they're sent whether or not the pointer is over one of the client's windows.
+pub unsafe fn select_raw_motion(display: *mut xlib::Display, root: xlib::Window) {
+    let mut mask = [0u8; 3];
+    xinput2::XISetMask(&mut mask, xinput2::XI_RawMotion);
+
+    let mut event_mask = xinput2::XIEventMask {
+        deviceid: xinput2::XIAllMasterDevices,
+        mask_len: mask.len() as c_int,
+        mask: mask.as_mut_ptr()
+    };
+    xinput2::XISelectEvents(display, root, &mut event_mask, 1);
+}
+
+/// Check whether a device reports relative motion, like a mouse, instead of absolute positions,
+/// like a tablet
+pub unsafe fn query_relative(display: *mut xlib::Display, deviceid: c_int) -> bool {
+    query_device(display, deviceid, |info| {
+        slice::from_raw_parts(info.classes, info.num_classes as usize).iter()
+            .filter(|&&c| (*c)._type == xinput2::XIValuatorClass)
+            .map(|&c| &*(c as *const xinput2::XIValuatorClassInfo))
+            .any(|v| v.number == 0 && v.mode == xinput2::XIModeRelative)
+    }).unwrap_or(false)
+}
+
+/// Get how far a raw motion event moved, before acceleration. Like the pointer position, the X and
+/// Y motion are the first two valuators.
+pub unsafe fn raw_delta(raw: &xinput2::XIRawEvent) -> (f64, f64) {
+    let value = |number| match packed_index(&raw.valuators, number) {
+        Some(i) => *raw.raw_values.offset(i as isize),
+        None    => 0.0
+    };
+    (value(0), value(1))
+}
+
+/// Get the scroll axes of a device from the server
+pub unsafe fn query_scroll_axes(display: *mut xlib::Display, deviceid: c_int) -> Vec<ScrollAxis> {
+    let mut count = 0;
+    let info = xinput2::XIQueryDevice(display, deviceid, &mut count);
+    if info.is_null() {
+        return Vec::new();
+    }
+
+    let axes = scroll_axes((*info).classes, (*info).num_classes);
+    xinput2::XIFreeDeviceInfo(info);
+    axes
+}
+
+/// Get the scroll axes out of a device's classes, along with their current values
+pub unsafe fn scroll_axes(classes: *mut *mut xinput2::XIAnyClassInfo, count: c_int) -> Vec<ScrollAxis> {
+    let classes = slice::from_raw_parts(classes, count as usize);
+
+    let mut axes: Vec<ScrollAxis> = classes.iter()
+        .filter(|&&c| (*c)._type == xinput2::XIScrollClass)
+        .map(|&c| {
+            let scroll = &*(c as *const xinput2::XIScrollClassInfo);
+            ScrollAxis {
+                number: scroll.number,
+                horizontal: scroll.scroll_type != xinput2::XIScrollTypeVertical,
+                increment: scroll.increment,
+                value: None
+            }
+        })
+        .collect();
+
+    for &c in classes.iter().filter(|&&c| (*c)._type == xinput2::XIValuatorClass) {
+        let valuator = &*(c as *const xinput2::XIValuatorClassInfo);
+        for axis in axes.iter_mut().filter(|a| a.number == valuator.number) {
+            axis.value = Some(valuator.value);
+        }
+    }
+
+    axes
+}
+
+/// Forget the last values of the axes. The values keep changing while the pointer is over other
+/// clients' windows, so they can't be compared against once the pointer comes back.
+pub fn reset_scroll_axes(axes: &mut [ScrollAxis]) {
+    for axis in axes {
+        axis.value = None;
+    }
+}
+
+/// Get how many steps an event scrolled by on each axis, and remember the event's values for next
+/// time. Like XInput, positive values are right and down.
+pub unsafe fn scroll_delta(axes: &mut [ScrollAxis], valuators: &xinput2::XIValuatorState) -> (f64, f64) {
+    let mask = slice::from_raw_parts(valuators.mask, valuators.mask_len as usize);
+    let mut values = valuators.values;
+    let mut delta = (0.0, 0.0);
+
+    // Only the valuators that changed are in the event, packed together in the order of their numbers
+    for number in 0..(mask.len() * 8) as c_int {
+        if !xinput2::XIMaskIsSet(mask, number) {
+            continue;
+        }
+        let value = *values;
+        values = values.offset(1);
+
+        for axis in axes.iter_mut().filter(|a| a.number == number) {
+            if let Some(last) = axis.value {
+                let steps = (value - last) / axis.increment;
+                match axis.horizontal {
+                    true  => delta.0 += steps,
+                    false => delta.1 += steps
+                }
+            }
+            axis.value = Some(value);
+        }
+    }
+
+    delta
+}
+
+/// Get the pressure valuator of a touch device from the server, if it has one
+pub unsafe fn query_pressure_axis(display: *mut xlib::Display, deviceid: c_int) -> Option<RangeAxis> {
+    query_device(display, deviceid, |info| find_axis(display, info, "Abs MT Pressure")).and_then(|a| a)
+}
+
+/// Get a device from the server if it's the pen or eraser of a tablet. Tablet drivers give each
+/// end of the pen its own device, and name the eraser's device after it.
+pub unsafe fn query_pen(display: *mut xlib::Display, deviceid: c_int) -> Option<Pen> {
+    query_device(display, deviceid, |info| {
+        let name = CStr::from_ptr(info.name).to_string_lossy().to_lowercase();
+        let pen = Pen {
+            pressure: find_axis(display, info, "Abs Pressure"),
+            tilt_x: find_axis(display, info, "Abs Tilt X").map(|a| a.number),
+            tilt_y: find_axis(display, info, "Abs Tilt Y").map(|a| a.number),
+            rotation: find_axis(display, info, "Abs Rotation Z").or_else(|| find_axis(display, info, "Abs Wheel")),
+            eraser: name.contains("eraser"),
+            state: PenState::default()
+        };
+
+        // Touchpads can have pressure too, but they don't tilt and aren't named like pens
+        let named_like_pen = ["stylus", "eraser", "pen"].iter().any(|n| name.contains(n));
+        match pen.pressure.is_some() && (pen.tilt_x.is_some() || named_like_pen) {
+            true  => Some(pen),
+            false => None
+        }
+    }).and_then(|p| p)
+}
+
+/// Get whether a button is down in an event's button state
+pub unsafe fn button_down(buttons: &xinput2::XIButtonState, button: c_int) -> bool {
+    let mask = slice::from_raw_parts(buttons.mask, buttons.mask_len as usize);
+    (button as usize) < mask.len() * 8 && xinput2::XIMaskIsSet(mask, button)
+}
+
+/// Look up a device on the server and pass its info to `f`
+unsafe fn query_device<T, F: FnOnce(&xinput2::XIDeviceInfo) -> T>(display: *mut xlib::Display, deviceid: c_int, f: F) -> Option<T> {
+    let mut count = 0;
+    let info = xinput2::XIQueryDevice(display, deviceid, &mut count);
+    if info.is_null() {
+        return None;
+    }
+
+    let result = f(&*info);
+    xinput2::XIFreeDeviceInfo(info);
+    Some(result)
+}
+
+/// Find the valuator of a device with the given label. The labels are atoms that the drivers
+/// create, so if the atom doesn't exist, no device has the valuator.
+unsafe fn find_axis(display: *mut xlib::Display, info: &xinput2::XIDeviceInfo, label: &str) -> Option<RangeAxis> {
+    let label = CString::new(label).unwrap();
+    let label = xlib::XInternAtom(display, label.as_ptr(), xlib::True);
+    if label == 0 {
+        return None;
+    }
+
+    slice::from_raw_parts(info.classes, info.num_classes as usize).iter()
+        .filter(|&&c| (*c)._type == xinput2::XIValuatorClass)
+        .map(|&c| &*(c as *const xinput2::XIValuatorClassInfo))
+        .find(|v| v.label == label && v.max > v.min)
+        .map(|v| RangeAxis{ number: v.number, min: v.min, max: v.max })
+}
+
+/// Get the value of one valuator from an event, if it's in the event
+unsafe fn valuator_value(valuators: &xinput2::XIValuatorState, number: c_int) -> Option<f64> {
+    packed_index(valuators, number).map(|i| *valuators.values.offset(i as isize))
+}
+
+/// Get where a valuator's value is in an event, if it's in the event. The values are packed
+/// together, so the value's index is how many valuators in the event come before it.
+unsafe fn packed_index(valuators: &xinput2::XIValuatorState, number: c_int) -> Option<usize> {
+    let mask = slice::from_raw_parts(valuators.mask, valuators.mask_len as usize);
+    match (number as usize) < mask.len() * 8 && xinput2::XIMaskIsSet(mask, number) {
+        true  => Some((0..number).filter(|&n| xinput2::XIMaskIsSet(mask, n)).count()),
+        false => None
+    }
+}
+
+/// Get an ID for a touch. XInput's touch IDs are only unique to the device, so the device is part
+/// of the ID too.
+#[inline]
+pub fn touch_id(deviceid: c_int, detail: c_int) -> u64 {
+    (deviceid as u64) << 32 | detail as u32 as u64
+}
+
+/// Check whether an event moved the pointer. Master pointers always have their X and Y position
+/// as the first two valuators.
+pub unsafe fn moved_pointer(valuators: &xinput2::XIValuatorState) -> bool {
+    valuators.mask_len > 0 && *valuators.mask & 0b11 != 0
+}
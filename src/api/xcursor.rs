@@ -0,0 +1,131 @@
+//! A minimal reader for Xcursor files, which is the format that X11 cursor themes use. libXcursor
+//! can load these on X11, but custom cursors are shared with the other backends, so the images are
+//! pulled out of the file by hand.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// The magic number at the start of every Xcursor file, "Xcur" read as a little-endian u32
+const MAGIC: u32 = 0x72756358;
+/// The type of the table of contents entries and chunks that hold images
+const IMAGE_TYPE: u32 = 0xfffd0002;
+/// The length of an image chunk's header
+const IMAGE_HEADER_LEN: usize = 36;
+/// The largest width or height that libXcursor accepts
+const MAX_SIZE: u32 = 0x7fff;
+
+/// A single image pulled out of an Xcursor file
+pub struct XcursorImage {
+    /// The size that the image is meant to be used at. This is usually, but not always, the
+    /// image's width and height.
+    pub nominal_size: u32,
+    pub width: u32,
+    pub height: u32,
+    pub hotspot: (u32, u32),
+    /// The image pixels, in ARGB order without premultiplied alpha, going from the top row to the
+    /// bottom row
+    pub pixels: Vec<u32>
+}
+
+/// Load the images from the Xcursor file at `path`. Animated cursors store every frame as its own
+/// image, and only the first frame of each size is kept. Returns `None` if the file could not be read
+/// or isn't a valid Xcursor file.
+pub fn load_xcursor(path: &Path) -> Option<Vec<XcursorImage>> {
+    let mut data = Vec::new();
+    match File::open(path) {
+        Ok(mut f) => if f.read_to_end(&mut data).is_err() { return None },
+        Err(_)    => return None
+    }
+
+    parse_xcursor(&data)
+}
+
+pub fn parse_xcursor(data: &[u8]) -> Option<Vec<XcursorImage>> {
+    if data.len() < 16 || read_u32(data, 0) != MAGIC {
+        return None;
+    }
+
+    let header_len = read_u32(data, 4) as usize;
+    let count = read_u32(data, 12) as usize;
+    let mut images: Vec<XcursorImage> = Vec::new();
+
+    for i in 0..count {
+        // Every table of contents entry is the chunk's type, subtype and position
+        let entry = match i.checked_mul(12).and_then(|e| e.checked_add(header_len)) {
+            Some(entry) if entry.checked_add(12).map_or(false, |end| end <= data.len()) => entry,
+            _ => return None
+        };
+
+        let nominal_size = read_u32(data, entry + 4);
+        if read_u32(data, entry) != IMAGE_TYPE || images.iter().any(|i| i.nominal_size == nominal_size) {
+            continue;
+        }
+
+        if let Some(image) = parse_image(data, read_u32(data, entry + 8) as usize) {
+            images.push(image);
+        }
+    }
+
+    Some(images)
+}
+
+fn parse_image(data: &[u8], offset: usize) -> Option<XcursorImage> {
+    match offset.checked_add(IMAGE_HEADER_LEN) {
+        Some(end) if end <= data.len() => (),
+        _ => return None
+    }
+    if read_u32(data, offset + 4) != IMAGE_TYPE {
+        return None;
+    }
+
+    let width = read_u32(data, offset + 16);
+    let height = read_u32(data, offset + 20);
+    let hotspot = (read_u32(data, offset + 24), read_u32(data, offset + 28));
+
+    if width == 0 || height == 0 || width > MAX_SIZE || height > MAX_SIZE {
+        return None;
+    }
+
+    let pixel_len = width as usize * height as usize;
+    let start = offset + IMAGE_HEADER_LEN;
+    match pixel_len.checked_mul(4).and_then(|l| l.checked_add(start)) {
+        Some(end) if end <= data.len() => (),
+        _ => return None
+    }
+
+    let pixels = (0..pixel_len).map(|i| unpremultiply(read_u32(data, start + i * 4))).collect();
+
+    Some(XcursorImage {
+        nominal_size: read_u32(data, offset + 8),
+        width: width,
+        height: height,
+        hotspot: hotspot,
+        pixels: pixels
+    })
+}
+
+/// Multiply a pixel's color by its alpha, which is what Xcursor and Wayland expect
+#[cfg(all(target_os = "linux", any(feature = "x11", feature = "wayland")))]
+pub fn premultiply(pixel: u32) -> u32 {
+    let alpha = pixel >> 24;
+    let channel = |shift: u32| (pixel >> shift & 0xFF) * alpha / 255 << shift;
+    alpha << 24 | channel(16) | channel(8) | channel(0)
+}
+
+/// Xcursor pixels have their color multiplied by their alpha, which has to be undone to match the
+/// pixels from everywhere else
+fn unpremultiply(pixel: u32) -> u32 {
+    let alpha = pixel >> 24;
+    if alpha == 0 || alpha == 255 {
+        return pixel;
+    }
+
+    let channel = |shift: u32| ((pixel >> shift & 0xFF) * 255 / alpha).min(255) << shift;
+    alpha << 24 | channel(16) | channel(8) | channel(0)
+}
+
+#[inline]
+fn read_u32(data: &[u8], at: usize) -> u32 {
+    data[at] as u32 | (data[at + 1] as u32) << 8 | (data[at + 2] as u32) << 16 | (data[at + 3] as u32) << 24
+}
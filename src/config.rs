@@ -12,9 +12,11 @@ use std::marker::{Send, Sync};
 /// 
 /// ```
 /// # use tub::config::WindowConfig;
-/// # use tub::platform::Window;
+/// # use tub::platform::{Window, EventsLoop};
 /// # use std::path::Path;
+/// # let events_loop = EventsLoop::new().unwrap();
 /// let window = Window::new(
+///     &events_loop,
 ///     WindowConfig::new()
 ///         .name("It's a window!".to_owned())
 ///         .icon(Some(Path::new("tub.ico").to_path_buf()))
@@ -285,4 +287,62 @@ impl Default for PixelFormat {
             stereoscopic: false
         }
     }
-}
\ No newline at end of file
+}
+
+/// The OpenGL API that a context implements
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlApi {
+    /// Desktop OpenGL
+    Gl,
+    /// OpenGL ES. Contexts for this API are always created through EGL.
+    Gles
+}
+
+/// A struct that contains information about the OpenGL context that gets created for a window. See
+/// the WindowConfig documentation for how and when to use the methods
+#[derive(Debug, Clone)]
+pub struct ContextConfig {
+    /// The API that the context should implement. Defaults to `GlApi::Gl`.
+    pub api: GlApi,
+    /// The `(major, minor)` version of the API that the context should implement. None is don't care,
+    /// defaults to None. Desktop OpenGL contexts of version 3.2 or newer use the core profile.
+    pub version: Option<(u8, u8)>,
+    /// Whether or not the context should be created through EGL instead of the platform's native
+    /// context API. Defaults to false.
+    pub egl: bool
+}
+
+impl ContextConfig {
+    #[inline]
+    pub fn new() -> ContextConfig {
+        Default::default()
+    }
+
+    #[inline]
+    pub fn api(mut self, api: GlApi) -> ContextConfig {
+        self.api = api;
+        self
+    }
+
+    #[inline]
+    pub fn version(mut self, version: Option<(u8, u8)>) -> ContextConfig {
+        self.version = version;
+        self
+    }
+
+    #[inline]
+    pub fn egl(mut self, egl: bool) -> ContextConfig {
+        self.egl = egl;
+        self
+    }
+}
+
+impl Default for ContextConfig {
+    fn default() -> ContextConfig {
+        ContextConfig {
+            api: GlApi::Gl,
+            version: None,
+            egl: false
+        }
+    }
+}
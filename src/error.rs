@@ -1,5 +1,6 @@
 use std::error::Error;
 use std::fmt;
+use std::path::PathBuf;
 
 pub type TubResult<T> = Result<T, TubError>;
 pub type GlCreationResult<T> = Result<T, GlCreationError>;
@@ -7,7 +8,9 @@ pub type GlCreationResult<T> = Result<T, GlCreationError>;
 #[derive(Debug, Clone)]
 pub enum TubError {
     OsError(String),
-    IconLoadError(u16)
+    IconLoadError(u16),
+    CursorLoadError(PathBuf),
+    BackendError(String)
 }
 
 impl fmt::Display for TubError {
@@ -16,7 +19,9 @@ impl fmt::Display for TubError {
 
         match *self {
             OsError(ref s) => write!(f, "{}", s),
-            IconLoadError(size) => write!(f, "Could not load {0}x{0} icon", size)
+            IconLoadError(size) => write!(f, "Could not load {0}x{0} icon", size),
+            CursorLoadError(ref p) => write!(f, "Could not load cursor from {}", p.display()),
+            BackendError(ref s) => write!(f, "Could not select window backend: {}", s)
         }
     }
 }
@@ -27,7 +32,9 @@ impl Error for TubError {
 
         match *self {
             OsError(ref s) => s,
-            IconLoadError(_) => "Icon load error"
+            IconLoadError(_) => "Icon load error",
+            CursorLoadError(_) => "Cursor load error",
+            BackendError(ref s) => s
         }
     }
 }
@@ -40,7 +47,10 @@ pub enum GlCreationError {
     MSAABufferError,
     IndescribableFormatError(String),
     ExtendedCreationError,
-    FunctionLoadError
+    FunctionLoadError,
+    UnsupportedApiError,
+    SharedContextError,
+    UnsupportedBackendError
 }
 
 impl fmt::Display for GlCreationError {
@@ -60,7 +70,10 @@ impl fmt::Display for GlCreationError {
                            situation may be a lack of support for software opengl (or vice versa). The OS \
                            error string is as follows: {}", e),
             ExtendedCreationError   => write!(f, "Could not create OpenGl context with extended attributes"),
-            FunctionLoadError       => write!(f, "Could not load functions for OpenGl context creation with attributes")
+            FunctionLoadError       => write!(f, "Could not load functions for OpenGl context creation with attributes"),
+            UnsupportedApiError     => write!(f, "The requested OpenGl API or version is not supported by this context backend"),
+            SharedContextError      => write!(f, "Could not share resources with a context created by a different backend"),
+            UnsupportedBackendError => write!(f, "OpenGl contexts can't be created for windows from this window backend")
         }
     }
 }
@@ -76,7 +89,10 @@ impl Error for GlCreationError {
             MSAABufferError             => "Could not create multisampled pixel format",
             IndescribableFormatError(_) => "Indescribable pixel format; see documentation for more details",
             ExtendedCreationError       => "Could not create OpenGl context with extended attributes",
-            FunctionLoadError           => "Could not load functions for OpenGl context creation with attributes"
+            FunctionLoadError           => "Could not load functions for OpenGl context creation with attributes",
+            UnsupportedApiError         => "Unsupported OpenGl API or version",
+            SharedContextError          => "Could not share resources with a context created by a different backend",
+            UnsupportedBackendError     => "OpenGl contexts can't be created for windows from this window backend"
         }
     }
 }
\ No newline at end of file
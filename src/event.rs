@@ -1,17 +1,42 @@
 use num::FromPrimitive;
 
+use std::fmt;
+use std::any::Any;
+use std::sync::Arc;
+
 pub type Xi32 = i32;
 pub type Yi32 = i32;
 pub type Xu32 = u32;
 pub type Yu32 = u32;
 
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Event {
-    KeyInput(PressState, VKeyCode),
-    MButtonInput(ClickType, MButton),
+    /// Triggered when a key is pressed, held or released. The key is `None` for keys that tub
+    /// doesn't know the meaning of, but every key has a scancode.
+    KeyInput(PressState, Option<Key>, ScanCode, ModifiersState),
+    /// Triggered when a character is typed. This takes the keyboard layout, dead keys and compose
+    /// sequences into account, so text input should use this instead of `KeyInput`. Keys like
+    /// enter and backspace send control characters.
+    ReceivedCharacter(char),
+    /// Triggered when an input method starts composing text
+    ImeCompositionStart,
+    /// Triggered when the text being composed changes. The range is where the cursor is in the
+    /// text, in bytes, and is `None` if the cursor should be hidden.
+    ImeCompositionUpdate(String, Option<(usize, usize)>),
+    /// Triggered when a composition ends, with the text that it produced. The text is empty if the
+    /// composition was cancelled. Otherwise, it's also sent as `ReceivedCharacter` events right
+    /// after this, so apps that don't show compositions can ignore these events.
+    ImeCompositionCommit(String),
+    MButtonInput(ClickType, MButton, ModifiersState),
     /// Triggered when the mouse moves
     MouseMoved(Xi32, Yi32),
+    /// Triggered when the mouse moves, with how far it moved in the mouse's own units before the OS
+    /// applies acceleration. These keep coming when the cursor is against the edge of the screen
+    /// or locked with `GrabMode::Locked`, and are sent to the focused window wherever the cursor is.
+    MouseMotion(f64, f64),
+    /// Triggered when the mouse wheel is turned or the user scrolls on a touchpad
+    MouseWheel(ScrollDelta, TouchPhase),
     /// Triggered when the mouse hovers over one point for a system-specified length
     /// of time
     MouseHover(Xi32, Yi32),
@@ -19,19 +44,135 @@ pub enum Event {
     MouseLeave,
     /// Triggered when the mouse enters the client area
     MouseEnter,
+    /// Triggered when a finger touches, moves across or is lifted from a touchscreen. Touchscreens
+    /// send these instead of mouse events.
+    Touch {
+        /// Identifies the finger for as long as it's touching the screen. Fingers that are touching
+        /// at the same time always have different IDs, but IDs can be reused afterwards.
+        id: u64,
+        /// `Started` when the finger touches the screen, `Ended` when it's lifted, and `Cancelled`
+        /// if the OS takes over the touch, like for a system gesture. On X11, touches are cancelled
+        /// when the window is unmapped or another client grabs the pointer.
+        phase: TouchPhase,
+        x: Xi32,
+        y: Yi32,
+        /// How hard the finger is pressing, from 0 to 1, or `None` if the touchscreen can't tell
+        pressure: Option<f32>
+    },
+    /// Triggered when a tablet pen moves over the window or its tip or buttons change. Pens still
+    /// move the mouse as well, so each of these is followed by the mouse events for the same input.
+    /// Values that the pen doesn't report are 0.
+    Pen {
+        /// The tablet that the pen is on, which stays the same for as long as it's plugged in
+        device: DeviceId,
+        x: f64,
+        y: f64,
+        /// How hard the tip is pressing, from 0 to 1
+        pressure: f32,
+        /// How far the pen is tilted to the right, in degrees from -90 to 90
+        tilt_x: f32,
+        /// How far the pen is tilted towards the user, in degrees from -90 to 90
+        tilt_y: f32,
+        /// How far the pen is turned clockwise around its own axis, in degrees from 0 to 360
+        rotation: f32,
+        /// Whether the pen is being used the other way around, as an eraser
+        eraser: bool,
+        buttons: PenButtons
+    },
     /// Triggered when the window is resized - note that this includes when the window
     /// is first created.
     Resized(ResizeType, Xu32, Xu32),
     Moved(Xi32, Yi32),
-    Closed
+    /// Triggered when the window gains (`true`) or loses (`false`) keyboard focus
+    Focused(bool),
+    /// Triggered when the user asks for the window to be closed, like by clicking the close button.
+    /// The window stays open until `Window::close` is called or the window is dropped, so apps can
+    /// ask whether to save first or ignore the request entirely.
+    CloseRequested,
+    /// Triggered once the window has been destroyed. The window doesn't get any events after this.
+    Destroyed,
+    /// Triggered when the window needs to be redrawn, either because the OS has lost what was drawn
+    /// or because `Window::request_redraw` was called. A window gets at most one of these each time
+    /// the events loop runs out of other events.
+    RedrawRequested,
+    /// Sent by `WindowProxy::wakeup`
+    Awakened,
+    /// Sent by `WindowProxy::send_user_event`
+    User(UserEvent)
+}
+
+/// A value of any type that was sent to a window with `WindowProxy::send_user_event`. Get the value
+/// back out with `downcast_ref`.
+///
+/// User events are compared by identity, so a user event is only equal to clones of itself.
+#[derive(Clone)]
+pub struct UserEvent(Arc<Any + Send + Sync>);
+
+impl UserEvent {
+    pub fn new<T: Any + Send + Sync>(value: T) -> UserEvent {
+        UserEvent(Arc::new(value))
+    }
+
+    #[inline]
+    pub fn is<T: Any>(&self) -> bool {
+        self.0.is::<T>()
+    }
+
+    #[inline]
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.0.downcast_ref::<T>()
+    }
+}
+
+impl PartialEq for UserEvent {
+    fn eq(&self, other: &UserEvent) -> bool {
+        &*self.0 as *const _ as *const () == &*other.0 as *const _ as *const ()
+    }
+}
+
+impl Eq for UserEvent {}
+
+impl fmt::Debug for UserEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "UserEvent(..)")
+    }
+}
+
+/// The modifier keys that were held down, and the lock keys that were on, when a key or mouse
+/// button event happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ModifiersState {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    /// The Windows key, or the Command key on Apple keyboards
+    pub logo: bool,
+    pub caps_lock: bool,
+    pub num_lock: bool
+}
+
+/// Identifies an input device, like a tablet. IDs are only unique among the devices that are
+/// plugged in, and can be reused once a device is unplugged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DeviceId(pub u64);
+
+/// The parts of a pen that were pressed down when a pen event happened
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PenButtons {
+    /// Whether the pen's tip is touching the tablet
+    pub tip: bool,
+    /// The button on the side of the pen that's nearest to the tip
+    pub barrel: bool,
+    /// The second button on the side of the pen, which isn't reported on Windows
+    pub secondary_barrel: bool
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum PressState {
     Pressed,
-    /// On windows, additional virtual keypresses are generated after a key has
-    /// been held down for long enough. When that happens, this state is triggered.
+    /// Sent over and over while a key is held down, at the user's key repeat rate. Keys that
+    /// type text send `ReceivedCharacter` events with every repeat too.
     Held,
     Released
 }
@@ -54,6 +195,29 @@ pub enum MButton {
     Button5 = 4
 }
 
+/// How far a `MouseWheel` event scrolled. Positive values scroll right and up, which is away from
+/// the user on a mouse wheel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScrollDelta {
+    /// The number of lines to scroll by, which is how mouse wheels report scrolling. Each notch of
+    /// the wheel is one line, but high-resolution wheels and touchpads can scroll by fractions of
+    /// a line.
+    Lines(f32, f32),
+    /// The number of pixels to scroll by, for devices that scroll smoothly instead of in steps
+    Pixels(f32, f32)
+}
+
+/// Where an event is in a gesture, like a touch or a touchpad scroll. Devices that don't have
+/// gestures, like mouse wheels, only ever send `Moved`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum TouchPhase {
+    Started,
+    Moved,
+    Ended,
+    Cancelled
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum ResizeType {
@@ -65,201 +229,239 @@ pub enum ResizeType {
     Changed
 }
 
+/// A key, identified by what it means under the active keyboard layout rather than by where it is
+/// on the keyboard. Use `ScanCode` for the latter.
+///
+/// The discriminants don't correspond to any platform's key codes; use the functions in
+/// `tub::keymap` to convert between these and platform codes. They do start at 0 and have no gaps,
+/// so `FromPrimitive` can be used to go through every key.
 #[repr(u8)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum VKeyCode {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
     /// Backspace
-    Back    = 0x08,
-    Tab     = 0x09,
-    Clear   = 0x0C,
-    Enter   = 0x0D,
-    Pause   = 0x13,
-    Escape  = 0x1B,
-    Space   = 0x20,
-    PgUp    = 0x21,
-    PgDn    = 0x22,
-    End     = 0x23,
-    Home    = 0x24,
-    Select  = 0x29,
-    Print   = 0x2A,
-    Execute = 0x2B,
-    PrntScr = 0x2C,
-    Insert  = 0x2D,
-    Delete  = 0x2E,
-    Help    = 0x2F,
-
-    Key0    = 0x30,
-    Key1    = 0x31,
-    Key2    = 0x32,
-    Key3    = 0x33,
-    Key4    = 0x34,
-    Key5    = 0x35,
-    Key6    = 0x36,
-    Key7    = 0x37,
-    Key8    = 0x38,
-    Key9    = 0x39,
-
-    A   = 0x41,
-    B   = 0x42,
-    C   = 0x43,
-    D   = 0x44,
-    E   = 0x45,
-    F   = 0x46,
-    G   = 0x47,
-    H   = 0x48,
-    I   = 0x49,
-    J   = 0x4a,
-    K   = 0x4b,
-    L   = 0x4c,
-    M   = 0x4d,
-    N   = 0x4e,
-    O   = 0x4f,
-    P   = 0x50,
-    Q   = 0x51,
-    R   = 0x52,
-    S   = 0x53,
-    T   = 0x54,
-    U   = 0x55,
-    V   = 0x56,
-    W   = 0x57,
-    X   = 0x58,
-    Y   = 0x59,
-    Z   = 0x5a,
+    Back,
+    Tab,
+    Clear,
+    Enter,
+    Pause,
+    Escape,
+    Space,
+    PgUp,
+    PgDn,
+    End,
+    Home,
+    Select,
+    Print,
+    Execute,
+    PrntScr,
+    Insert,
+    Delete,
+    Help,
+
+    Key0,
+    Key1,
+    Key2,
+    Key3,
+    Key4,
+    Key5,
+    Key6,
+    Key7,
+    Key8,
+    Key9,
+
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
 
     /// ';:' on US standard keyboards, though it may not be this on other layouts
-    Semi    = 0xBA,
-    Plus    = 0xBB,
-    Comma   = 0xBC,
-    Minus   = 0xBD,
+    Semi,
+    /// '=+' on US standard keyboards, though it may not be this on other layouts
+    Plus,
+    Comma,
+    Minus,
     /// The period key
-    Dot     = 0xBE,
+    Dot,
     /// '/?' on US standard keyboards, though it may not be this on other layouts
-    Slash   = 0xBF,
+    Slash,
     /// '`~' on US standard keyboards, though it may not be this on other layouts
-    Tilde   = 0xC0,
+    Tilde,
 
     /// '[{' on US standard keyboards, though it may not be this on other layouts
-    LBrac   = 0xDB,
+    LBrac,
     /// ']}' on US standard keyboards, though it may not be this on other layouts
-    RBrac   = 0xDD,
+    RBrac,
     /// '\|' on US standard keyboards, though it may not be this on other layouts
-    Pipe    = 0xDC,
+    Pipe,
     /// `"'` on US standard keyboards, though it may not be this on other layouts
-    Quote   = 0xDE,
-
-    Sleep   = 0x5F,
-    Num0    = 0x60,
-    Num1    = 0x61,
-    Num2    = 0x62,
-    Num3    = 0x63,
-    Num4    = 0x64,
-    Num5    = 0x65,
-    Num6    = 0x66,
-    Num7    = 0x67,
-    Num8    = 0x68,
-    Num9    = 0x69,
-    NumStar = 0x6A,
-    NumPlus = 0x6B,
-    NumSub  = 0x6D,
-    NumDot  = 0x6E,
-    NumSlash= 0x6F,
-
-    F1      = 0x70,
-    F2      = 0x71,
-    F3      = 0x72,
-    F4      = 0x73,
-    F5      = 0x74,
-    F6      = 0x75,
-    F7      = 0x76,
-    F8      = 0x77,
-    F9      = 0x78,
-    F10     = 0x79,
-    F11     = 0x7A,
-    F12     = 0x7B,
-    F13     = 0x7C,
-    F14     = 0x7D,
-    F15     = 0x7E,
-    F16     = 0x7F,
-    F17     = 0x80,
-    F18     = 0x81,
-    F19     = 0x82,
-    F20     = 0x83,
-    F21     = 0x84,
-    F22     = 0x85,
-    F23     = 0x86,
-    F24     = 0x87,
-
-    Num     = 0x90,
-    Caps    = 0x14,
-    Scroll  = 0x91,
-
-    LShift  = 0xA0,
-    RShift  = 0xA1,
-    LCtrl   = 0xA2,
-    RCtrl   = 0xA3,
-    LAlt    = 0xA4,
-    RAlt    = 0xA5,
+    Quote,
+    /// A key that differs between layouts, like '!§' on French keyboards
+    Oem8,
+    /// The extra key next to left shift on ISO keyboards, like '<>' on German keyboards
+    Oem102,
+
+    /// The left Windows key, or the left Command key on Apple keyboards
+    LWin,
+    /// The right Windows key, or the right Command key on Apple keyboards
+    RWin,
+    /// The context menu key
+    Apps,
+    Sleep,
+
+    Num0,
+    Num1,
+    Num2,
+    Num3,
+    Num4,
+    Num5,
+    Num6,
+    Num7,
+    Num8,
+    Num9,
+    NumStar,
+    NumPlus,
+    /// The numpad's thousands separator, which only some layouts have
+    NumSeparator,
+    NumSub,
+    NumDot,
+    NumSlash,
+
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
+
+    Num,
+    Caps,
+    Scroll,
+
+    LShift,
+    RShift,
+    LCtrl,
+    RCtrl,
+    LAlt,
+    /// Right alt, which is AltGr on international layouts
+    RAlt,
 
     /// Browser back key
-    BBack   = 0xA6,
+    BBack,
     /// Browser forward key
-    BFwd    = 0xA7,
+    BFwd,
     /// Browser refresh key
-    BRef    = 0xA8,
+    BRef,
     /// Browser stop key
-    BStop   = 0xA9,
+    BStop,
     /// Browser search key
-    BSearch = 0xAA,
+    BSearch,
     /// Browser favorites key
-    BFav    = 0xAB,
+    BFav,
     /// Browser start/home key
-    BHome   = 0xAC,
-
+    BHome,
+
+    /// Mute volume key
+    VolMute,
+    /// Volume down key
+    VolDown,
+    /// Volume up key
+    VolUp,
     /// Next track key
-    MNTrack = 0xB0,
+    MNTrack,
     /// Previous track key
-    MPTrack = 0xB1, // B)
+    MPTrack,
     /// Stop media key
-    MStop   = 0xB2,
+    MStop,
     /// Play/pause media key
-    MPause  = 0xB3,
-
-    
+    MPause,
+    /// Start mail key
+    Mail,
+    /// Select media key
+    MSelect,
+    /// Start application 1 key, usually "My Computer"
+    App1,
+    /// Start application 2 key, usually the calculator
+    App2,
 
     /// Left arrow key
-    LArrow  = 0x25,
+    LArrow,
     /// Up arrow key
-    UArrow  = 0x26,
+    UArrow,
     /// Right arrow key
-    RArrow  = 0x27,
+    RArrow,
     /// Down arrow key
-    DArrow  = 0x28,
-
+    DArrow,
 
     // IME keys
-    Kana    = 0x15,
-    Junja   = 0x17,
-    Final   = 0x18,
-    Kanji   = 0x19,
-    Convert = 0x1C,
-    Nonconvert = 0x1D,
-    Accept  = 0x1E,
-    ModeChange = 0x1F,
-    Process = 0xE5,
-
-
-
-    // Come back to these
-    Shift   = 0x10,
-    Control = 0x11,
-    Menu    = 0x12
+    /// Kana mode on Japanese keyboards, or Hangul mode on Korean keyboards
+    Kana,
+    Junja,
+    Final,
+    /// Kanji mode on Japanese keyboards, or Hanja mode on Korean keyboards
+    Kanji,
+    Convert,
+    Nonconvert,
+    Accept,
+    ModeChange,
+    /// Sent by Windows in place of keys that an IME is handling
+    Process,
+
+    // Keys from IBM 3270 terminal keyboards
+    Attn,
+    CrSel,
+    ExSel,
+    /// Erase to end of field
+    EraseEof,
+    Play,
+    Zoom,
+    Pa1,
+    OemClear
 }
 
-
-impl FromPrimitive for VKeyCode {
-    fn from_i64(n: i64) -> Option<VKeyCode> {
+impl FromPrimitive for Key {
+    fn from_i64(n: i64) -> Option<Key> {
         if 0 <= n {
-            VKeyCode::from_u64(n as u64)
+            Key::from_u64(n as u64)
         }
 
         else {
@@ -267,146 +469,200 @@ impl FromPrimitive for VKeyCode {
         }
     }
 
-    fn from_u64(n: u64) -> Option<VKeyCode> {
-        match n {
-            0x08    => Some(VKeyCode::Back   ),
-            0x09    => Some(VKeyCode::Tab    ),
-            0x0C    => Some(VKeyCode::Clear  ),
-            0x0D    => Some(VKeyCode::Enter  ),
-            0x13    => Some(VKeyCode::Pause  ),
-            0x1B    => Some(VKeyCode::Escape ),
-            0x20    => Some(VKeyCode::Space  ),
-            0x21    => Some(VKeyCode::PgUp   ),
-            0x22    => Some(VKeyCode::PgDn   ),
-            0x23    => Some(VKeyCode::End    ),
-            0x24    => Some(VKeyCode::Home   ),
-            0x29    => Some(VKeyCode::Select ),
-            0x2A    => Some(VKeyCode::Print  ),
-            0x2B    => Some(VKeyCode::Execute),
-            0x2C    => Some(VKeyCode::PrntScr),
-            0x2D    => Some(VKeyCode::Insert ),
-            0x2E    => Some(VKeyCode::Delete ),
-            0x2F    => Some(VKeyCode::Help   ),
-            0x30    => Some(VKeyCode::Key0   ),
-            0x31    => Some(VKeyCode::Key1   ),
-            0x32    => Some(VKeyCode::Key2   ),
-            0x33    => Some(VKeyCode::Key3   ),
-            0x34    => Some(VKeyCode::Key4   ),
-            0x35    => Some(VKeyCode::Key5   ),
-            0x36    => Some(VKeyCode::Key6   ),
-            0x37    => Some(VKeyCode::Key7   ),
-            0x38    => Some(VKeyCode::Key8   ),
-            0x39    => Some(VKeyCode::Key9   ),
-
-            0x41    => Some(VKeyCode::A),
-            0x42    => Some(VKeyCode::B),
-            0x43    => Some(VKeyCode::C),
-            0x44    => Some(VKeyCode::D),
-            0x45    => Some(VKeyCode::E),
-            0x46    => Some(VKeyCode::F),
-            0x47    => Some(VKeyCode::G),
-            0x48    => Some(VKeyCode::H),
-            0x49    => Some(VKeyCode::I),
-            0x4a    => Some(VKeyCode::J),
-            0x4b    => Some(VKeyCode::K),
-            0x4c    => Some(VKeyCode::L),
-            0x4d    => Some(VKeyCode::M),
-            0x4e    => Some(VKeyCode::N),
-            0x4f    => Some(VKeyCode::O),
-            0x50    => Some(VKeyCode::P),
-            0x51    => Some(VKeyCode::Q),
-            0x52    => Some(VKeyCode::R),
-            0x53    => Some(VKeyCode::S),
-            0x54    => Some(VKeyCode::T),
-            0x55    => Some(VKeyCode::U),
-            0x56    => Some(VKeyCode::V),
-            0x57    => Some(VKeyCode::W),
-            0x58    => Some(VKeyCode::X),
-            0x59    => Some(VKeyCode::Y),
-            0x5a    => Some(VKeyCode::Z),
-
-            0xBA    => Some(VKeyCode::Semi    ),
-            0xBB    => Some(VKeyCode::Plus    ),
-            0xBC    => Some(VKeyCode::Comma   ),
-            0xBD    => Some(VKeyCode::Minus   ),
-            0xBE    => Some(VKeyCode::Dot     ),
-            0xBF    => Some(VKeyCode::Slash   ),
-            0xC0    => Some(VKeyCode::Tilde   ),
-            0xDB    => Some(VKeyCode::LBrac   ),
-            0xDD    => Some(VKeyCode::RBrac   ),
-            0xDC    => Some(VKeyCode::Pipe    ),
-            0xDE    => Some(VKeyCode::Quote   ),
-            0x5F    => Some(VKeyCode::Sleep   ),
-            0x60    => Some(VKeyCode::Num0    ),
-            0x61    => Some(VKeyCode::Num1    ),
-            0x62    => Some(VKeyCode::Num2    ),
-            0x63    => Some(VKeyCode::Num3    ),
-            0x64    => Some(VKeyCode::Num4    ),
-            0x65    => Some(VKeyCode::Num5    ),
-            0x66    => Some(VKeyCode::Num6    ),
-            0x67    => Some(VKeyCode::Num7    ),
-            0x68    => Some(VKeyCode::Num8    ),
-            0x69    => Some(VKeyCode::Num9    ),
-            0x6A    => Some(VKeyCode::NumStar ),
-            0x6B    => Some(VKeyCode::NumPlus ),
-            0x6D    => Some(VKeyCode::NumSub  ),
-            0x6E    => Some(VKeyCode::NumDot  ),
-            0x6F    => Some(VKeyCode::NumSlash),
-            0x70    => Some(VKeyCode::F1      ),
-            0x71    => Some(VKeyCode::F2      ),
-            0x72    => Some(VKeyCode::F3      ),
-            0x73    => Some(VKeyCode::F4      ),
-            0x74    => Some(VKeyCode::F5      ),
-            0x75    => Some(VKeyCode::F6      ),
-            0x76    => Some(VKeyCode::F7      ),
-            0x77    => Some(VKeyCode::F8      ),
-            0x78    => Some(VKeyCode::F9      ),
-            0x79    => Some(VKeyCode::F10     ),
-            0x7A    => Some(VKeyCode::F11     ),
-            0x7B    => Some(VKeyCode::F12     ),
-            0x7C    => Some(VKeyCode::F13     ),
-            0x7D    => Some(VKeyCode::F14     ),
-            0x7E    => Some(VKeyCode::F15     ),
-            0x7F    => Some(VKeyCode::F16     ),
-            0x80    => Some(VKeyCode::F17     ),
-            0x81    => Some(VKeyCode::F18     ),
-            0x82    => Some(VKeyCode::F19     ),
-            0x83    => Some(VKeyCode::F20     ),
-            0x84    => Some(VKeyCode::F21     ),
-            0x85    => Some(VKeyCode::F22     ),
-            0x86    => Some(VKeyCode::F23     ),
-            0x87    => Some(VKeyCode::F24     ),
-            0x90    => Some(VKeyCode::Num     ),
-            0x14    => Some(VKeyCode::Caps    ),
-            0x91    => Some(VKeyCode::Scroll  ),
-            0xA0    => Some(VKeyCode::LShift  ),
-            0xA1    => Some(VKeyCode::RShift  ),
-            0xA2    => Some(VKeyCode::LCtrl   ),
-            0xA3    => Some(VKeyCode::RCtrl   ),
-            0xA4    => Some(VKeyCode::LAlt    ),
-            0xA5    => Some(VKeyCode::RAlt    ),
-            0xA6    => Some(VKeyCode::BBack   ),
-            0xA7    => Some(VKeyCode::BFwd    ),
-            0xA8    => Some(VKeyCode::BRef    ),
-            0xA9    => Some(VKeyCode::BStop   ),
-            0xAA    => Some(VKeyCode::BSearch ),
-            0xAB    => Some(VKeyCode::BFav    ),
-            0xAC    => Some(VKeyCode::BHome   ),
-            0xB0    => Some(VKeyCode::MNTrack ),
-            0xB1    => Some(VKeyCode::MPTrack ),
-            0xB2    => Some(VKeyCode::MStop   ),
-            0xB3    => Some(VKeyCode::MPause  ),
-            0x25    => Some(VKeyCode::LArrow  ),
-            0x26    => Some(VKeyCode::UArrow  ),
-            0x27    => Some(VKeyCode::RArrow  ),
-            0x28    => Some(VKeyCode::DArrow  ),
-            0x15    => Some(VKeyCode::Kana    ),
-            0x17    => Some(VKeyCode::Junja   ),
-            0x18    => Some(VKeyCode::Final   ),
-            0x19    => Some(VKeyCode::Kanji   ),
-            0x1C    => Some(VKeyCode::Convert ),
-
-            _ => None
+    fn from_u64(n: u64) -> Option<Key> {
+        use std::mem;
+
+        // OemClear is the last key
+        if n <= Key::OemClear as u64 {
+            Some(unsafe{ mem::transmute(n as u8) })
+        }
+
+        else {
+            None
         }
     }
 }
+
+/// Identifies a key by where it is on the keyboard, regardless of the keyboard layout. This is what
+/// controls like WASD should be bound to, so that they stay in the same place on AZERTY or Dvorak
+/// keyboards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanCode {
+    /// The scancode that the OS reported. On win32 this is the set 1 scancode with `0xE000` added
+    /// for extended keys, and on Linux it's the evdev keycode.
+    pub raw: u32,
+    /// The key at the scancode's position, or `None` if the scancode isn't one that tub knows about
+    pub key: Option<PhysicalKey>
+}
+
+/// A key on the keyboard, named after what it is on a US keyboard. The discriminants are the key's
+/// usage ID on the USB HID keyboard page.
+#[repr(u16)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PhysicalKey {
+    A       = 0x04,
+    B       = 0x05,
+    C       = 0x06,
+    D       = 0x07,
+    E       = 0x08,
+    F       = 0x09,
+    G       = 0x0A,
+    H       = 0x0B,
+    I       = 0x0C,
+    J       = 0x0D,
+    K       = 0x0E,
+    L       = 0x0F,
+    M       = 0x10,
+    N       = 0x11,
+    O       = 0x12,
+    P       = 0x13,
+    Q       = 0x14,
+    R       = 0x15,
+    S       = 0x16,
+    T       = 0x17,
+    U       = 0x18,
+    V       = 0x19,
+    W       = 0x1A,
+    X       = 0x1B,
+    Y       = 0x1C,
+    Z       = 0x1D,
+
+    Key1    = 0x1E,
+    Key2    = 0x1F,
+    Key3    = 0x20,
+    Key4    = 0x21,
+    Key5    = 0x22,
+    Key6    = 0x23,
+    Key7    = 0x24,
+    Key8    = 0x25,
+    Key9    = 0x26,
+    Key0    = 0x27,
+
+    Enter   = 0x28,
+    Escape  = 0x29,
+    /// Backspace
+    Back    = 0x2A,
+    Tab     = 0x2B,
+    Space   = 0x2C,
+    Minus   = 0x2D,
+    /// '=+'
+    Equal   = 0x2E,
+    /// '[{'
+    LBrac   = 0x2F,
+    /// ']}'
+    RBrac   = 0x30,
+    /// '\|'
+    Pipe    = 0x31,
+    /// The key next to Enter on ISO keyboards, which is '#~' on UK keyboards
+    NonUsHash   = 0x32,
+    /// ';:'
+    Semi    = 0x33,
+    /// `'"`
+    Quote   = 0x34,
+    /// '`~'
+    Tilde   = 0x35,
+    Comma   = 0x36,
+    /// The period key
+    Dot     = 0x37,
+    /// '/?'
+    Slash   = 0x38,
+    Caps    = 0x39,
+
+    F1      = 0x3A,
+    F2      = 0x3B,
+    F3      = 0x3C,
+    F4      = 0x3D,
+    F5      = 0x3E,
+    F6      = 0x3F,
+    F7      = 0x40,
+    F8      = 0x41,
+    F9      = 0x42,
+    F10     = 0x43,
+    F11     = 0x44,
+    F12     = 0x45,
+
+    PrntScr = 0x46,
+    Scroll  = 0x47,
+    Pause   = 0x48,
+    Insert  = 0x49,
+    Home    = 0x4A,
+    PgUp    = 0x4B,
+    Delete  = 0x4C,
+    End     = 0x4D,
+    PgDn    = 0x4E,
+    RArrow  = 0x4F,
+    LArrow  = 0x50,
+    DArrow  = 0x51,
+    UArrow  = 0x52,
+
+    Num     = 0x53,
+    NumSlash= 0x54,
+    NumStar = 0x55,
+    NumSub  = 0x56,
+    NumPlus = 0x57,
+    NumEnter= 0x58,
+    Num1    = 0x59,
+    Num2    = 0x5A,
+    Num3    = 0x5B,
+    Num4    = 0x5C,
+    Num5    = 0x5D,
+    Num6    = 0x5E,
+    Num7    = 0x5F,
+    Num8    = 0x60,
+    Num9    = 0x61,
+    Num0    = 0x62,
+    NumDot  = 0x63,
+
+    /// The key between left shift and Z on ISO keyboards
+    NonUsBackslash  = 0x64,
+    /// The context menu key
+    Apps    = 0x65,
+    Power   = 0x66,
+    NumEqual= 0x67,
+
+    F13     = 0x68,
+    F14     = 0x69,
+    F15     = 0x6A,
+    F16     = 0x6B,
+    F17     = 0x6C,
+    F18     = 0x6D,
+    F19     = 0x6E,
+    F20     = 0x6F,
+    F21     = 0x70,
+    F22     = 0x71,
+    F23     = 0x72,
+    F24     = 0x73,
+
+    Help    = 0x75,
+    Mute    = 0x7F,
+    VolUp   = 0x80,
+    VolDown = 0x81,
+    /// The comma on Brazilian numpads
+    NumComma= 0x85,
+
+    /// '\_' on Japanese keyboards
+    IntlRo  = 0x87,
+    /// Katakana/Hiragana on Japanese keyboards
+    Kana    = 0x88,
+    /// '¥' on Japanese keyboards
+    IntlYen = 0x89,
+    /// Henkan on Japanese keyboards
+    Convert = 0x8A,
+    /// Muhenkan on Japanese keyboards
+    NonConvert  = 0x8B,
+    /// Han/Yeong on Korean keyboards
+    Lang1   = 0x90,
+    /// Hanja on Korean keyboards
+    Lang2   = 0x91,
+
+    LCtrl   = 0xE0,
+    LShift  = 0xE1,
+    LAlt    = 0xE2,
+    /// The left Windows key, or the left Command key on Apple keyboards
+    LWin    = 0xE3,
+    RCtrl   = 0xE4,
+    RShift  = 0xE5,
+    RAlt    = 0xE6,
+    /// The right Windows key, or the right Command key on Apple keyboards
+    RWin    = 0xE7
+}
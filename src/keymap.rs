@@ -0,0 +1,445 @@
+//! Conversions between `event::Key` and the key codes that each platform uses. Backends use these
+//! to translate key events, but they're also useful for talking to platform APIs directly.
+//!
+//! Every key converts to a platform code and back to itself:
+//!
+//! ```
+//! # extern crate num;
+//! # extern crate tub;
+//! # fn main() {
+//! use num::FromPrimitive;
+//! use tub::event::Key;
+//! use tub::keymap::*;
+//!
+//! let keys: Vec<Key> = (0..).map(Key::from_u64).take_while(|k| k.is_some()).map(|k| k.unwrap()).collect();
+//!
+//! for &key in &keys {
+//!     assert_eq!(vk_from_key(key).and_then(key_from_vk), Some(key));
+//!     if let Some(keysym) = keysym_from_key(key) {
+//!         assert_eq!(key_from_keysym(keysym), Some(key));
+//!     }
+//! }
+//!
+//! // Every key has a virtual key code, and these are the only ones without a keysym.
+//! let no_keysym: Vec<Key> = keys.iter().cloned().filter(|&k| keysym_from_key(k).is_none()).collect();
+//! assert_eq!(no_keysym, [Key::Print, Key::Oem8, Key::Oem102, Key::Final, Key::Accept, Key::Process, Key::Zoom, Key::OemClear]);
+//! # }
+//! ```
+//!
+//! And every platform code converts to a key that converts back to a code for the same key, even
+//! where several codes mean the same key:
+//!
+//! ```
+//! # use tub::keymap::*;
+//! for &(vk, key) in VK_TABLE {
+//!     assert_eq!(key_from_vk(vk), Some(key));
+//!     assert_eq!(vk_from_key(key).and_then(key_from_vk), Some(key));
+//! }
+//! for &(keysym, key) in KEYSYM_TABLE {
+//!     assert_eq!(key_from_keysym(keysym), Some(key));
+//!     assert_eq!(keysym_from_key(key).and_then(key_from_keysym), Some(key));
+//! }
+//! ```
+
+use event::Key;
+use event::Key::*;
+
+/// Win32 virtual key codes and the keys they mean. The generic `VK_SHIFT`, `VK_CONTROL` and
+/// `VK_MENU` codes aren't here, since which side of the keyboard they're from has to be worked out
+/// from the scancode.
+pub static VK_TABLE: &'static [(u32, Key)] = &[
+    (0x08, Back    ),
+    (0x09, Tab     ),
+    (0x0C, Clear   ),
+    (0x0D, Enter   ),
+    (0x13, Pause   ),
+    (0x1B, Escape  ),
+    (0x20, Space   ),
+    (0x21, PgUp    ),
+    (0x22, PgDn    ),
+    (0x23, End     ),
+    (0x24, Home    ),
+    (0x29, Select  ),
+    (0x2A, Print   ),
+    (0x2B, Execute ),
+    (0x2C, PrntScr ),
+    (0x2D, Insert  ),
+    (0x2E, Delete  ),
+    (0x2F, Help    ),
+
+    (0x30, Key0),
+    (0x31, Key1),
+    (0x32, Key2),
+    (0x33, Key3),
+    (0x34, Key4),
+    (0x35, Key5),
+    (0x36, Key6),
+    (0x37, Key7),
+    (0x38, Key8),
+    (0x39, Key9),
+
+    (0x41, A),
+    (0x42, B),
+    (0x43, C),
+    (0x44, D),
+    (0x45, E),
+    (0x46, F),
+    (0x47, G),
+    (0x48, H),
+    (0x49, I),
+    (0x4A, J),
+    (0x4B, K),
+    (0x4C, L),
+    (0x4D, M),
+    (0x4E, N),
+    (0x4F, O),
+    (0x50, P),
+    (0x51, Q),
+    (0x52, R),
+    (0x53, S),
+    (0x54, T),
+    (0x55, U),
+    (0x56, V),
+    (0x57, W),
+    (0x58, X),
+    (0x59, Y),
+    (0x5A, Z),
+
+    (0xBA, Semi    ),
+    (0xBB, Plus    ),
+    (0xBC, Comma   ),
+    (0xBD, Minus   ),
+    (0xBE, Dot     ),
+    (0xBF, Slash   ),
+    (0xC0, Tilde   ),
+    (0xDB, LBrac   ),
+    (0xDD, RBrac   ),
+    (0xDC, Pipe    ),
+    (0xDE, Quote   ),
+    (0xDF, Oem8    ),
+    (0xE2, Oem102  ),
+
+    (0x5B, LWin    ),
+    (0x5C, RWin    ),
+    (0x5D, Apps    ),
+    (0x5F, Sleep   ),
+
+    (0x60, Num0    ),
+    (0x61, Num1    ),
+    (0x62, Num2    ),
+    (0x63, Num3    ),
+    (0x64, Num4    ),
+    (0x65, Num5    ),
+    (0x66, Num6    ),
+    (0x67, Num7    ),
+    (0x68, Num8    ),
+    (0x69, Num9    ),
+    (0x6A, NumStar ),
+    (0x6B, NumPlus ),
+    (0x6C, NumSeparator),
+    (0x6D, NumSub  ),
+    (0x6E, NumDot  ),
+    (0x6F, NumSlash),
+
+    (0x70, F1      ),
+    (0x71, F2      ),
+    (0x72, F3      ),
+    (0x73, F4      ),
+    (0x74, F5      ),
+    (0x75, F6      ),
+    (0x76, F7      ),
+    (0x77, F8      ),
+    (0x78, F9      ),
+    (0x79, F10     ),
+    (0x7A, F11     ),
+    (0x7B, F12     ),
+    (0x7C, F13     ),
+    (0x7D, F14     ),
+    (0x7E, F15     ),
+    (0x7F, F16     ),
+    (0x80, F17     ),
+    (0x81, F18     ),
+    (0x82, F19     ),
+    (0x83, F20     ),
+    (0x84, F21     ),
+    (0x85, F22     ),
+    (0x86, F23     ),
+    (0x87, F24     ),
+
+    (0x90, Num     ),
+    (0x14, Caps    ),
+    (0x91, Scroll  ),
+
+    (0xA0, LShift  ),
+    (0xA1, RShift  ),
+    (0xA2, LCtrl   ),
+    (0xA3, RCtrl   ),
+    (0xA4, LAlt    ),
+    (0xA5, RAlt    ),
+
+    (0xA6, BBack   ),
+    (0xA7, BFwd    ),
+    (0xA8, BRef    ),
+    (0xA9, BStop   ),
+    (0xAA, BSearch ),
+    (0xAB, BFav    ),
+    (0xAC, BHome   ),
+
+    (0xAD, VolMute ),
+    (0xAE, VolDown ),
+    (0xAF, VolUp   ),
+    (0xB0, MNTrack ),
+    (0xB1, MPTrack ),
+    (0xB2, MStop   ),
+    (0xB3, MPause  ),
+    (0xB4, Mail    ),
+    (0xB5, MSelect ),
+    (0xB6, App1    ),
+    (0xB7, App2    ),
+
+    (0x25, LArrow  ),
+    (0x26, UArrow  ),
+    (0x27, RArrow  ),
+    (0x28, DArrow  ),
+
+    (0x15, Kana    ),
+    (0x17, Junja   ),
+    (0x18, Final   ),
+    (0x19, Kanji   ),
+    (0x1C, Convert ),
+    (0x1D, Nonconvert),
+    (0x1E, Accept  ),
+    (0x1F, ModeChange),
+    (0xE5, Process ),
+
+    (0xF6, Attn    ),
+    (0xF7, CrSel   ),
+    (0xF8, ExSel   ),
+    (0xF9, EraseEof),
+    (0xFA, Play    ),
+    (0xFB, Zoom    ),
+    (0xFD, Pa1     ),
+    (0xFE, OemClear)
+];
+
+/// X11 keysyms and the keys they mean. xkbcommon's `xkb_keysym_t` uses the same values, so this is
+/// used by both the X11 and Wayland backends. Letters are lowercase, since keys are looked up by
+/// their unshifted keysym.
+///
+/// Where a key has several keysyms, the first one listed is the one that `keysym_from_key` gives.
+pub static KEYSYM_TABLE: &'static [(u32, Key)] = &[
+    (0xFF08, Back    ), // XK_BackSpace
+    (0xFF09, Tab     ), // XK_Tab
+    (0xFF0B, Clear   ), // XK_Clear
+    (0xFF0D, Enter   ), // XK_Return
+    (0xFF13, Pause   ), // XK_Pause
+    (0xFF1B, Escape  ), // XK_Escape
+    (0x0020, Space   ), // XK_space
+    (0xFF55, PgUp    ), // XK_Prior
+    (0xFF56, PgDn    ), // XK_Next
+    (0xFF57, End     ), // XK_End
+    (0xFF50, Home    ), // XK_Home
+    (0xFF60, Select  ), // XK_Select
+    (0xFF62, Execute ), // XK_Execute
+    (0xFF61, PrntScr ), // XK_Print
+    (0xFF63, Insert  ), // XK_Insert
+    (0xFFFF, Delete  ), // XK_Delete
+    (0xFF6A, Help    ), // XK_Help
+
+    (0x0030, Key0),
+    (0x0031, Key1),
+    (0x0032, Key2),
+    (0x0033, Key3),
+    (0x0034, Key4),
+    (0x0035, Key5),
+    (0x0036, Key6),
+    (0x0037, Key7),
+    (0x0038, Key8),
+    (0x0039, Key9),
+
+    (0x0061, A),
+    (0x0062, B),
+    (0x0063, C),
+    (0x0064, D),
+    (0x0065, E),
+    (0x0066, F),
+    (0x0067, G),
+    (0x0068, H),
+    (0x0069, I),
+    (0x006A, J),
+    (0x006B, K),
+    (0x006C, L),
+    (0x006D, M),
+    (0x006E, N),
+    (0x006F, O),
+    (0x0070, P),
+    (0x0071, Q),
+    (0x0072, R),
+    (0x0073, S),
+    (0x0074, T),
+    (0x0075, U),
+    (0x0076, V),
+    (0x0077, W),
+    (0x0078, X),
+    (0x0079, Y),
+    (0x007A, Z),
+
+    (0x003B, Semi    ), // XK_semicolon
+    (0x003D, Plus    ), // XK_equal
+    (0x002C, Comma   ), // XK_comma
+    (0x002D, Minus   ), // XK_minus
+    (0x002E, Dot     ), // XK_period
+    (0x002F, Slash   ), // XK_slash
+    (0x0060, Tilde   ), // XK_grave
+    (0x005B, LBrac   ), // XK_bracketleft
+    (0x005D, RBrac   ), // XK_bracketright
+    (0x005C, Pipe    ), // XK_backslash
+    (0x0027, Quote   ), // XK_apostrophe
+
+    (0xFFEB, LWin    ), // XK_Super_L
+    (0xFFEC, RWin    ), // XK_Super_R
+    (0xFF67, Apps    ), // XK_Menu
+    (0x1008FF2F, Sleep), // XF86XK_Sleep
+
+    (0xFFB0, Num0    ), // XK_KP_0
+    (0xFFB1, Num1    ),
+    (0xFFB2, Num2    ),
+    (0xFFB3, Num3    ),
+    (0xFFB4, Num4    ),
+    (0xFFB5, Num5    ),
+    (0xFFB6, Num6    ),
+    (0xFFB7, Num7    ),
+    (0xFFB8, Num8    ),
+    (0xFFB9, Num9    ), // XK_KP_9
+    (0xFFAA, NumStar ), // XK_KP_Multiply
+    (0xFFAB, NumPlus ), // XK_KP_Add
+    (0xFFAC, NumSeparator), // XK_KP_Separator
+    (0xFFAD, NumSub  ), // XK_KP_Subtract
+    (0xFFAE, NumDot  ), // XK_KP_Decimal
+    (0xFFAF, NumSlash), // XK_KP_Divide
+    (0xFF8D, Enter   ), // XK_KP_Enter
+
+    // With num lock off, the numpad sends the navigation keys
+    (0xFF9E, Insert  ), // XK_KP_Insert
+    (0xFF9F, Delete  ), // XK_KP_Delete
+    (0xFF9C, End     ), // XK_KP_End
+    (0xFF99, DArrow  ), // XK_KP_Down
+    (0xFF9B, PgDn    ), // XK_KP_Next
+    (0xFF96, LArrow  ), // XK_KP_Left
+    (0xFF9D, Clear   ), // XK_KP_Begin
+    (0xFF98, RArrow  ), // XK_KP_Right
+    (0xFF95, Home    ), // XK_KP_Home
+    (0xFF97, UArrow  ), // XK_KP_Up
+    (0xFF9A, PgUp    ), // XK_KP_Prior
+
+    (0xFFBE, F1      ),
+    (0xFFBF, F2      ),
+    (0xFFC0, F3      ),
+    (0xFFC1, F4      ),
+    (0xFFC2, F5      ),
+    (0xFFC3, F6      ),
+    (0xFFC4, F7      ),
+    (0xFFC5, F8      ),
+    (0xFFC6, F9      ),
+    (0xFFC7, F10     ),
+    (0xFFC8, F11     ),
+    (0xFFC9, F12     ),
+    (0xFFCA, F13     ),
+    (0xFFCB, F14     ),
+    (0xFFCC, F15     ),
+    (0xFFCD, F16     ),
+    (0xFFCE, F17     ),
+    (0xFFCF, F18     ),
+    (0xFFD0, F19     ),
+    (0xFFD1, F20     ),
+    (0xFFD2, F21     ),
+    (0xFFD3, F22     ),
+    (0xFFD4, F23     ),
+    (0xFFD5, F24     ),
+
+    (0xFF7F, Num     ), // XK_Num_Lock
+    (0xFFE5, Caps    ), // XK_Caps_Lock
+    (0xFF14, Scroll  ), // XK_Scroll_Lock
+
+    (0xFFE1, LShift  ), // XK_Shift_L
+    (0xFFE2, RShift  ), // XK_Shift_R
+    (0xFFE3, LCtrl   ), // XK_Control_L
+    (0xFFE4, RCtrl   ), // XK_Control_R
+    (0xFFE9, LAlt    ), // XK_Alt_L
+    (0xFFEA, RAlt    ), // XK_Alt_R
+    // AltGr on international layouts
+    (0xFE03, RAlt    ), // XK_ISO_Level3_Shift
+
+    (0x1008FF26, BBack  ), // XF86XK_Back
+    (0x1008FF27, BFwd   ), // XF86XK_Forward
+    (0x1008FF29, BRef   ), // XF86XK_Refresh
+    (0x1008FF28, BStop  ), // XF86XK_Stop
+    (0x1008FF1B, BSearch), // XF86XK_Search
+    (0x1008FF30, BFav   ), // XF86XK_Favorites
+    (0x1008FF18, BHome  ), // XF86XK_HomePage
+
+    (0x1008FF12, VolMute), // XF86XK_AudioMute
+    (0x1008FF11, VolDown), // XF86XK_AudioLowerVolume
+    (0x1008FF13, VolUp  ), // XF86XK_AudioRaiseVolume
+    (0x1008FF17, MNTrack), // XF86XK_AudioNext
+    (0x1008FF16, MPTrack), // XF86XK_AudioPrev
+    (0x1008FF15, MStop  ), // XF86XK_AudioStop
+    (0x1008FF14, MPause ), // XF86XK_AudioPlay
+    (0x1008FF31, MPause ), // XF86XK_AudioPause
+    (0x1008FF19, Mail   ), // XF86XK_Mail
+    (0x1008FF32, MSelect), // XF86XK_AudioMedia
+    (0x1008FF33, App1   ), // XF86XK_MyComputer
+    (0x1008FF1D, App2   ), // XF86XK_Calculator
+
+    (0xFF51, LArrow  ), // XK_Left
+    (0xFF52, UArrow  ), // XK_Up
+    (0xFF53, RArrow  ), // XK_Right
+    (0xFF54, DArrow  ), // XK_Down
+
+    // Hangul and Hanja share the Kana and Kanji keys, like they share virtual key codes
+    (0xFF2E, Kana    ), // XK_Kana_Shift
+    (0xFF31, Kana    ), // XK_Hangul
+    (0xFF38, Junja   ), // XK_Hangul_Jeonja
+    (0xFF21, Kanji   ), // XK_Kanji
+    (0xFF34, Kanji   ), // XK_Hangul_Hanja
+    (0xFF23, Convert ), // XK_Henkan
+    (0xFF22, Nonconvert), // XK_Muhenkan
+    (0xFF7E, ModeChange), // XK_Mode_switch
+
+    (0xFD0E, Attn    ), // XK_3270_Attn
+    (0xFD1C, CrSel   ), // XK_3270_CursorSelect
+    (0xFD1B, ExSel   ), // XK_3270_ExSelect
+    (0xFD06, EraseEof), // XK_3270_EraseEOF
+    (0xFD16, Play    ), // XK_3270_Play
+    (0xFD0A, Pa1     )  // XK_3270_PA1
+];
+
+/// Get the key for a win32 virtual key code
+pub fn key_from_vk(vk: u32) -> Option<Key> {
+    find_key(VK_TABLE, vk)
+}
+
+/// Get the win32 virtual key code for a key. Every key has one.
+pub fn vk_from_key(key: Key) -> Option<u32> {
+    find_code(VK_TABLE, key)
+}
+
+/// Get the key for an X11 or xkbcommon keysym
+pub fn key_from_keysym(keysym: u32) -> Option<Key> {
+    find_key(KEYSYM_TABLE, keysym)
+}
+
+/// Get the X11 or xkbcommon keysym for a key, if it has one
+pub fn keysym_from_key(key: Key) -> Option<u32> {
+    find_code(KEYSYM_TABLE, key)
+}
+
+#[inline]
+fn find_key(table: &[(u32, Key)], code: u32) -> Option<Key> {
+    table.iter().find(|&&(c, _)| c == code).map(|&(_, k)| k)
+}
+
+#[inline]
+fn find_code(table: &[(u32, Key)], key: Key) -> Option<u32> {
+    table.iter().find(|&&(_, k)| k == key).map(|&(c, _)| c)
+}
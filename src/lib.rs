@@ -1,23 +1,45 @@
 extern crate num;
 extern crate rand;
+#[cfg(all(windows, feature = "win32"))]
 extern crate winapi;
+#[cfg(all(windows, feature = "win32"))]
 extern crate user32;
+#[cfg(all(windows, feature = "win32"))]
 extern crate gdi32;
+#[cfg(all(windows, feature = "win32"))]
 extern crate kernel32;
+#[cfg(all(windows, feature = "win32"))]
 extern crate dwmapi;
+#[cfg(target_os = "linux")]
+extern crate libc;
+#[cfg(all(target_os = "linux", feature = "x11"))]
+extern crate x11;
+#[cfg(all(target_os = "linux", feature = "wayland"))]
+extern crate wayland_client;
+#[cfg(all(target_os = "linux", feature = "wayland"))]
+extern crate wayland_protocols;
+#[cfg(all(target_os = "linux", feature = "wayland"))]
+extern crate wayland_cursor;
 
 mod api;
 pub mod platform;
 pub mod event;
+pub mod keymap;
 pub mod config;
 pub mod error;
 
 use platform::Window;
 
+use error::{TubResult, TubError};
+
 use std::fmt;
 use std::ops::Deref;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT, Ordering};
 
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CursorType {
     AppStarting,
     Arrow,
@@ -36,6 +58,197 @@ pub enum CursorType {
     Invisible
 }
 
+/// A cursor made out of an image. Cloning a cursor is cheap, and backends only create the native
+/// cursor once for every window that a cursor is used in.
+#[derive(Clone)]
+pub struct CustomCursor {
+    id: usize,
+    width: u32,
+    height: u32,
+    hotspot: (u32, u32),
+    /// The pixels in ARGB order without premultiplied alpha, going from the top row to the bottom row
+    pixels: Arc<Vec<u32>>
+}
+
+static NEXT_CURSOR_ID: AtomicUsize = ATOMIC_USIZE_INIT;
+
+/// The size of `.cur` images that Windows uses by default, which is picked when a file has several
+const CUR_SIZE: u32 = 32;
+/// The size of Xcursor images that libXcursor uses when `XCURSOR_SIZE` isn't set
+const XCURSOR_SIZE: u32 = 24;
+
+impl CustomCursor {
+    /// Make a cursor out of RGBA pixels without premultiplied alpha, going from the top row to the
+    /// bottom row. Returns `None` if `rgba` isn't `width * height * 4` bytes long, the image is
+    /// empty or the hotspot isn't in the image.
+    ///
+    /// ```
+    /// use tub::CustomCursor;
+    ///
+    /// let pixels = vec![255; 16 * 16 * 4];
+    /// let cursor = CustomCursor::from_rgba(16, 16, &pixels, (8, 8)).unwrap();
+    /// assert_eq!(cursor.hotspot(), (8, 8));
+    ///
+    /// assert!(CustomCursor::from_rgba(16, 16, &pixels[4..], (8, 8)).is_none());
+    /// assert!(CustomCursor::from_rgba(16, 16, &pixels, (16, 0)).is_none());
+    /// ```
+    pub fn from_rgba(width: u32, height: u32, rgba: &[u8], hotspot: (u32, u32)) -> Option<CustomCursor> {
+        if rgba.len() != width as usize * height as usize * 4 {
+            return None;
+        }
+
+        let pixels = rgba.chunks(4).map(|p| (p[3] as u32) << 24 | (p[0] as u32) << 16 | (p[1] as u32) << 8 | p[2] as u32);
+        CustomCursor::from_argb(width, height, pixels.collect(), hotspot)
+    }
+
+    /// Load a cursor from a Windows `.cur` file. If the file has several images, the one closest
+    /// to the size that Windows uses by default is picked. Like with window icons, only uncompressed
+    /// 32-bit images can be loaded.
+    pub fn from_cur_file<P: AsRef<Path>>(path: P) -> TubResult<CustomCursor> {
+        let path = path.as_ref();
+        let image = api::ico::load_ico(path).and_then(|images|
+            images.into_iter().min_by_key(|i| (i.width as i64 - CUR_SIZE as i64).abs()));
+
+        match image {
+            Some(i) => CustomCursor::from_argb(i.width, i.height, i.pixels, i.hotspot),
+            None    => None
+        }.ok_or(TubError::CursorLoadError(path.to_owned()))
+    }
+
+    /// Load a cursor from an Xcursor file, like the ones in X11 cursor themes. If the file has
+    /// several sizes, the one closest to libXcursor's default size is picked, and animated cursors
+    /// only get their first frame.
+    pub fn from_xcursor_file<P: AsRef<Path>>(path: P) -> TubResult<CustomCursor> {
+        let path = path.as_ref();
+        let image = api::xcursor::load_xcursor(path).and_then(|images|
+            images.into_iter().min_by_key(|i| (i.nominal_size as i64 - XCURSOR_SIZE as i64).abs()));
+
+        match image {
+            Some(i) => CustomCursor::from_argb(i.width, i.height, i.pixels, i.hotspot),
+            None    => None
+        }.ok_or(TubError::CursorLoadError(path.to_owned()))
+    }
+
+    fn from_argb(width: u32, height: u32, pixels: Vec<u32>, hotspot: (u32, u32)) -> Option<CustomCursor> {
+        if width == 0 || height == 0 || hotspot.0 >= width || hotspot.1 >= height {
+            return None;
+        }
+
+        Some(
+            CustomCursor {
+                id: NEXT_CURSOR_ID.fetch_add(1, Ordering::Relaxed),
+                width: width,
+                height: height,
+                hotspot: hotspot,
+                pixels: Arc::new(pixels)
+            }
+        )
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[inline]
+    pub fn hotspot(&self) -> (u32, u32) {
+        self.hotspot
+    }
+
+    /// Get the cursor's pixels in ARGB order without premultiplied alpha, going from the top row to
+    /// the bottom row
+    #[inline]
+    pub fn pixels(&self) -> &[u32] {
+        &self.pixels
+    }
+
+    /// Get a number that's shared by this cursor and its clones, and by no other cursor. Backends
+    /// use this to look up the native cursors that they've already made.
+    #[inline]
+    #[cfg(any(all(windows, feature = "win32"), all(target_os = "linux", any(feature = "x11", feature = "wayland"))))]
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl PartialEq for CustomCursor {
+    fn eq(&self, other: &CustomCursor) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for CustomCursor {}
+
+impl fmt::Debug for CustomCursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CustomCursor")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("hotspot", &self.hotspot)
+            .finish()
+    }
+}
+
+/// A cursor that can be shown over a window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cursor {
+    System(CursorType),
+    Custom(CustomCursor)
+}
+
+impl From<CursorType> for Cursor {
+    #[inline]
+    fn from(cursor_type: CursorType) -> Cursor {
+        Cursor::System(cursor_type)
+    }
+}
+
+impl From<CustomCursor> for Cursor {
+    #[inline]
+    fn from(cursor: CustomCursor) -> Cursor {
+        Cursor::Custom(cursor)
+    }
+}
+
+impl<'a> From<&'a CustomCursor> for Cursor {
+    #[inline]
+    fn from(cursor: &'a CustomCursor) -> Cursor {
+        Cursor::Custom(cursor.clone())
+    }
+}
+
+/// How a window holds on to the cursor. Windows only hold the cursor while they have focus, and
+/// take it back when they get focus again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrabMode {
+    /// The cursor moves freely
+    None,
+    /// The cursor can't leave the window's client area
+    Confined,
+    /// The cursor is hidden and stays where it is. The mouse's movement is still reported through
+    /// `MouseMotion` events, which is what first-person camera controls want.
+    Locked
+}
+
+/// A rectangle in a window's client area
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32
+}
+
+/// A unique identifier for a window. Events from an `EventsLoop` are tagged with one of these to
+/// say which window they came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WindowId(usize);
+
 #[derive(Clone)]
 pub enum WindowType<'p> {
     /// An owned window. This type of window is always drawn on top of it's owner.
@@ -0,0 +1,90 @@
+use api::glx;
+use api::egl;
+use api::backend::NativeWindow;
+use error::{TubResult, GlCreationError, GlCreationResult};
+use config::{PixelFormat, ContextConfig, GlApi};
+use super::Window;
+
+pub struct GlContext<'w, 'c> ( GlContextInner<'w, 'c> );
+
+enum GlContextInner<'w, 'c> {
+    Glx(glx::GlContext<'w, 'c>),
+    Egl(egl::GlContext<'w, 'c>)
+}
+
+impl<'w, 'c> GlContext<'w, 'c> {
+    pub fn new(window: &'w Window, shared_context: Option<&'c GlContext>) -> GlCreationResult<GlContext<'w, 'c>> {
+        GlContext::new_with_config(window, shared_context, Default::default())
+    }
+
+    /// Create a context with a specific API and version. OpenGL ES contexts, and contexts with
+    /// `ContextConfig::egl` set, are created through EGL; everything else goes through GLX.
+    #[allow(unreachable_patterns)]
+    pub fn new_with_config(window: &'w Window, shared_context: Option<&'c GlContext>, context_config: ContextConfig) -> GlCreationResult<GlContext<'w, 'c>> {
+        use self::GlContextInner::*;
+
+        let window = match window.native() {
+            NativeWindow::X11(w) => w,
+            _                    => return Err(GlCreationError::UnsupportedBackendError)
+        };
+
+        let use_egl = context_config.egl || context_config.api == GlApi::Gles;
+
+        // Contexts can only share resources with contexts from the same backend
+        let inner = match (use_egl, shared_context.map(|c| &c.0)) {
+            (false, None)               => Glx(try!(glx::GlContext::new(window, None, &context_config))),
+            (false, Some(&Glx(ref c)))  => Glx(try!(glx::GlContext::new(window, Some(c), &context_config))),
+            (true, None)                => Egl(try!(egl::GlContext::new(window, None, &context_config))),
+            (true, Some(&Egl(ref c)))   => Egl(try!(egl::GlContext::new(window, Some(c), &context_config))),
+            _                           => return Err(GlCreationError::SharedContextError)
+        };
+
+        Ok(GlContext(inner))
+    }
+
+    pub unsafe fn make_current(&self) -> TubResult<()> {
+        match self.0 {
+            GlContextInner::Glx(ref c) => c.make_current(),
+            GlContextInner::Egl(ref c) => c.make_current()
+        }
+    }
+
+    pub fn get_proc_address(&self, proc_name: &str) -> *const () {
+        match self.0 {
+            GlContextInner::Glx(ref c) => c.get_proc_address(proc_name),
+            GlContextInner::Egl(ref c) => c.get_proc_address(proc_name)
+        }
+    }
+
+    pub fn swap_buffers(&self) {
+        match self.0 {
+            GlContextInner::Glx(ref c) => c.swap_buffers(),
+            GlContextInner::Egl(ref c) => c.swap_buffers()
+        }
+    }
+}
+
+/// An OpenGL context that isn't attached to any window, created through EGL. On Mesa, this works
+/// without any display server, which makes it useful for rendering on headless machines.
+pub struct HeadlessContext<'c> ( egl::GlContext<'static, 'c> );
+
+impl<'c> HeadlessContext<'c> {
+    /// Create a headless context. If `size` is `Some`, the context gets a default framebuffer of that
+    /// size. Otherwise, the context is surfaceless and everything has to be drawn into framebuffer
+    /// objects.
+    pub fn new(size: Option<(u32, u32)>, pixel_format: PixelFormat, context_config: ContextConfig, shared_context: Option<&'c HeadlessContext>) -> GlCreationResult<HeadlessContext<'c>> {
+        egl::GlContext::new_headless(size, &pixel_format, shared_context.map(|c| &c.0), &context_config).map(|c| HeadlessContext(c))
+    }
+
+    pub unsafe fn make_current(&self) -> TubResult<()> {
+        self.0.make_current()
+    }
+
+    pub fn get_proc_address(&self, proc_name: &str) -> *const () {
+        self.0.get_proc_address(proc_name)
+    }
+
+    pub fn swap_buffers(&self) {
+        self.0.swap_buffers()
+    }
+}
@@ -1,4 +1,773 @@
+#[cfg(any(all(windows, feature = "win32"), all(target_os = "linux", feature = "x11")))]
 pub use self::platform::*;
 
+#[cfg(all(windows, feature = "win32"))]
 #[path="windows.rs"]
-mod platform;
\ No newline at end of file
+mod platform;
+
+#[cfg(all(target_os = "linux", feature = "x11"))]
+#[path="linux.rs"]
+mod platform;
+
+mod soft_buffer;
+pub use self::soft_buffer::SoftBuffer;
+
+#[cfg(feature = "osmesa")]
+pub use api::osmesa::OsMesaContext;
+
+use std::env;
+use std::any::Any;
+use std::time::{Duration, Instant};
+use std::sync::mpsc;
+use std::sync::mpsc::{Sender, Receiver};
+use std::cell::RefCell;
+#[cfg(target_os = "linux")]
+use std::cmp;
+#[cfg(target_os = "linux")]
+use std::sync::Arc;
+#[cfg(target_os = "linux")]
+use std::rc::{Rc, Weak};
+
+#[cfg(target_os = "linux")]
+use libc;
+
+use api::backend::{WindowBackend, NativeWindow, EventSink};
+#[cfg(target_os = "linux")]
+use api::backend::{EventPump, Waker};
+#[cfg(all(windows, feature = "win32"))]
+use api::win32;
+#[cfg(all(target_os = "linux", feature = "x11"))]
+use api::x11;
+#[cfg(all(target_os = "linux", feature = "wayland"))]
+use api::wayland;
+#[cfg(feature = "mock")]
+use api::mock as mock_api;
+use error::{TubResult, TubError};
+use config::{WindowConfig, PixelFormat};
+use event::{Event, UserEvent};
+use {Cursor, GrabMode, Rect, WindowType, WindowId};
+
+/// A backend that keeps all window state in memory and never talks to the OS. This is meant for
+/// testing code that uses tub without needing a desktop session.
+#[cfg(feature = "mock")]
+pub mod mock {
+    pub use api::mock::{Window, WindowType};
+}
+
+/// The backends that are tried, in order, when `TUB_BACKEND` isn't set. X11 comes before Wayland
+/// because OpenGL contexts can only be created for X11 windows, and most Wayland sessions can run
+/// X11 programs anyway. The mock backend is never picked unless it's asked for.
+const DEFAULT_BACKENDS: &'static [Backend] = &[Backend::Win32, Backend::X11, Backend::Wayland];
+
+/// The windowing systems that tub can create windows with. Which of these can actually be used
+/// depends on the platform and on the cargo features that tub was built with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Win32,
+    X11,
+    Wayland,
+    /// Windows that only exist in memory. See the `mock` module.
+    Mock
+}
+
+impl Backend {
+    /// Get the backend that `Window::new` creates windows with. If the `TUB_BACKEND` environment
+    /// variable is set to `win32`, `x11`, `wayland` or `mock`, that backend is used. Otherwise, this is the
+    /// first backend that's compiled in and has something to connect to.
+    pub fn from_env() -> TubResult<Backend> {
+        match env::var("TUB_BACKEND") {
+            Ok(name) => {
+                let backend = try!(Backend::from_name(&name).ok_or_else(||
+                    TubError::BackendError(format!("TUB_BACKEND is set to unknown backend \"{}\"", name))));
+
+                match backend.is_compiled() {
+                    true  => Ok(backend),
+                    false => Err(TubError::BackendError(format!("TUB_BACKEND is set to \"{}\", but tub was built without that backend", name)))
+                }
+            }
+
+            Err(_) =>
+                DEFAULT_BACKENDS.iter().cloned().find(|b| b.is_available())
+                    .ok_or(TubError::BackendError("No window backend is available".to_owned()))
+        }
+    }
+
+    /// Get a backend from its name, as used in `TUB_BACKEND`. Names are case-insensitive.
+    pub fn from_name(name: &str) -> Option<Backend> {
+        match &*name.to_lowercase() {
+            "win32" => Some(Backend::Win32),
+            "x11"   => Some(Backend::X11),
+            "wayland" => Some(Backend::Wayland),
+            "mock"  => Some(Backend::Mock),
+            _       => None
+        }
+    }
+
+    /// Whether or not support for the backend was compiled into tub
+    pub fn is_compiled(&self) -> bool {
+        match *self {
+            Backend::Win32 => cfg!(all(windows, feature = "win32")),
+            Backend::X11   => cfg!(all(target_os = "linux", feature = "x11")),
+            Backend::Wayland => cfg!(all(target_os = "linux", feature = "wayland")),
+            Backend::Mock  => cfg!(feature = "mock")
+        }
+    }
+
+    /// Whether or not the backend is compiled in and there's a display server for it to connect to.
+    /// This doesn't try to connect, so window creation can still fail if the display can't be opened.
+    /// The Wayland backend also needs libxkbcommon, which is loaded at runtime.
+    pub fn is_available(&self) -> bool {
+        self.is_compiled() &&
+            match *self {
+                Backend::X11 => env::var_os("DISPLAY").is_some(),
+                #[cfg(all(target_os = "linux", feature = "wayland"))]
+                Backend::Wayland => (env::var_os("WAYLAND_DISPLAY").is_some() || env::var_os("WAYLAND_SOCKET").is_some()) &&
+                                    wayland::is_available(),
+                _            => true
+            }
+    }
+
+    fn new_window(&self, config: WindowConfig, pixel_format: PixelFormat, sink: EventSink) -> TubResult<Box<WindowBackend>> {
+        match *self {
+            #[cfg(all(windows, feature = "win32"))]
+            Backend::Win32 => win32::Window::new(config, pixel_format, sink).map(|w| Box::new(w) as Box<WindowBackend>),
+            #[cfg(all(target_os = "linux", feature = "x11"))]
+            Backend::X11   => x11::Window::new(config, pixel_format, sink).map(|w| Box::new(w) as Box<WindowBackend>),
+            #[cfg(all(target_os = "linux", feature = "wayland"))]
+            Backend::Wayland => wayland::Window::new(config, pixel_format, sink).map(|w| Box::new(w) as Box<WindowBackend>),
+            #[cfg(feature = "mock")]
+            Backend::Mock  => mock_api::Window::new(config, pixel_format, sink).map(|w| Box::new(w) as Box<WindowBackend>),
+            _ => Err(TubError::BackendError(format!("tub was built without the {:?} backend", self)))
+        }
+    }
+}
+
+/// Whether `EventsLoop::run` should keep going after the callback returns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlFlow {
+    Continue,
+    Break
+}
+
+/// Receives the events of every window that was created with it, tagged with the ID of the window
+/// that each one came from. Owned and child windows send their events to the same loop as their
+/// owner.
+///
+/// ```
+/// # use tub::platform::{Window, Backend, EventsLoop};
+/// # use tub::event::Event;
+/// let events_loop = EventsLoop::new().unwrap();
+/// let first = Window::new_with_backend(&events_loop, Backend::Mock, Default::default(), Default::default()).unwrap();
+/// let second = first.new_owned(Default::default(), Default::default()).unwrap();
+/// events_loop.poll_events().count();
+///
+/// second.set_pos(10, 20);
+/// first.set_pos(30, 40);
+/// let events: Vec<_> = events_loop.poll_events().collect();
+/// assert_eq!(events, [(second.id(), Event::Moved(10, 20)), (first.id(), Event::Moved(30, 40))]);
+/// ```
+pub struct EventsLoop {
+    sender: Sender<(WindowId, Event)>,
+    receiver: Receiver<(WindowId, Event)>,
+    /// The windows that have asked to be redrawn since redraws were last sent, in the order that
+    /// they asked
+    redraws: RefCell<Vec<WindowId>>,
+    #[cfg(target_os = "linux")]
+    waker: Arc<Waker>,
+    /// The pumps of any windows whose events have to be read on this thread. These are weak so
+    /// that the loop doesn't keep a backend's connection open after its windows are gone.
+    #[cfg(target_os = "linux")]
+    pumps: RefCell<Vec<Weak<EventPump>>>
+}
+
+impl EventsLoop {
+    #[cfg(not(target_os = "linux"))]
+    pub fn new() -> TubResult<EventsLoop> {
+        let (tx, rx) = mpsc::channel();
+
+        Ok(
+            EventsLoop {
+                sender: tx,
+                receiver: rx,
+                redraws: RefCell::new(Vec::new())
+            }
+        )
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn new() -> TubResult<EventsLoop> {
+        let (tx, rx) = mpsc::channel();
+
+        Ok(
+            EventsLoop {
+                sender: tx,
+                receiver: rx,
+                redraws: RefCell::new(Vec::new()),
+                waker: Arc::new(try!(Waker::new())),
+                pumps: RefCell::new(Vec::new())
+            }
+        )
+    }
+
+    /// Get a non-blocking iterator over the events of all of the loop's windows
+    #[inline]
+    pub fn poll_events(&self) -> PollEventsIter {
+        PollEventsIter {
+            events_loop: self,
+            redraws: RedrawBatch::default()
+        }
+    }
+
+    /// Get a blocking iterator over the events of all of the loop's windows. This never ends on
+    /// its own, even once every window has been closed.
+    #[inline]
+    pub fn wait_events(&self) -> WaitEventsIter {
+        WaitEventsIter {
+            events_loop: self,
+            redraws: RedrawBatch::default()
+        }
+    }
+
+    /// Get an iterator over the events of all of the loop's windows that waits for each event for
+    /// at most `timeout`, measured from when this is called. Once the timeout has passed, the
+    /// iterator returns whatever events are already queued and then ends.
+    #[inline]
+    pub fn wait_events_timeout(&self, timeout: Duration) -> WaitEventsUntilIter {
+        self.wait_events_until(Instant::now() + timeout)
+    }
+
+    /// Get an iterator over the events of all of the loop's windows that waits for each event until
+    /// `deadline`. Each event is returned as soon as it arrives, so this can be used to handle
+    /// input while waiting for the next frame of an animation:
+    ///
+    /// ```
+    /// # use tub::platform::EventsLoop;
+    /// # use std::time::{Duration, Instant};
+    /// let events_loop = EventsLoop::new().unwrap();
+    /// let mut next_frame = Instant::now();
+    ///
+    /// for _ in 0..3 {
+    ///     next_frame += Duration::from_millis(16);
+    ///     for (id, event) in events_loop.wait_events_until(next_frame) {
+    ///         // Handle input
+    ///     }
+    ///     // Draw the next frame
+    /// }
+    /// assert!(Instant::now() >= next_frame);
+    /// ```
+    #[inline]
+    pub fn wait_events_until(&self, deadline: Instant) -> WaitEventsUntilIter {
+        WaitEventsUntilIter {
+            events_loop: self,
+            deadline: deadline,
+            redraws: RedrawBatch::default()
+        }
+    }
+
+    /// Wait for events and call `callback` with each one, until it returns `ControlFlow::Break`
+    pub fn run<F>(&self, mut callback: F)
+            where F: FnMut(WindowId, Event) -> ControlFlow {
+        for (id, event) in self.wait_events() {
+            if callback(id, event) == ControlFlow::Break {
+                break;
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn new_sink(&self) -> EventSink {
+        EventSink::new(self.sender.clone())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn new_sink(&self) -> EventSink {
+        EventSink::new(self.sender.clone(), self.waker.clone())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn add_window(&self, _: &WindowBackend) {}
+
+    /// Start reading the window's events through its pump, if its backend has one
+    #[cfg(target_os = "linux")]
+    fn add_window(&self, window: &WindowBackend) {
+        if let Some(pump) = window.event_pump() {
+            self.pumps.borrow_mut().push(Rc::downgrade(&pump));
+        }
+    }
+
+    /// Get the next event for an iterator, waiting for one until `deadline`. If there's no
+    /// deadline, this waits forever, and if the deadline has already passed, this doesn't wait at
+    /// all.
+    ///
+    /// `RedrawRequested` events go through the channel like any other event, so that they wake the
+    /// loop up, but they're held back until the loop runs out of other events. Then each window
+    /// that asked gets one redraw. Redraws that are asked for while those are being handled wait
+    /// until the loop runs out of events again, and iterators that can't wait anymore only send
+    /// redraws once, so that redrawing every frame doesn't keep `poll_events` going forever.
+    fn next_event(&self, deadline: Option<Instant>, batch: &mut RedrawBatch) -> Option<(WindowId, Event)> {
+        loop {
+            if let Some(id) = batch.ids.pop() {
+                return Some((id, Event::RedrawRequested));
+            }
+
+            let event = match self.receive_event(Some(Instant::now())) {
+                Some(e) => Some(e),
+                None    => {
+                    let can_wait = time_until(deadline).map_or(true, |t| t.is_some());
+                    let mut redraws = self.redraws.borrow_mut();
+
+                    if !redraws.is_empty() && (can_wait || !batch.sent) {
+                        batch.ids = redraws.drain(..).rev().collect();
+                        batch.sent = true;
+                        continue;
+                    }
+
+                    drop(redraws);
+                    self.receive_event(deadline)
+                }
+            };
+
+            match event {
+                Some((id, Event::RedrawRequested)) => {
+                    let mut redraws = self.redraws.borrow_mut();
+                    if !redraws.contains(&id) {
+                        redraws.push(id);
+                    }
+                }
+
+                event => return event
+            }
+        }
+    }
+
+    /// Get the next event from the loop's channel, waiting for one until `deadline`
+    #[cfg(not(target_os = "linux"))]
+    fn receive_event(&self, deadline: Option<Instant>) -> Option<(WindowId, Event)> {
+        match time_until(deadline) {
+            None                    => self.receiver.recv().ok(),
+            Some(Some(timeout))     => self.receiver.recv_timeout(timeout).ok(),
+            Some(None)              => self.receiver.try_recv().ok()
+        }
+    }
+
+    /// Every backend's event thread sends its events straight to the loop's channel, but backends
+    /// with pumps only get their events when the pump is run. When there are pumps, the loop
+    /// waits on their file descriptors instead of on the channel, along with the waker's pipe so
+    /// that events sent from other threads still wake it up.
+    #[cfg(target_os = "linux")]
+    fn receive_event(&self, deadline: Option<Instant>) -> Option<(WindowId, Event)> {
+        loop {
+            if let Ok(event) = self.receiver.try_recv() {
+                return Some(event);
+            }
+
+            let pumps: Vec<Rc<EventPump>> = {
+                let mut pumps = self.pumps.borrow_mut();
+                pumps.retain(|p| p.upgrade().is_some());
+                pumps.iter().filter_map(|p| p.upgrade()).collect()
+            };
+
+            for pump in &pumps {
+                pump.dispatch_pending();
+            }
+            if let Ok(event) = self.receiver.try_recv() {
+                return Some(event);
+            }
+
+            let timeout = match time_until(deadline) {
+                None          => None,
+                Some(Some(t)) => Some(t),
+                Some(None)    => return None
+            };
+            // Wake up in time for the pumps' own timers as well
+            let timeout = match time_until(pumps.iter().filter_map(|p| p.next_timeout()).min()) {
+                None          => timeout,
+                Some(Some(t)) => Some(timeout.map_or(t, |u| cmp::min(t, u))),
+                Some(None)    => Some(Duration::from_secs(0))
+            };
+            if pumps.is_empty() {
+                return match timeout {
+                    None    => self.receiver.recv().ok(),
+                    Some(t) => self.receiver.recv_timeout(t).ok()
+                };
+            }
+
+            let mut fds: Vec<libc::pollfd> = pumps.iter().map(|p| p.fd())
+                .chain(Some(self.waker.fd()))
+                .map(|fd| libc::pollfd{ fd: fd, events: libc::POLLIN, revents: 0 })
+                .collect();
+
+            self.waker.start_waiting();
+            // Anything that was sent before the waker started waiting wouldn't have woken it up
+            if let Ok(event) = self.receiver.try_recv() {
+                self.waker.stop_waiting();
+                return Some(event);
+            }
+            // Round the timeout up, so that this doesn't wake up just before the deadline and then
+            // spin until it passes
+            let timeout_ms = match timeout {
+                None    => -1,
+                Some(t) => cmp::min(t.as_secs() * 1000 + (t.subsec_nanos() as u64 + 999_999) / 1_000_000,
+                                    libc::c_int::max_value() as u64) as libc::c_int
+            };
+            unsafe{ libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout_ms) };
+            self.waker.stop_waiting();
+        }
+    }
+}
+
+/// The redraws that an events iterator is in the middle of sending
+#[derive(Default)]
+struct RedrawBatch {
+    /// The windows that still have to be sent their redraw, in reverse order
+    ids: Vec<WindowId>,
+    /// Whether the iterator has sent any redraws yet
+    sent: bool
+}
+
+/// Get how long there is until `deadline`. The outer option is `None` when there's no deadline, and
+/// the inner one is `None` when the deadline has passed.
+fn time_until(deadline: Option<Instant>) -> Option<Option<Duration>> {
+    deadline.map(|d| {
+        let now = Instant::now();
+        match d > now {
+            true  => Some(d - now),
+            false => None
+        }
+    })
+}
+
+pub struct Window<'p> {
+    backend: Box<WindowBackend + 'p>,
+    win_type: WindowType<'p>,
+    /// A copy of the sink that the backend sends the window's events through. Owned and child
+    /// windows get sinks for the same loop from this, and soft buffers get the window's size
+    /// from it.
+    sink: EventSink
+}
+
+impl<'p> Window<'p> {
+    /// Create a new window with the backend chosen by `Backend::from_env`
+    pub fn new(events_loop: &EventsLoop, config: WindowConfig, pixel_format: PixelFormat) -> TubResult<Window<'p>> {
+        Window::new_with_backend(events_loop, try!(Backend::from_env()), config, pixel_format)
+    }
+
+    /// Create a new window with a specific backend, ignoring `TUB_BACKEND`.
+    ///
+    /// ```
+    /// # use tub::platform::{Window, Backend, EventsLoop};
+    /// let events_loop = EventsLoop::new().unwrap();
+    /// let window = Window::new_with_backend(&events_loop, Backend::Mock, Default::default(), Default::default()).unwrap();
+    /// assert_eq!(window.get_backend(), Backend::Mock);
+    /// ```
+    pub fn new_with_backend(events_loop: &EventsLoop, backend: Backend, config: WindowConfig, pixel_format: PixelFormat) -> TubResult<Window<'p>> {
+        let sink = events_loop.new_sink();
+        let backend = try!(backend.new_window(config, pixel_format, sink.clone()));
+
+        events_loop.add_window(&*backend);
+        Ok(Window::from_backend(backend, sink, WindowType::Top))
+    }
+
+    /// Creates a window that is owned by the calling window. Owned windows always use the same
+    /// backend and events loop as their owner.
+    pub fn new_owned(&'p self, config: WindowConfig, pixel_format: PixelFormat) -> TubResult<Window<'p>> {
+        let sink = self.sink.sibling();
+        self.backend.new_owned(config, pixel_format, sink.clone()).map(|b| Window::from_backend(b, sink, WindowType::Owned(self)))
+    }
+
+    pub fn new_child(&'p self, config: WindowConfig, pixel_format: PixelFormat) -> TubResult<Window<'p>> {
+        let sink = self.sink.sibling();
+        self.backend.new_child(config, pixel_format, sink.clone()).map(|b| Window::from_backend(b, sink, WindowType::Child(self)))
+    }
+
+    fn from_backend(backend: Box<WindowBackend + 'p>, sink: EventSink, win_type: WindowType<'p>) -> Window<'p> {
+        sink.init_size(backend.get_inner_size().unwrap_or((0, 0)));
+
+        Window {
+            backend: backend,
+            win_type: win_type,
+            sink: sink
+        }
+    }
+
+    /// Get the ID that the window's events are tagged with
+    #[inline]
+    pub fn id(&self) -> WindowId {
+        self.sink.id()
+    }
+
+    /// Get a proxy that other threads can use to send events to the window
+    #[inline]
+    pub fn create_proxy(&self) -> WindowProxy {
+        WindowProxy {
+            sink: self.sink.clone()
+        }
+    }
+
+    #[inline]
+    pub fn set_title(&self, title: &str) {
+        self.backend.set_title(title);
+    }
+
+    #[inline]
+    pub fn show(&self) {
+        self.backend.show();
+    }
+
+    #[inline]
+    pub fn hide(&self) {
+        self.backend.hide();
+    }
+
+    /// Destroy the window, which sends it the `Destroyed` event. Nothing that's done to the window
+    /// afterwards has any effect. Dropping the window also destroys it.
+    #[inline]
+    pub fn close(&self) {
+        self.backend.close();
+    }
+
+    /// Ask for a `RedrawRequested` event. However many times this is called, the window only gets
+    /// one event once the loop has handled its other events, so apps can call this whenever
+    /// something changes and draw in response to the event:
+    ///
+    /// ```
+    /// # use tub::platform::{Window, Backend, EventsLoop};
+    /// # use tub::event::Event;
+    /// let events_loop = EventsLoop::new().unwrap();
+    /// let window = Window::new_with_backend(&events_loop, Backend::Mock, Default::default(), Default::default()).unwrap();
+    ///
+    /// window.request_redraw();
+    /// window.request_redraw();
+    ///
+    /// let redraws = events_loop.poll_events().filter(|&(_, ref e)| *e == Event::RedrawRequested).count();
+    /// assert_eq!(redraws, 1);
+    /// ```
+    #[inline]
+    pub fn request_redraw(&self) {
+        self.sink.send(Event::RedrawRequested);
+    }
+
+    /// Allow the window to take user input. Any newly created window defaults to
+    /// being enabled.
+    #[inline]
+    pub fn enable(&self) {
+        self.backend.enable();
+    }
+
+    /// Disallow the window from taking user input.
+    #[inline]
+    pub fn disable(&self) {
+        self.backend.disable();
+    }
+
+    /// Sets input focus to this window
+    #[inline]
+    pub fn focus(&self) {
+        self.backend.focus();
+    }
+
+    #[inline]
+    pub fn get_inner_pos(&self) -> Option<(i32, i32)> {
+        self.backend.get_inner_pos()
+    }
+
+    /// Gets the position of the upper-left corner of the window, including the title bar
+    #[inline]
+    pub fn get_outer_pos(&self) -> Option<(i32, i32)> {
+        self.backend.get_outer_pos()
+    }
+
+    #[inline]
+    pub fn get_inner_size(&self) -> Option<(u32, u32)> {
+        self.backend.get_inner_size()
+    }
+
+    #[inline]
+    pub fn get_outer_size(&self) -> Option<(u32, u32)> {
+        self.backend.get_outer_size()
+    }
+
+    #[inline]
+    pub fn set_pos(&self, x: i32, y: i32) -> Option<()> {
+        self.backend.set_pos(x, y)
+    }
+
+    #[inline]
+    pub fn set_inner_size(&self, x: u32, y: u32) -> Option<()> {
+        self.backend.set_inner_size(x, y)
+    }
+
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        self.backend.is_active()
+    }
+
+    /// Set the cursor that's shown while the pointer is over the window. This takes either a
+    /// `CursorType` or a `CustomCursor`.
+    #[inline]
+    pub fn set_cursor<C: Into<Cursor>>(&self, cursor: C) {
+        self.backend.set_cursor(&cursor.into());
+    }
+
+    #[inline]
+    pub fn set_cursor_pos(&self, x: i32, y: i32) {
+        self.backend.set_cursor_pos(x, y);
+    }
+
+    /// Confine or lock the cursor to the window while it has focus. Returns `None` if the backend
+    /// can't grab the cursor, which is currently the case on Wayland.
+    #[inline]
+    pub fn set_cursor_grab(&self, mode: GrabMode) -> Option<()> {
+        self.backend.set_cursor_grab(mode)
+    }
+
+    /// Keep the cursor inside part of the window's client area while the window has focus, or let
+    /// it move freely again if `rect` is `None`. This replaces the grab from `set_cursor_grab`, and
+    /// confining the cursor to the whole client area is the same as `GrabMode::Confined`. Returns
+    /// `None` if the backend can't confine the cursor.
+    #[inline]
+    pub fn confine_cursor(&self, rect: Option<Rect>) -> Option<()> {
+        self.backend.confine_cursor(rect)
+    }
+
+    /// Tell the input method where the text cursor is, relative to window space, so that it can
+    /// put its candidate window next to it.
+    #[inline]
+    pub fn set_ime_position(&self, x: i32, y: i32) {
+        self.backend.set_ime_position(x, y);
+    }
+
+    #[inline]
+    pub fn get_type(&self) -> WindowType {
+        self.win_type.clone()
+    }
+
+    /// Get the backend that the window was created with
+    #[inline]
+    pub fn get_backend(&self) -> Backend {
+        match self.backend.native() {
+            #[cfg(all(windows, feature = "win32"))]
+            NativeWindow::Win32(_) => Backend::Win32,
+            #[cfg(all(target_os = "linux", feature = "x11"))]
+            NativeWindow::X11(_)   => Backend::X11,
+            #[cfg(all(target_os = "linux", feature = "wayland"))]
+            NativeWindow::Wayland(_) => Backend::Wayland,
+            #[cfg(feature = "mock")]
+            NativeWindow::Mock(_)  => Backend::Mock
+        }
+    }
+
+    /// If the window was created with the mock backend, get the underlying mock window. This
+    /// can be used to push events into windows that were created through `Window::new` with
+    /// `TUB_BACKEND=mock`.
+    #[cfg(feature = "mock")]
+    #[allow(unreachable_patterns)]
+    pub fn as_mock(&self) -> Option<&mock::Window> {
+        match self.backend.native() {
+            NativeWindow::Mock(w) => Some(w),
+            _                     => None
+        }
+    }
+
+    #[inline]
+    pub fn get_config(&self) -> &WindowConfig {
+        self.backend.get_config()
+    }
+
+    #[inline]
+    pub fn get_pixel_format(&self) -> &PixelFormat {
+        self.backend.get_pixel_format()
+    }
+
+    /// Used by the OpenGL contexts and soft buffers to get at the backend's window
+    #[inline]
+    fn native(&self) -> NativeWindow {
+        self.backend.native()
+    }
+}
+
+/// Sends events to a window from any thread, which wakes up the window's events loop if it's
+/// waiting. This lets background work like loading assets tell the main loop when it's done.
+///
+/// ```
+/// # use tub::platform::{Window, Backend, EventsLoop};
+/// # use tub::event::Event;
+/// # use std::thread;
+/// let events_loop = EventsLoop::new().unwrap();
+/// let window = Window::new_with_backend(&events_loop, Backend::Mock, Default::default(), Default::default()).unwrap();
+/// events_loop.poll_events().count();
+///
+/// let proxy = window.create_proxy();
+/// thread::spawn(move || proxy.send_user_event("Loaded"));
+///
+/// let (id, event) = events_loop.wait_events().next().unwrap();
+/// assert_eq!(id, window.id());
+/// match event {
+///     Event::User(e) => assert_eq!(e.downcast_ref::<&str>(), Some(&"Loaded")),
+///     _              => panic!("Expected a user event")
+/// }
+/// ```
+#[derive(Clone)]
+pub struct WindowProxy {
+    sink: EventSink
+}
+
+impl WindowProxy {
+    /// Send an `Awakened` event to the window
+    #[inline]
+    pub fn wakeup(&self) {
+        self.sink.send(Event::Awakened);
+    }
+
+    /// Send a `User` event holding `value` to the window
+    #[inline]
+    pub fn send_user_event<T: Any + Send + Sync>(&self, value: T) {
+        self.sink.send(Event::User(UserEvent::new(value)));
+    }
+
+    /// Get the ID of the window that the proxy sends events to
+    #[inline]
+    pub fn id(&self) -> WindowId {
+        self.sink.id()
+    }
+}
+
+pub struct PollEventsIter<'l> {
+    events_loop: &'l EventsLoop,
+    redraws: RedrawBatch
+}
+
+impl<'l> Iterator for PollEventsIter<'l> {
+    type Item = (WindowId, Event);
+
+    fn next(&mut self) -> Option<(WindowId, Event)> {
+        self.events_loop.next_event(Some(Instant::now()), &mut self.redraws)
+    }
+}
+
+pub struct WaitEventsIter<'l> {
+    events_loop: &'l EventsLoop,
+    redraws: RedrawBatch
+}
+
+impl<'l> Iterator for WaitEventsIter<'l> {
+    type Item = (WindowId, Event);
+
+    fn next(&mut self) -> Option<(WindowId, Event)> {
+        self.events_loop.next_event(None, &mut self.redraws)
+    }
+}
+
+pub struct WaitEventsUntilIter<'l> {
+    events_loop: &'l EventsLoop,
+    deadline: Instant,
+    redraws: RedrawBatch
+}
+
+impl<'l> Iterator for WaitEventsUntilIter<'l> {
+    type Item = (WindowId, Event);
+
+    fn next(&mut self) -> Option<(WindowId, Event)> {
+        self.events_loop.next_event(Some(self.deadline), &mut self.redraws)
+    }
+}
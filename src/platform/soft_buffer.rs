@@ -0,0 +1,131 @@
+use api::backend::NativeWindow;
+#[cfg(all(windows, feature = "win32"))]
+use api::win32;
+#[cfg(all(target_os = "linux", feature = "x11"))]
+use api::x11;
+#[cfg(all(target_os = "linux", feature = "wayland"))]
+use api::wayland;
+#[cfg(feature = "mock")]
+use api::mock;
+use error::TubResult;
+use super::Window;
+
+/// A buffer of pixels that's drawn to a window by the CPU, for programs that don't need OpenGL.
+/// Each pixel is a `u32` in `0xAARRGGBB` form, stored in rows from the top-left corner of the
+/// window. Alpha is only used by windows that were created with `transparent` set, and the color
+/// channels have to be premultiplied by it.
+///
+/// The buffer always matches the size from the last `Resized` event sent by the window. When one
+/// comes in with a new size, the buffer is resized and cleared the next time that it's drawn to.
+///
+/// ```
+/// # use tub::platform::{Window, Backend, EventsLoop, SoftBuffer};
+/// # use tub::config::WindowConfig;
+/// let events_loop = EventsLoop::new().unwrap();
+/// let window = Window::new_with_backend(&events_loop, Backend::Mock, WindowConfig::new().size(Some((640, 480))), Default::default()).unwrap();
+///
+/// let mut buffer = SoftBuffer::new(&window).unwrap();
+/// for pixel in buffer.pixels_mut() {
+///     *pixel = 0xFFFF0000;
+/// }
+/// buffer.present().unwrap();
+///
+/// window.set_inner_size(320, 240);
+/// assert_eq!(buffer.pixels_mut().len(), 320 * 240);
+/// ```
+pub struct SoftBuffer<'w> {
+    window: &'w Window<'w>,
+    size: (u32, u32),
+    inner: SoftBufferInner<'w>
+}
+
+enum SoftBufferInner<'w> {
+    #[cfg(all(windows, feature = "win32"))]
+    Win32(win32::soft_buffer::SoftBuffer<'w>),
+    #[cfg(all(target_os = "linux", feature = "x11"))]
+    X11(x11::soft_buffer::SoftBuffer<'w>),
+    #[cfg(all(target_os = "linux", feature = "wayland"))]
+    Wayland(wayland::soft_buffer::SoftBuffer<'w>),
+    #[cfg(feature = "mock")]
+    Mock(mock::SoftBuffer<'w>)
+}
+
+impl<'w> SoftBuffer<'w> {
+    pub fn new(window: &'w Window<'w>) -> TubResult<SoftBuffer<'w>> {
+        use self::SoftBufferInner::*;
+
+        let inner = match window.native() {
+            #[cfg(all(windows, feature = "win32"))]
+            NativeWindow::Win32(w)   => Win32(win32::soft_buffer::SoftBuffer::new(w)),
+            #[cfg(all(target_os = "linux", feature = "x11"))]
+            NativeWindow::X11(w)     => X11(try!(x11::soft_buffer::SoftBuffer::new(w))),
+            #[cfg(all(target_os = "linux", feature = "wayland"))]
+            NativeWindow::Wayland(w) => Wayland(try!(wayland::soft_buffer::SoftBuffer::new(w))),
+            #[cfg(feature = "mock")]
+            NativeWindow::Mock(w)    => Mock(mock::SoftBuffer::new(w))
+        };
+
+        Ok(
+            SoftBuffer {
+                window: window,
+                size: (0, 0),
+                inner: inner
+            }
+        )
+    }
+
+    /// Get the size of the buffer, which is the size that the window had in the last `Resized`
+    /// event that it sent
+    #[inline]
+    pub fn get_size(&self) -> (u32, u32) {
+        self.window.sink.last_size()
+    }
+
+    /// Get the buffer's pixels, resizing the buffer first if the window's size has changed
+    pub fn pixels_mut(&mut self) -> &mut [u32] {
+        use self::SoftBufferInner::*;
+
+        let size = self.window.sink.last_size();
+        if size != self.size {
+            self.size = size;
+
+            match self.inner {
+                #[cfg(all(windows, feature = "win32"))]
+                Win32(ref mut b)   => b.resize(size.0, size.1),
+                #[cfg(all(target_os = "linux", feature = "x11"))]
+                X11(ref mut b)     => b.resize(size.0, size.1),
+                #[cfg(all(target_os = "linux", feature = "wayland"))]
+                Wayland(ref mut b) => b.resize(size.0, size.1),
+                #[cfg(feature = "mock")]
+                Mock(ref mut b)    => b.resize(size.0, size.1)
+            }
+        }
+
+        match self.inner {
+            #[cfg(all(windows, feature = "win32"))]
+            Win32(ref mut b)   => b.pixels_mut(),
+            #[cfg(all(target_os = "linux", feature = "x11"))]
+            X11(ref mut b)     => b.pixels_mut(),
+            #[cfg(all(target_os = "linux", feature = "wayland"))]
+            Wayland(ref mut b) => b.pixels_mut(),
+            #[cfg(feature = "mock")]
+            Mock(ref mut b)    => b.pixels_mut()
+        }
+    }
+
+    /// Draw the buffer's pixels to the window
+    pub fn present(&mut self) -> TubResult<()> {
+        use self::SoftBufferInner::*;
+
+        match self.inner {
+            #[cfg(all(windows, feature = "win32"))]
+            Win32(ref b)       => { b.present(); Ok(()) }
+            #[cfg(all(target_os = "linux", feature = "x11"))]
+            X11(ref b)         => { b.present(); Ok(()) }
+            #[cfg(all(target_os = "linux", feature = "wayland"))]
+            Wayland(ref mut b) => b.present(),
+            #[cfg(feature = "mock")]
+            Mock(_)            => Ok(())
+        }
+    }
+}